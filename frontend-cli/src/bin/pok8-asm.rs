@@ -0,0 +1,59 @@
+// Builds a ROM out of assembly source, see pok_8_core::asm for the syntax
+use pok_8_core::asm::assemble_file;
+
+use std::path::{Path, PathBuf};
+use std::{env, fs, process::exit};
+
+fn main() {
+    let args: Vec<_> = env::args().skip(1).collect();
+
+    let help_keyword = "help".to_string();
+    if args.contains(&help_keyword) {
+        usage();
+        exit(0);
+    }
+
+    let mut output = None;
+    let mut positional = Vec::new();
+
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-o" => {
+                output = Some(PathBuf::from(args.next().unwrap_or_else(|| {
+                    println!("ERROR:  -o needs a file name");
+                    usage();
+                    exit(1);
+                })));
+            }
+
+            _ => positional.push(arg),
+        }
+    }
+
+    if positional.len() != 1 {
+        println!("ERROR:  Error parsing cli arguments");
+        usage();
+        exit(1);
+    }
+
+    let source = Path::new(&positional[0]);
+    let output = output.unwrap_or_else(|| source.with_extension("ch8"));
+
+    let rom = assemble_file(source).unwrap_or_else(|err| {
+        println!("ERROR:  {}", err);
+        exit(1);
+    });
+
+    if let Err(err) = fs::write(&output, &rom) {
+        println!("ERROR:  Could not write '{}': {}", output.display(), err);
+        exit(1);
+    }
+    println!("{} bytes written to {}", rom.len(), output.display());
+}
+
+fn usage() {
+    println!("USAGE:  pok8-asm path/to/source.asm [-o path/to/game]");
+    println!();
+    println!("Without -o the ROM is written next to the source with a .ch8 extension");
+}
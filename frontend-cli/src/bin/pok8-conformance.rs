@@ -0,0 +1,117 @@
+// Builds the self-checking conformance ROM for each quirk profile and runs it under that profile, to validate the
+// core. The ROMs can also be written out to run anywhere else, they report on screen as well as in RAM
+use pok_8_core::conformance;
+use pok_8_core::quirks::Quirks;
+
+use std::path::Path;
+use std::{env, fs, process::exit};
+
+fn main() {
+    let args: Vec<_> = env::args().skip(1).collect();
+
+    let help_keyword = "help".to_string();
+    if args.contains(&help_keyword) {
+        usage();
+        exit(0);
+    }
+
+    let mut profiles: Vec<&str> = Vec::new();
+    let mut out = None;
+    let mut verbose = false;
+
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--quirks" => {
+                let name = args.next().unwrap_or_default();
+                match Quirks::PRESETS.iter().find(|preset| **preset == name) {
+                    Some(preset) => profiles.push(preset),
+                    None => {
+                        println!("ERROR:  Unknown quirk profile '{}'", name);
+                        usage();
+                        exit(1);
+                    }
+                }
+            }
+
+            "--out" => {
+                out = Some(args.next().unwrap_or_else(|| {
+                    println!("ERROR:  --out needs a directory");
+                    usage();
+                    exit(1);
+                }));
+            }
+
+            "--verbose" => verbose = true,
+
+            _ => {
+                println!("ERROR:  Unknown argument '{}'", arg);
+                usage();
+                exit(1);
+            }
+        }
+    }
+
+    if profiles.is_empty() {
+        profiles = Quirks::PRESETS.to_vec();
+    }
+
+    let mut failed = 0;
+    for name in profiles {
+        let quirks = Quirks::from_name(name).unwrap();
+
+        if let Some(dir) = &out {
+            write(dir, name, &quirks);
+        }
+
+        let results = conformance::run(&quirks).unwrap_or_else(|err| {
+            println!("ERROR:  {} stopped: {}", name, err);
+            exit(1);
+        });
+
+        let passed = results.iter().filter(|result| result.passed == Some(true)).count();
+        println!("{:<8} {}/{} passed", name, passed, results.len());
+
+        for result in &results {
+            match result.passed {
+                Some(true) if verbose => println!("  ok    {}", result.name),
+                Some(true) => (),
+                Some(false) => println!("  FAIL  {}", result.name),
+                None => println!("  FAIL  {} (never ran)", result.name),
+            }
+        }
+        failed += results.len() - passed;
+    }
+
+    if failed > 0 {
+        exit(1);
+    }
+}
+
+// conformance-<profile>.ch8 and the source it came from next to it
+fn write(dir: &str, name: &str, quirks: &Quirks) {
+    let base = Path::new(dir).join(format!("conformance-{}", name));
+
+    for (path, contents) in [
+        (base.with_extension("ch8"), conformance::rom(quirks)),
+        (base.with_extension("asm"), conformance::source(quirks).into_bytes()),
+    ] {
+        fs::write(&path, contents).unwrap_or_else(|err| {
+            println!("ERROR:  Could not write '{}': {}", path.display(), err);
+            exit(1);
+        });
+    }
+}
+
+fn usage() {
+    println!("USAGE:  pok8-conformance");
+    println!();
+    println!("OPTIONS:");
+    println!("  --quirks <profile>   only this profile, can be given more than once (default all of them)");
+    println!("  --out <dir>          also write each profile's ROM and source, as conformance-<profile>.ch8/.asm");
+    println!("  --verbose            list the cases that passed too");
+    println!();
+    println!("Profiles: {}", Quirks::PRESETS.join(", "));
+    println!("Each ROM expects its own profile's behaviour and key {:X} held down. It draws a tick or a cross per", conformance::HELD_KEY);
+    println!("case, in the order --verbose lists them, and the number of failures in the bottom left");
+}
@@ -0,0 +1,169 @@
+// Runs a ROM without a window, once plain and once per movie, adds up what ran and writes coverage reports
+use frontend_cli::*;
+use pok_8_core::coverage::Coverage;
+use pok_8_core::emu::*;
+use pok_8_core::movie::Movie;
+
+use std::path::Path;
+use std::{env, fs, process::exit};
+
+const DEFAULT_FRAMES: usize = 600; // 10 seconds
+const ROM_ADDR: u16 = 0x200; // where Emu::load puts it
+
+fn main() {
+    let args: Vec<_> = env::args().skip(1).collect();
+
+    let help_keyword = "help".to_string();
+    if args.contains(&help_keyword) {
+        usage();
+        exit(0);
+    }
+
+    let mut config = EmuConfig::default();
+    let mut frames = None;
+    let mut movies = Vec::new();
+    let mut merges = Vec::new();
+    let mut save = None;
+    let mut annotate = None;
+    let mut lcov = None;
+    let mut positional = Vec::new();
+
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--frames" => {
+                let text = args.next().unwrap_or_default();
+                frames = Some(text.parse::<usize>().unwrap_or_else(|_| {
+                    println!("ERROR:  Could not parse frames '{}'", text);
+                    usage();
+                    exit(1);
+                }));
+            }
+
+            "--play" | "--merge" | "--save" | "--annotate" | "--lcov" => {
+                let path = args.next().unwrap_or_else(|| {
+                    println!("ERROR:  {} needs a file", arg);
+                    usage();
+                    exit(1);
+                });
+
+                match arg.as_str() {
+                    "--play" => movies.push(path),
+                    "--merge" => merges.push(path),
+                    "--save" => save = Some(path),
+                    "--annotate" => annotate = Some(path),
+                    _ => lcov = Some(path),
+                }
+            }
+
+            _ if parse_emu_option(&arg, &mut args, &mut config, usage) => {}
+
+            _ => positional.push(arg),
+        }
+    }
+
+    if positional.len() != 1 {
+        println!("ERROR:  Error parsing cli arguments");
+        usage();
+        exit(1);
+    }
+
+    let rom = read_rom(&positional[0]);
+
+    let mut coverage = Coverage::new();
+
+    for path in &merges {
+        let saved = fs::read_to_string(path)
+            .map_err(|err| err.to_string())
+            .and_then(|text| Coverage::from_text(&text, &rom).map_err(|err| err.to_string()))
+            .unwrap_or_else(|err| {
+                println!("ERROR:  Could not read coverage '{}': {}", path, err);
+                exit(1);
+            });
+        coverage.merge(&saved);
+    }
+
+    // the plain run only happens when asked for, or when there's nothing else to go on
+    if frames.is_some() || (movies.is_empty() && merges.is_empty()) {
+        let emu = start_emu(&rom, config);
+        coverage.merge(&run(emu, frames.unwrap_or(DEFAULT_FRAMES), None));
+    }
+
+    // a movie brings its own quirks, seed and keypad, and runs for as long as it's recorded
+    for path in &movies {
+        let movie = fs::read(path)
+            .map_err(|err| err.to_string())
+            .and_then(|data| Movie::from_bytes(&data).map_err(|err| err.to_string()))
+            .unwrap_or_else(|err| {
+                println!("ERROR:  Could not read movie '{}': {}", path, err);
+                exit(1);
+            });
+        let emu = movie.start(&rom).unwrap_or_else(|err| {
+            println!("ERROR:  Movie '{}': {}", path, err);
+            exit(1);
+        });
+        coverage.merge(&run(emu, movie.frames.len(), Some(&movie)));
+    }
+
+    if let Some(path) = &save {
+        write(path, &coverage.to_text(&rom));
+    }
+    if let Some(path) = &annotate {
+        write(path, &coverage.annotate(&rom, ROM_ADDR));
+    }
+
+    // the tracefile points at a listing written next to it, genhtml reads the source from there
+    if let Some(path) = &lcov {
+        let listing = Path::new(path).with_extension("asm");
+        write(&listing.to_string_lossy(), &Coverage::listing(&rom, ROM_ADDR));
+        write(path, &coverage.lcov(&rom, ROM_ADDR, &listing.to_string_lossy()));
+    }
+
+    if save.is_none() && annotate.is_none() && lcov.is_none() {
+        print!("{}", coverage.annotate(&rom, ROM_ADDR));
+        println!();
+    }
+    print!("{}", coverage.summary(&rom, ROM_ADDR));
+}
+
+// Coverage of a single run, up to an error or the ROM halting if either comes first
+fn run(mut emu: Emu, frames: usize, movie: Option<&Movie>) -> Coverage {
+    emu.set_coverage(Coverage::new());
+
+    for frame in 0..frames {
+        if let Some(movie) = movie {
+            movie.apply(&mut emu, frame);
+        }
+
+        if let Err(err) = emu.run_frame() {
+            println!("WARNING:  Stopped at frame {}: {}", frame, err);
+            break;
+        }
+        if emu.is_halted() {
+            break;
+        }
+    }
+
+    emu.take_coverage().unwrap_or_default()
+}
+
+fn write(path: &str, contents: &str) {
+    fs::write(path, contents).unwrap_or_else(|err| {
+        println!("ERROR:  Could not write '{}': {}", path, err);
+        exit(1);
+    });
+}
+
+fn usage() {
+    println!("USAGE:  pok8-cov path/to/game");
+    println!();
+    println!("OPTIONS:");
+    println!("  --frames <n>         frames for a plain run (default {}), which only happens", DEFAULT_FRAMES);
+    println!("                       with --play or --merge if this is given");
+    println!("  --play <movie>       add a run through a recorded movie, can be given more than once");
+    println!("  --merge <file>       add coverage saved by --save, can be given more than once");
+    println!("  --save <file>        save the combined coverage for a later --merge");
+    println!("  --annotate <file>    write the disassembly with execution counts, printed when nothing else is written");
+    println!("  --lcov <file>        write an lcov tracefile, and the listing it refers to alongside as .asm");
+    emu_options_usage(None);
+}
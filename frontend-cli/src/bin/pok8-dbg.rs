@@ -0,0 +1,449 @@
+// A gdb-like prompt over the core Debugger. Nothing is drawn to a window, so it works over SSH
+use frontend_cli::*;
+use pok_8_core::debugger::*;
+use pok_8_core::disasm::{disassemble, Line};
+use pok_8_core::emu::*;
+use pok_8_core::instruction::Reg;
+
+use std::io::{self, BufRead, Write};
+use std::{env, process::exit};
+
+const RUN_LIMIT: u64 = 10_000_000; // instructions next/finish/continue run before handing back the prompt anyway
+const DISAS_LINES: usize = 10;
+
+fn main() {
+    let args: Vec<_> = env::args().skip(1).collect();
+
+    let help_keyword = "help".to_string();
+    if args.contains(&help_keyword) {
+        usage();
+        exit(0);
+    }
+
+    let mut config = EmuConfig::default();
+    let mut positional = Vec::new();
+
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            _ if parse_emu_option(&arg, &mut args, &mut config, usage) => {}
+
+            _ => positional.push(arg),
+        }
+    }
+
+    if positional.len() != 1 {
+        println!("ERROR:  Error parsing cli arguments");
+        usage();
+        exit(1);
+    }
+
+    let rom = read_rom(&positional[0]);
+    let emu = start_emu(&rom, config);
+
+    let mut dbg = Debugger::new(emu);
+    println!("loaded {} ({} bytes), type help for the commands", positional[0], rom.len());
+    print_location(&dbg);
+
+    // an empty line repeats the last command, like gdb
+    let mut last = String::new();
+    let mut lines = io::stdin().lock().lines();
+
+    loop {
+        print!("(pok8) ");
+        io::stdout().flush().ok();
+
+        let line = match lines.next() {
+            Some(Ok(line)) => line,
+            _ => break,
+        };
+        let line = if line.trim().is_empty() { last.clone() } else { line };
+
+        let words: Vec<&str> = line.split_whitespace().collect();
+        if words.is_empty() {
+            continue;
+        }
+        if matches!(words[0], "quit" | "q") {
+            break;
+        }
+
+        if let Err(err) = command(&mut dbg, &words) {
+            println!("ERROR:  {}", err);
+        }
+        last = line;
+    }
+}
+
+fn command(dbg: &mut Debugger, words: &[&str]) -> Result<(), String> {
+    let args = &words[1..];
+
+    match words[0] {
+        "break" | "b" => break_command(dbg, args),
+        "watch" | "w" => watch_command(dbg, args),
+        "delete" | "d" => delete_command(dbg, args),
+
+        "step" | "s" => {
+            let count = args.first().map(|text| parse_number(text)).transpose()?.unwrap_or(1);
+            let mut reason = StopReason::Step;
+
+            for _ in 0..count {
+                reason = dbg.step();
+                if reason != StopReason::Step {
+                    break;
+                }
+            }
+            report(dbg, reason);
+            Ok(())
+        }
+
+        "next" | "n" => {
+            let reason = dbg.step_over(RUN_LIMIT);
+            report(dbg, reason);
+            Ok(())
+        }
+
+        "finish" | "f" => {
+            let reason = dbg.step_out(RUN_LIMIT);
+            report(dbg, reason);
+            Ok(())
+        }
+
+        "continue" | "c" => {
+            let limit = args.first().map(|text| parse_number(text)).transpose()?.unwrap_or(RUN_LIMIT as u32);
+            let reason = dbg.run_until_break(limit as u64);
+            report(dbg, reason);
+            Ok(())
+        }
+
+        "regs" | "r" => {
+            print_regs(dbg.emu());
+            Ok(())
+        }
+
+        "mem" | "x" => {
+            let addr = args.first().ok_or("mem needs an address")?;
+            let len = args.get(1).map(|text| parse_number(text)).transpose()?.unwrap_or(16);
+            print_mem(dbg.emu(), parse_number(addr)? as usize, len as usize);
+            Ok(())
+        }
+
+        "disas" => {
+            let addr = args.first().map(|text| parse_addr(text)).transpose()?.unwrap_or(dbg.emu().pc());
+            let count = args.get(1).map(|text| parse_number(text)).transpose()?.unwrap_or(DISAS_LINES as u32);
+
+            for line in lines_at(dbg.emu(), addr, count as usize) {
+                let marker = if line.addr == dbg.emu().pc() { "=>" } else { "  " };
+                let bp = if dbg.breakpoints().iter().any(|bp| bp.addr == line.addr) { "*" } else { " " };
+                println!("{}{} {}", marker, bp, line);
+            }
+            Ok(())
+        }
+
+        "set" => set_command(dbg, args),
+
+        "bt" => {
+            let emu = dbg.emu();
+            println!("#0  {}", describe(emu, emu.pc()));
+
+            // each return address sits just past the CALL that pushed it
+            for (depth, ret) in emu.stack().iter().rev().enumerate() {
+                println!("#{}  {}", depth + 1, describe(emu, ret.wrapping_sub(2)));
+            }
+            Ok(())
+        }
+
+        "screen" => {
+            print!("{}", dbg.emu().get_display().to_text());
+            Ok(())
+        }
+
+        "key" | "k" => {
+            let Some(key) = args.first() else {
+                println!("held: {:016b}", dbg.emu().keys_mask());
+                return Ok(());
+            };
+
+            let key = u8::from_str_radix(key, 16).ok().filter(|key| *key < 16).ok_or(format!("'{}' isn't a key, 0-F", key))?;
+            match args.get(1).copied().unwrap_or("down") {
+                "down" => dbg.emu_mut().key_down(key as usize),
+                "up" => dbg.emu_mut().key_up(key as usize),
+                other => return Err(format!("'{}' should be up or down", other)),
+            }
+            Ok(())
+        }
+
+        "help" | "h" => {
+            help();
+            Ok(())
+        }
+
+        other => Err(format!("Unknown command '{}', try help", other)),
+    }
+}
+
+// break                      list them
+// break <addr> [if vX <op> <value>]
+fn break_command(dbg: &mut Debugger, args: &[&str]) -> Result<(), String> {
+    let Some(addr) = args.first() else {
+        for (idx, bp) in dbg.breakpoints().iter().enumerate() {
+            println!("{}  {}", idx + 1, describe_breakpoint(bp));
+        }
+        return Ok(());
+    };
+
+    let addr = parse_addr(addr)?;
+    let condition = match &args[1..] {
+        [] => None,
+        ["if", reg, symbol, value] => Some(Condition {
+            reg: parse_reg(reg)?,
+            compare: Compare::from_symbol(symbol).ok_or(format!("'{}' isn't one of == != < <= > >=", symbol))?,
+            value: parse_byte(value)?,
+        }),
+        _ => return Err("expected break <addr> [if vX <op> <value>]".to_string()),
+    };
+
+    let bp = Breakpoint { addr, condition };
+    dbg.add_breakpoint(bp);
+    println!("breakpoint {}  {}", dbg.breakpoints().len(), describe_breakpoint(&bp));
+    Ok(())
+}
+
+// watch                      list them
+// watch <addr> [len] [r|w|rw]
+// watch i [r|w|rw]
+fn watch_command(dbg: &mut Debugger, args: &[&str]) -> Result<(), String> {
+    let Some(target) = args.first() else {
+        for (idx, watch) in dbg.watchpoints().iter().enumerate() {
+            println!("{}  {}", idx + 1, describe_watchpoint(watch));
+        }
+        return Ok(());
+    };
+
+    let mut rest = &args[1..];
+    let watch = if target.eq_ignore_ascii_case("i") {
+        Watchpoint::I {
+            access: parse_access(rest)?,
+        }
+    } else {
+        let start = parse_addr(target)?;
+        let len = match rest.first().map(|text| parse_number(text)) {
+            Some(Ok(len)) => {
+                rest = &rest[1..];
+                len.max(1)
+            }
+            _ => 1,
+        };
+
+        Watchpoint::Ram {
+            start,
+            end: start.saturating_add((len - 1).min(u16::MAX as u32) as u16),
+            access: parse_access(rest)?,
+        }
+    };
+
+    dbg.add_watchpoint(watch);
+    println!("watchpoint {}  {}", dbg.watchpoints().len(), describe_watchpoint(&watch));
+    Ok(())
+}
+
+// delete break <n> or delete watch <n>, numbered as the listings are
+fn delete_command(dbg: &mut Debugger, args: &[&str]) -> Result<(), String> {
+    let (kind, number) = match args {
+        [kind, number] => (*kind, parse_number(number)? as usize),
+        _ => return Err("expected delete break|watch <n>".to_string()),
+    };
+
+    let idx = number.checked_sub(1).ok_or("they're numbered from 1")?;
+    let removed = match kind {
+        "break" | "b" => dbg.remove_breakpoint(idx).is_some(),
+        "watch" | "w" => dbg.remove_watchpoint(idx).is_some(),
+        other => return Err(format!("'{}' should be break or watch", other)),
+    };
+
+    if !removed {
+        return Err(format!("there's no {} {}", kind, number));
+    }
+    Ok(())
+}
+
+// set v3 0x10, set i 0x300, set pc 0x200, set dt 60, set st 0
+fn set_command(dbg: &mut Debugger, args: &[&str]) -> Result<(), String> {
+    let [target, value] = args else {
+        return Err("expected set <vX|i|pc|dt|st> <value>".to_string());
+    };
+
+    let emu = dbg.emu_mut();
+    match target.to_ascii_lowercase().as_str() {
+        "i" => emu.set_i_reg(parse_addr(value)?),
+        "pc" => emu.set_pc(parse_addr(value)?),
+        "dt" => emu.set_delay_timer(parse_byte(value)?),
+        "st" => emu.set_sound_timer(parse_byte(value)?),
+        _ => emu.set_v_reg(parse_reg(target)?, parse_byte(value)?),
+    }
+    Ok(())
+}
+
+fn report(dbg: &Debugger, reason: StopReason) {
+    let emu = dbg.emu();
+
+    match reason {
+        StopReason::Step => (),
+        StopReason::Breakpoint { addr } => println!("breakpoint at {:#05X}", addr),
+        StopReason::RamWatch { addr, write } => {
+            let verb = if write { "wrote" } else { "read" };
+            println!("watchpoint: {} {:#05X}, which holds {:#04X}", verb, addr, emu.ram()[addr]);
+        }
+        StopReason::IWatch { write } => {
+            let verb = if write { "set" } else { "used" };
+            println!("watchpoint: {} I, which holds {:#05X}", verb, emu.i_reg());
+        }
+        StopReason::Halted => println!("halted"),
+        StopReason::Error(err) => println!("stopped:  {}", err),
+        StopReason::Limit => println!("still running at the instruction limit, stopped here"),
+    }
+
+    print_location(dbg);
+}
+
+fn print_location(dbg: &Debugger) {
+    match lines_at(dbg.emu(), dbg.emu().pc(), 1).first() {
+        Some(line) => println!("=> {}", line),
+        None => println!("=> {:04X}  (past the end of RAM)", dbg.emu().pc()),
+    }
+}
+
+fn print_regs(emu: &Emu) {
+    for (half, values) in emu.v_reg().chunks(8).enumerate() {
+        let regs: Vec<String> = values
+            .iter()
+            .enumerate()
+            .map(|(idx, value)| format!("V{:X} {:02X}", half * 8 + idx, value))
+            .collect();
+        println!("{}", regs.join("  "));
+    }
+
+    println!(
+        "I  {:04X}  PC {:04X}  SP {}  DT {:02X}  ST {:02X}",
+        emu.i_reg(),
+        emu.pc(),
+        emu.sp(),
+        emu.delay_timer(),
+        emu.sound_timer()
+    );
+}
+
+// 16 bytes a row, cut off at the end of RAM
+fn print_mem(emu: &Emu, addr: usize, len: usize) {
+    let ram = emu.ram();
+    let end = addr.saturating_add(len).min(ram.len());
+
+    for start in (addr.min(end)..end).step_by(16) {
+        let bytes: Vec<String> = ram[start..(start + 16).min(end)].iter().map(|b| format!("{:02X}", b)).collect();
+        println!("{:04X}  {}", start, bytes.join(" "));
+    }
+}
+
+// Disassembled from addr onwards, data included, whatever the bytes there happen to be
+fn lines_at(emu: &Emu, addr: u16, count: usize) -> Vec<Line> {
+    let ram = emu.ram();
+    let start = addr as usize;
+    let end = start.saturating_add(count * 4).min(ram.len());
+
+    let mut lines = disassemble(&ram[start.min(end)..end], addr);
+    lines.truncate(count);
+    lines
+}
+
+fn describe(emu: &Emu, addr: u16) -> String {
+    match lines_at(emu, addr, 1).first() {
+        Some(line) => line.to_string(),
+        None => format!("{:04X}", addr),
+    }
+}
+
+fn describe_breakpoint(bp: &Breakpoint) -> String {
+    match bp.condition {
+        Some(cond) => format!("{:#05X} if {} {} {:#04X}", bp.addr, cond.reg, cond.compare.symbol(), cond.value),
+        None => format!("{:#05X}", bp.addr),
+    }
+}
+
+fn describe_watchpoint(watch: &Watchpoint) -> String {
+    match *watch {
+        Watchpoint::Ram { start, end, access } if start == end => format!("{:#05X} {}", start, access_name(access)),
+        Watchpoint::Ram { start, end, access } => format!("{:#05X}-{:#05X} {}", start, end, access_name(access)),
+        Watchpoint::I { access } => format!("I {}", access_name(access)),
+    }
+}
+
+fn access_name(access: Access) -> &'static str {
+    match access {
+        Access::Read => "r",
+        Access::Write => "w",
+        Access::ReadWrite => "rw",
+    }
+}
+
+// watchpoints default to writes, like gdb's watch
+fn parse_access(args: &[&str]) -> Result<Access, String> {
+    match args {
+        [] | ["w"] => Ok(Access::Write),
+        ["r"] => Ok(Access::Read),
+        ["rw"] => Ok(Access::ReadWrite),
+        _ => Err(format!("expected r, w or rw, not '{}'", args.join(" "))),
+    }
+}
+
+fn parse_reg(text: &str) -> Result<Reg, String> {
+    text.strip_prefix(['v', 'V'])
+        .and_then(|idx| u8::from_str_radix(idx, 16).ok())
+        .and_then(Reg::new)
+        .ok_or(format!("'{}' isn't a register, V0-VF", text))
+}
+
+fn parse_byte(text: &str) -> Result<u8, String> {
+    u8::try_from(parse_number(text)?).map_err(|_| format!("'{}' doesn't fit in a byte", text))
+}
+
+fn parse_addr(text: &str) -> Result<u16, String> {
+    u16::try_from(parse_number(text)?).map_err(|_| format!("'{}' is past the end of RAM", text))
+}
+
+// 0x10, $10 or plain decimal
+fn parse_number(text: &str) -> Result<u32, String> {
+    let parsed = if let Some(hex) = text.strip_prefix("0x").or_else(|| text.strip_prefix('$')) {
+        u32::from_str_radix(hex, 16)
+    } else {
+        text.parse()
+    };
+
+    parsed.map_err(|_| format!("Could not parse number '{}'", text))
+}
+
+fn help() {
+    println!("break [<addr> [if vX <op> <value>]]  stop before addr runs, op is == != < <= > >=, no address lists them");
+    println!("watch [<addr> [len] [r|w|rw]]        stop after an instruction touches RAM, w by default");
+    println!("watch i [r|w|rw]                     stop after an instruction uses or sets I");
+    println!("delete break|watch <n>               remove one, numbered as the listings are");
+    println!("step [n]                             run n instructions (default 1), into calls");
+    println!("next                                 run one instruction, a CALL runs its whole subroutine");
+    println!("finish                               run until the current subroutine returns");
+    println!("continue [limit]                     run until something stops it");
+    println!("regs                                 V0-VF, I, PC, SP and the timers");
+    println!("mem <addr> [len]                     dump RAM (default 16 bytes)");
+    println!("disas [addr] [count]                 disassemble from addr (default PC)");
+    println!("set <vX|i|pc|dt|st> <value>          change a register");
+    println!("bt                                   the call stack, innermost first");
+    println!("screen                               the framebuffer as text");
+    println!("key [<0-F> [down|up]]                press or release a key, no key lists the held ones");
+    println!("quit");
+    println!();
+    println!("Numbers are decimal, 0x hex or $ hex. An empty line repeats the last command.");
+}
+
+fn usage() {
+    println!("USAGE:  pok8-dbg path/to/game");
+    println!();
+    println!("OPTIONS:");
+    emu_options_usage(None);
+}
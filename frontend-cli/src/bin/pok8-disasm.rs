@@ -0,0 +1,83 @@
+// Prints a ROM as address, opcode and mnemonic, one instruction per line
+use pok_8_core::disasm::{disassemble, labels, source};
+
+use std::collections::BTreeMap;
+use std::{env, fs, process::exit};
+
+fn main() {
+    let args: Vec<_> = env::args().skip(1).collect();
+
+    let help_keyword = "help".to_string();
+    if args.contains(&help_keyword) {
+        usage();
+        exit(0);
+    }
+
+    let mut use_labels = false;
+    let mut print_source = false;
+    let mut base_addr = 0x200;
+    let mut positional = Vec::new();
+
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--labels" => use_labels = true,
+            "--source" => print_source = true,
+
+            "--base" => {
+                let text = args.next().unwrap_or_default();
+                base_addr = parse_addr(&text).unwrap_or_else(|| {
+                    println!("ERROR:  Could not parse base address '{}'", text);
+                    usage();
+                    exit(1);
+                });
+            }
+
+            _ => positional.push(arg),
+        }
+    }
+
+    if positional.len() != 1 {
+        println!("ERROR:  Error parsing cli arguments");
+        usage();
+        exit(1);
+    }
+
+    let rom = fs::read(&positional[0]).unwrap_or_else(|err| {
+        println!("ERROR:  Could not read ROM '{}': {}", positional[0], err);
+        exit(1);
+    });
+
+    let lines = disassemble(&rom, base_addr);
+    let labels = if use_labels { labels(&lines) } else { BTreeMap::new() };
+
+    if print_source {
+        print!("{}", source(&lines, &labels));
+        return;
+    }
+
+    for line in &lines {
+        if let Some(label) = labels.get(&line.addr) {
+            println!("{}:", label);
+        }
+        println!("{:04X}  {:<8}  {}", line.addr, line.opcode(), line.text(&labels));
+    }
+}
+
+// 0x200, $200 or plain decimal
+fn parse_addr(text: &str) -> Option<u16> {
+    if let Some(hex) = text.strip_prefix("0x").or_else(|| text.strip_prefix('$')) {
+        u16::from_str_radix(hex, 16).ok()
+    } else {
+        text.parse().ok()
+    }
+}
+
+fn usage() {
+    println!("USAGE:  pok8-disasm path/to/game");
+    println!();
+    println!("OPTIONS:");
+    println!("  --labels         name jump and call targets (loc_XXX, sub_XXX) and print them in place of addresses");
+    println!("  --base <addr>    address the ROM is loaded at (default 0x200)");
+    println!("  --source         print source pok8-asm can assemble back into the same ROM");
+}
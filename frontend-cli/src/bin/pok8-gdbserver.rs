@@ -0,0 +1,87 @@
+// Serves a ROM to gdb or any other remote serial protocol client on a local TCP port
+use frontend_cli::*;
+use pok_8_core::emu::EmuConfig;
+use pok_8_core::gdb::{GdbStub, Session};
+
+use std::net::TcpListener;
+use std::{env, process::exit};
+
+const DEFAULT_PORT: u16 = 1234;
+
+fn main() {
+    let args: Vec<_> = env::args().skip(1).collect();
+
+    let help_keyword = "help".to_string();
+    if args.contains(&help_keyword) {
+        usage();
+        exit(0);
+    }
+
+    let mut config = EmuConfig::default();
+    let mut port = DEFAULT_PORT;
+    let mut positional = Vec::new();
+
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--port" => {
+                let text = args.next().unwrap_or_default();
+                port = text.parse().unwrap_or_else(|_| {
+                    println!("ERROR:  Could not parse port '{}'", text);
+                    usage();
+                    exit(1);
+                });
+            }
+
+            _ if parse_emu_option(&arg, &mut args, &mut config, usage) => {}
+
+            _ => positional.push(arg),
+        }
+    }
+
+    if positional.len() != 1 {
+        println!("ERROR:  Error parsing cli arguments");
+        usage();
+        exit(1);
+    }
+
+    let emu = load_rom(&positional[0], config);
+
+    // loopback only, the protocol has no authentication
+    let listener = TcpListener::bind(("127.0.0.1", port)).unwrap_or_else(|err| {
+        println!("ERROR:  Could not listen on port {}: {}", port, err);
+        exit(1);
+    });
+
+    let mut stub = GdbStub::new(emu);
+    println!("listening on 127.0.0.1:{}, connect with: target remote :{}", port, port);
+
+    // one client at a time, the ROM stays where the last one left it until a client kills it
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(err) => {
+                println!("WARNING:  Could not accept a connection: {}", err);
+                continue;
+            }
+        };
+
+        println!("client connected");
+        match stub.serve(stream) {
+            Ok(Session::Detached) => println!("client detached"),
+            Ok(Session::Killed) => {
+                println!("killed by the client");
+                break;
+            }
+            Err(err) => println!("WARNING:  Connection lost: {}", err),
+        }
+    }
+}
+
+fn usage() {
+    println!("USAGE:  pok8-gdbserver path/to/game");
+    println!();
+    println!("OPTIONS:");
+    println!("  --port <number>      TCP port on 127.0.0.1 to listen on (default {})", DEFAULT_PORT);
+    emu_options_usage(None);
+}
@@ -0,0 +1,192 @@
+// Runs a ROM for a number of frames with no window or audio, then dumps the screen and prints a hash of the state.
+// The seed defaults to 0 rather than random so two runs of the same command end the same way
+use frontend_cli::*;
+use pok_8_core::emu::*;
+use pok_8_core::image::{to_pbm, to_png, DEFAULT_PALETTE};
+
+use std::{env, fs, process::exit};
+
+const DEFAULT_FRAMES: usize = 600; // 10 seconds
+const DEFAULT_SEED: u64 = 0;
+
+// A key going down or up at the start of a frame, before it runs
+struct KeyEvent {
+    frame: usize,
+    key: usize,
+    down: bool,
+}
+
+fn main() {
+    let args: Vec<_> = env::args().skip(1).collect();
+
+    let help_keyword = "help".to_string();
+    if args.contains(&help_keyword) {
+        usage();
+        exit(0);
+    }
+
+    let mut config = EmuConfig {
+        seed: Some(DEFAULT_SEED),
+        ..EmuConfig::default()
+    };
+    let mut frames = DEFAULT_FRAMES;
+    let mut scale = 1;
+    let mut input = None;
+    let mut png = None;
+    let mut pbm = None;
+    let mut ascii = None;
+    let mut positional = Vec::new();
+
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--frames" | "--scale" => {
+                let text = args.next().unwrap_or_default();
+                let number = text.parse::<usize>().unwrap_or_else(|_| {
+                    println!("ERROR:  Could not parse {} '{}'", arg, text);
+                    usage();
+                    exit(1);
+                });
+
+                if arg == "--frames" {
+                    frames = number;
+                } else {
+                    scale = number;
+                }
+            }
+
+            "--input" | "--png" | "--pbm" | "--ascii" => {
+                let path = args.next().unwrap_or_else(|| {
+                    println!("ERROR:  {} needs a file", arg);
+                    usage();
+                    exit(1);
+                });
+
+                match arg.as_str() {
+                    "--input" => input = Some(path),
+                    "--png" => png = Some(path),
+                    "--pbm" => pbm = Some(path),
+                    _ => ascii = Some(path),
+                }
+            }
+
+            _ if parse_emu_option(&arg, &mut args, &mut config, usage) => {}
+
+            _ => positional.push(arg),
+        }
+    }
+
+    if positional.len() != 1 {
+        println!("ERROR:  Error parsing cli arguments");
+        usage();
+        exit(1);
+    }
+
+    let rom = read_rom(&positional[0]);
+
+    let events = input.map_or_else(Vec::new, |path| {
+        fs::read_to_string(&path)
+            .map_err(|err| err.to_string())
+            .and_then(|text| parse_input(&text))
+            .unwrap_or_else(|err| {
+                println!("ERROR:  Could not read input '{}': {}", path, err);
+                exit(1);
+            })
+    });
+
+    let mut emu = start_emu(&rom, config);
+
+    let mut events = events.iter().peekable();
+    for frame in 0..frames {
+        while let Some(event) = events.next_if(|event| event.frame == frame) {
+            if event.down {
+                emu.key_down(event.key);
+            } else {
+                emu.key_up(event.key);
+            }
+        }
+
+        // the screen and state at the error are still written, but the exit code says it went wrong
+        if let Err(err) = emu.run_frame() {
+            println!("ERROR:  Stopped at frame {}: {}", frame, err);
+            dump(&emu, png.as_deref(), pbm.as_deref(), ascii.as_deref(), scale);
+            exit(1);
+        }
+        if emu.is_halted() {
+            break;
+        }
+    }
+
+    dump(&emu, png.as_deref(), pbm.as_deref(), ascii.as_deref(), scale);
+}
+
+// - for the ASCII art goes to stdout
+fn dump(emu: &Emu, png: Option<&str>, pbm: Option<&str>, ascii: Option<&str>, scale: usize) {
+    let display = emu.get_display();
+
+    if let Some(path) = png {
+        write(path, &to_png(&display, &DEFAULT_PALETTE, scale));
+    }
+    if let Some(path) = pbm {
+        write(path, to_pbm(&display).as_bytes());
+    }
+    match ascii {
+        Some("-") => print!("{}", display.to_text()),
+        Some(path) => write(path, display.to_text().as_bytes()),
+        None => (),
+    }
+
+    println!("state:   {:016x}", emu.state_hash());
+}
+
+// One event per line, `<frame> <key> down|up` with the key in hex. # starts a comment
+fn parse_input(text: &str) -> Result<Vec<KeyEvent>, String> {
+    let mut events = Vec::new();
+
+    for (idx, line) in text.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or_default().trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        let bad = || format!("line {}: expected '<frame> <key> down|up', got '{}'", idx + 1, line);
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        let [frame, key, action] = fields[..] else {
+            return Err(bad());
+        };
+
+        let frame = frame.parse::<usize>().map_err(|_| bad())?;
+        let key = usize::from_str_radix(key, 16).ok().filter(|key| *key < 16).ok_or_else(bad)?;
+        let down = match action {
+            "down" => true,
+            "up" => false,
+            _ => return Err(bad()),
+        };
+
+        events.push(KeyEvent { frame, key, down });
+    }
+
+    // in frame order, keeping the file's order within a frame
+    events.sort_by_key(|event| event.frame);
+    Ok(events)
+}
+
+fn write(path: &str, contents: &[u8]) {
+    fs::write(path, contents).unwrap_or_else(|err| {
+        println!("ERROR:  Could not write '{}': {}", path, err);
+        exit(1);
+    });
+}
+
+fn usage() {
+    println!("USAGE:  pok8-headless path/to/game");
+    println!();
+    println!("OPTIONS:");
+    println!("  --frames <n>         frames to run for (default {}, 10 seconds)", DEFAULT_FRAMES);
+    println!("  --input <file>       keys to press, one '<frame> <key> down|up' per line, key in hex");
+    println!("  --png <file>         write the final screen as a PNG");
+    println!("  --scale <n>          pixels per CHIP-8 pixel in the PNG (default 1)");
+    println!("  --pbm <file>         write the final screen as a plain PBM");
+    println!("  --ascii <file>       write the final screen as text, . for off and # for on, - for stdout");
+    emu_options_usage(Some(DEFAULT_SEED));
+}
@@ -0,0 +1,133 @@
+// Runs a ROM without a window for a number of frames, or through a movie, and prints where the cycles went
+use frontend_cli::*;
+use pok_8_core::emu::*;
+use pok_8_core::movie::Movie;
+use pok_8_core::profile::Profiler;
+
+use std::{env, fs, process::exit};
+
+const DEFAULT_FRAMES: usize = 600; // 10 seconds
+const DEFAULT_ROWS: usize = 20;
+
+fn main() {
+    let args: Vec<_> = env::args().skip(1).collect();
+
+    let help_keyword = "help".to_string();
+    if args.contains(&help_keyword) {
+        usage();
+        exit(0);
+    }
+
+    let mut config = EmuConfig::default();
+    let mut frames = DEFAULT_FRAMES;
+    let mut rows = DEFAULT_ROWS;
+    let mut movie = None;
+    let mut folded = None;
+    let mut positional = Vec::new();
+
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--frames" | "--top" => {
+                let text = args.next().unwrap_or_default();
+                let number = text.parse::<usize>().unwrap_or_else(|_| {
+                    println!("ERROR:  Could not parse {} '{}'", arg, text);
+                    usage();
+                    exit(1);
+                });
+
+                if arg == "--frames" {
+                    frames = number;
+                } else {
+                    rows = number;
+                }
+            }
+
+            "--play" | "--folded" => {
+                let path = args.next().unwrap_or_else(|| {
+                    println!("ERROR:  {} needs a file", arg);
+                    usage();
+                    exit(1);
+                });
+
+                if arg == "--play" {
+                    movie = Some(path);
+                } else {
+                    folded = Some(path);
+                }
+            }
+
+            _ if parse_emu_option(&arg, &mut args, &mut config, usage) => {}
+
+            _ => positional.push(arg),
+        }
+    }
+
+    if positional.len() != 1 {
+        println!("ERROR:  Error parsing cli arguments");
+        usage();
+        exit(1);
+    }
+
+    let rom = read_rom(&positional[0]);
+
+    // a movie brings its own quirks, seed and keypad, and runs for as long as it's recorded
+    let movie = movie.map(|path| {
+        fs::read(&path)
+            .map_err(|err| err.to_string())
+            .and_then(|data| Movie::from_bytes(&data).map_err(|err| err.to_string()))
+            .unwrap_or_else(|err| {
+                println!("ERROR:  Could not read movie '{}': {}", path, err);
+                exit(1);
+            })
+    });
+
+    let mut emu = match &movie {
+        Some(movie) => movie.start(&rom).unwrap_or_else(|err| {
+            println!("ERROR:  {}", err);
+            exit(1);
+        }),
+        None => start_emu(&rom, config),
+    };
+
+    if let Some(movie) = &movie {
+        frames = movie.frames.len();
+    }
+
+    emu.set_profiler(Profiler::new());
+    for frame in 0..frames {
+        if let Some(movie) = &movie {
+            movie.apply(&mut emu, frame);
+        }
+
+        // the profile up to the error is still worth having
+        if let Err(err) = emu.run_frame() {
+            println!("WARNING:  Stopped at frame {}: {}", frame, err);
+            break;
+        }
+        if emu.is_halted() {
+            break;
+        }
+    }
+
+    let profiler = emu.take_profiler().unwrap_or_default();
+    print!("{}", profiler.summary(rows));
+
+    if let Some(path) = folded {
+        fs::write(&path, profiler.folded()).unwrap_or_else(|err| {
+            println!("ERROR:  Could not write '{}': {}", path, err);
+            exit(1);
+        });
+    }
+}
+
+fn usage() {
+    println!("USAGE:  pok8-profile path/to/game");
+    println!();
+    println!("OPTIONS:");
+    println!("  --frames <n>         frames to run for (default {}, 10 seconds)", DEFAULT_FRAMES);
+    println!("  --play <movie>       run through a recorded movie instead, with its keypad, quirks and seed");
+    println!("  --top <n>            rows in each table (default {})", DEFAULT_ROWS);
+    println!("  --folded <file>      write folded call stacks for flamegraph.pl or inferno-flamegraph");
+    emu_options_usage(None);
+}
@@ -0,0 +1,49 @@
+// Plays a movie recorded with `frontend-desktop --record` without opening a window and checks it ends the
+// way the recording did
+use pok_8_core::movie::Movie;
+
+use std::{env, fs, process::exit};
+
+fn main() {
+    let args: Vec<_> = env::args().skip(1).collect();
+
+    if args.len() != 2 || args[0] == "help" {
+        usage();
+        exit(if args.len() == 1 { 0 } else { 1 });
+    }
+
+    let movie = fs::read(&args[0])
+        .map_err(|err| err.to_string())
+        .and_then(|data| Movie::from_bytes(&data).map_err(|err| err.to_string()))
+        .unwrap_or_else(|err| {
+            println!("ERROR:  Could not read movie '{}': {}", args[0], err);
+            exit(1);
+        });
+
+    let rom = fs::read(&args[1]).unwrap_or_else(|err| {
+        println!("ERROR:  Could not read ROM '{}': {}", args[1], err);
+        exit(1);
+    });
+
+    let emu = movie.replay(&rom).unwrap_or_else(|err| {
+        println!("ERROR:  {}", err);
+        exit(1);
+    });
+
+    let hash = emu.state_hash();
+    println!("frames:  {}", movie.frames.len());
+    println!("state:   {:016x}", hash);
+
+    match movie.end_hash {
+        Some(expected) if expected == hash => println!("matches the recording"),
+        Some(expected) => {
+            println!("MISMATCH:  recording ended on {:016x}", expected);
+            exit(1);
+        }
+        None => println!("no end state recorded to check against"),
+    }
+}
+
+fn usage() {
+    println!("USAGE:  pok8-replay path/to/movie path/to/game");
+}
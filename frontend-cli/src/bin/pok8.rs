@@ -0,0 +1,126 @@
+// Tools that don't need a window, as subcommands. `pok8 test` runs scenario files against the ROMs they name and
+// `pok8 bless` rewrites their goldens
+use pok_8_core::scenario::Scenario;
+
+use std::path::{Path, PathBuf};
+use std::{env, fs, process::exit};
+
+const SCENARIO_EXT: &str = "scenario";
+
+fn main() {
+    let args: Vec<_> = env::args().skip(1).collect();
+
+    match args.split_first() {
+        Some((command, rest)) if command == "test" => test(rest, false),
+        Some((command, rest)) if command == "bless" => test(rest, true),
+        Some((command, _)) if command == "help" => usage(),
+        Some((command, _)) => {
+            println!("ERROR:  Unknown command '{}'", command);
+            usage();
+            exit(1);
+        }
+        None => {
+            usage();
+            exit(1);
+        }
+    }
+}
+
+// bless writes the goldens from this run, for when the way a ROM behaves has changed on purpose
+fn test(args: &[String], bless: bool) {
+    if args.is_empty() {
+        println!("ERROR:  Give scenario files or directories of them");
+        usage();
+        exit(1);
+    }
+
+    let mut files = Vec::new();
+    for arg in args {
+        let path = Path::new(arg);
+        if path.is_dir() {
+            collect(path, &mut files);
+        } else {
+            files.push(path.to_path_buf());
+        }
+    }
+
+    let (mut passed, mut failed) = (0, 0);
+    for file in &files {
+        let scenario = match Scenario::from_file(file) {
+            Ok(scenario) => scenario,
+            Err(err) => {
+                println!("FAIL  {}\n      {}", file.display(), err);
+                failed += 1;
+                continue;
+            }
+        };
+
+        let report = if bless { scenario.bless() } else { scenario.run() };
+        if report.passed() && bless {
+            println!("ok    {} ({} goldens written)", file.display(), report.blessed);
+            passed += 1;
+            continue;
+        }
+        if report.passed() {
+            println!("ok    {} ({} frames)", file.display(), report.frames);
+            passed += 1;
+            continue;
+        }
+
+        println!("FAIL  {}", file.display());
+        for failure in &report.failures {
+            let message = failure.message.replace('\n', "\n        ");
+            match failure.line {
+                0 => println!("      {}", message),
+                line => println!("      line {}: {}", line, message),
+            }
+        }
+        failed += 1;
+    }
+
+    println!();
+    println!("{} passed, {} failed", passed, failed);
+    if failed > 0 {
+        exit(1);
+    }
+}
+
+// Every .scenario under dir, in name order so runs list the same way everywhere
+fn collect(dir: &Path, files: &mut Vec<PathBuf>) {
+    let entries = fs::read_dir(dir).unwrap_or_else(|err| {
+        println!("ERROR:  Could not read '{}': {}", dir.display(), err);
+        exit(1);
+    });
+
+    let mut paths: Vec<PathBuf> = entries.filter_map(|entry| entry.ok().map(|entry| entry.path())).collect();
+    paths.sort();
+
+    for path in paths {
+        if path.is_dir() {
+            collect(&path, files);
+        } else if path.extension().is_some_and(|ext| ext == SCENARIO_EXT) {
+            files.push(path);
+        }
+    }
+}
+
+fn usage() {
+    println!("USAGE:  pok8 <command>");
+    println!();
+    println!("COMMANDS:");
+    println!("  test <path>...       run scenario files, or every .{} under a directory", SCENARIO_EXT);
+    println!("  bless <path>...      run them the same way, writing their screen files and state hashes");
+    println!();
+    println!("A scenario names a ROM, presses keys, waits and checks what it expects, one statement per line:");
+    println!();
+    println!("  rom game.asm             ; relative to the scenario, .asm source is assembled first");
+    println!("  quirks schip             ; also seed and rng, all before the first step");
+    println!("  press 5                  ; keys in hex, release 5 lets go");
+    println!("  wait 30                  ; frames, or `at 120` to run up to a frame");
+    println!("  expect v3 == 0x05        ; V0-VF, I, PC, SP, DT or ST, with == != < <= > >=");
+    println!("  expect mem 0x300 01 02   ; RAM from an address");
+    println!("  expect halted");
+    println!("  expect state 1a2b...     ; Emu::state_hash, as pok8-headless prints it");
+    println!("  expect screen end.png    ; the whole screen, from a .png or text file");
+    println!("  expect screen at 8 4     ; rows of . and # up to a line with `end`, checked at that corner");
+}
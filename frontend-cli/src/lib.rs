@@ -0,0 +1,74 @@
+// What the command line tools (and the desktop frontend) share: the flags that build an EmuConfig, and getting a ROM
+// into an Emu. Errors print, show the tool's own usage and exit like the rest of each tool's parsing does
+use pok_8_core::emu::*;
+use pok_8_core::quirks::Quirks;
+use pok_8_core::rng::RngKind;
+
+use std::{fs, process::exit};
+
+// Handles --quirks, --seed and --rng, taking the value off args. False means arg is none of them
+pub fn parse_emu_option(arg: &str, args: &mut impl Iterator<Item = String>, config: &mut EmuConfig, usage: fn()) -> bool {
+    match arg {
+        "--quirks" => {
+            let name = args.next().unwrap_or_default();
+            config.quirks = Quirks::from_name(&name).unwrap_or_else(|| {
+                println!("ERROR:  Unknown quirk profile '{}'", name);
+                usage();
+                exit(1);
+            });
+        }
+
+        "--seed" => {
+            let text = args.next().unwrap_or_default();
+            config.seed = Some(text.parse::<u64>().unwrap_or_else(|_| {
+                println!("ERROR:  Could not parse seed '{}'", text);
+                usage();
+                exit(1);
+            }));
+        }
+
+        "--rng" => {
+            let name = args.next().unwrap_or_default();
+            config.rng = RngKind::from_name(&name).unwrap_or_else(|| {
+                println!("ERROR:  Unknown random number generator '{}'", name);
+                usage();
+                exit(1);
+            });
+        }
+
+        _ => return false,
+    }
+
+    true
+}
+
+// The usage lines for the flags above, default_seed being whatever the tool starts with instead of a random one
+pub fn emu_options_usage(default_seed: Option<u64>) {
+    println!("  --quirks <profile>   opcode behaviour, one of: {}", Quirks::PRESETS.join(", "));
+    match default_seed {
+        Some(seed) => println!("  --seed <number>      seed for the random number generator (default {})", seed),
+        None => println!("  --seed <number>      seed the random number generator so runs repeat exactly"),
+    }
+    println!("  --rng <kind>         random number generator, xorshift or counter (default xorshift)");
+}
+
+pub fn read_rom(path: &str) -> Vec<u8> {
+    fs::read(path).unwrap_or_else(|err| {
+        println!("ERROR:  Could not read ROM '{}': {}", path, err);
+        exit(1);
+    })
+}
+
+pub fn start_emu(rom: &[u8], config: EmuConfig) -> Emu {
+    let mut emu = Emu::with_config(config);
+    emu.load(rom).unwrap_or_else(|err| {
+        println!("ERROR:  {}", err);
+        exit(1);
+    });
+
+    emu
+}
+
+pub fn load_rom(path: &str, config: EmuConfig) -> Emu {
+    start_emu(&read_rom(path), config)
+}
@@ -0,0 +1,74 @@
+use pok_8_core::speaker::{AudioSink, Synth, Tone};
+
+use sdl2::audio::{AudioCallback, AudioDevice, AudioSpecDesired};
+use sdl2::AudioSubsystem;
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+const SAMPLE_RATE: i32 = 44100;
+pub const MAX_TONE_HZ: f32 = SAMPLE_RATE as f32 / 2.0; // Nyquist, any higher and the tone folds back down
+
+// Runs on SDL's audio thread
+struct Voice {
+    synth: Synth,
+    sample_rate: f32,
+    muted: Arc<AtomicBool>,
+}
+
+impl AudioCallback for Voice {
+    type Channel = f32;
+
+    fn callback(&mut self, out: &mut [f32]) {
+        let muted = self.muted.load(Ordering::Relaxed);
+
+        for sample in out.iter_mut() {
+            let next = self.synth.next_sample(self.sample_rate);
+            *sample = if muted { 0.0 } else { next };
+        }
+    }
+}
+
+// AudioSink that pauses and resumes an SDL playback device
+pub struct SdlAudio {
+    device: AudioDevice<Voice>,
+    muted: Arc<AtomicBool>,
+}
+
+impl SdlAudio {
+    pub fn init(audio_subsystem: &AudioSubsystem, tone: Tone) -> Result<Self, String> {
+        let desired = AudioSpecDesired {
+            freq: Some(SAMPLE_RATE),
+            channels: Some(1),
+            samples: None,
+        };
+
+        let muted = Arc::new(AtomicBool::new(false));
+        let device = audio_subsystem.open_playback(None, &desired, |spec| Voice {
+            synth: Synth::new(tone),
+            sample_rate: spec.freq as f32,
+            muted: muted.clone(),
+        })?;
+
+        Ok(Self { device, muted })
+    }
+
+    // The sink itself goes to Emu, this stays with the window so it can toggle mute
+    pub fn mute_handle(&self) -> Arc<AtomicBool> {
+        self.muted.clone()
+    }
+}
+
+impl AudioSink for SdlAudio {
+    fn sound_on(&mut self) {
+        self.device.resume();
+    }
+
+    fn sound_off(&mut self) {
+        self.device.pause();
+    }
+
+    fn pattern(&mut self, pattern: Option<&[u8; 16]>, pitch: u8) {
+        self.device.lock().synth.set_pattern(pattern, pitch);
+    }
+}
@@ -1,48 +1,186 @@
-
+pub mod audio;
+pub mod overlay;
+pub mod panels;
+pub mod recorder;
+pub mod rewind;
 pub mod window;
-use window::POK8;
+use audio::MAX_TONE_HZ;
+use window::{Options, POK8};
+
+use frontend_cli::{emu_options_usage, parse_emu_option};
+
+use pok_8_core::speaker::Waveform;
+use pok_8_core::trace::TraceOptions;
 
 use std::{env, process::exit};
 
 fn main() {
-    let args: Vec<_> = env::args().collect();
-    if args.len() < 2 || args.len() > 3 {
-        println!("ERROR:  Error parsing cli arguments");
-        usage();
-        exit(1);
-    }
-    
+    let args: Vec<_> = env::args().skip(1).collect();
+
     let help_keyword = "help".to_string();
     if args.contains(&help_keyword) {
         usage();
         exit(0);
     }
-    
-    
-    match args.len() {
-        
-        2 => {
-            
-            POK8::init(&args[1], None);
-        },
-
-        3 => {
-
-            let scale = args[2].parse::<u32>().expect("Could not parse window_scale given. \n See cargo run help for usage.");
-            
-            POK8::init(&args[1], Some(scale));
-        },
-        
-        _ => {
-            // Unreachable case
-            println!("Catastrophic failure. Unknown Error.");
-            usage();
-            exit(1);
+
+    let mut options = Options::default();
+    let mut positional = Vec::new();
+
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            _ if parse_emu_option(&arg, &mut args, &mut options.config, usage) => {}
+
+            "--palette" => {
+                let text = args.next().unwrap_or_default();
+                options.palette = Options::parse_palette(&text).unwrap_or_else(|| {
+                    println!("ERROR:  Could not parse palette '{}'", text);
+                    usage();
+                    exit(1);
+                });
+            }
+
+            "--tone" => {
+                let text = args.next().unwrap_or_default();
+                let audible = |hz: &f32| hz.is_finite() && *hz > 0.0 && *hz <= MAX_TONE_HZ;
+                options.tone.frequency = text.parse::<f32>().ok().filter(audible).unwrap_or_else(|| {
+                    println!("ERROR:  Tone frequency must be above 0 and at most {} Hz, got '{}'", MAX_TONE_HZ, text);
+                    usage();
+                    exit(1);
+                });
+            }
+
+            "--waveform" => {
+                let name = args.next().unwrap_or_default();
+                options.tone.waveform = Waveform::from_name(&name).unwrap_or_else(|| {
+                    println!("ERROR:  Unknown waveform '{}'", name);
+                    usage();
+                    exit(1);
+                });
+            }
+
+            "--volume" => {
+                let text = args.next().unwrap_or_default();
+                let percent = text.parse::<u8>().ok().filter(|v| *v <= 100).unwrap_or_else(|| {
+                    println!("ERROR:  Volume must be 0-100, got '{}'", text);
+                    usage();
+                    exit(1);
+                });
+                options.tone.volume = percent as f32 / 100.0;
+            }
+
+            "--mute" => options.muted = true,
+            "--debug" => options.debug = true,
+
+            "--trace" => {
+                let path = args.next().unwrap_or_else(|| {
+                    println!("ERROR:  --trace needs a file to write to");
+                    usage();
+                    exit(1);
+                });
+                options.trace = Some(path.into());
+            }
+
+            "--trace-range" => {
+                let text = args.next().unwrap_or_default();
+                let range = TraceOptions::parse_range(&text).unwrap_or_else(|| {
+                    println!("ERROR:  Could not parse address range '{}'", text);
+                    usage();
+                    exit(1);
+                });
+                options.trace_options.ranges.push(range);
+            }
+
+            "--trace-ring" => {
+                let text = args.next().unwrap_or_default();
+                options.trace_options.ring = Some(text.parse::<usize>().unwrap_or_else(|_| {
+                    println!("ERROR:  Could not parse ring size '{}'", text);
+                    usage();
+                    exit(1);
+                }));
+            }
+
+            "--profile" => {
+                let path = args.next().unwrap_or_else(|| {
+                    println!("ERROR:  --profile needs a file to write to");
+                    usage();
+                    exit(1);
+                });
+                options.profile = Some(path.into());
+            }
+
+            "--rewind" => {
+                let text = args.next().unwrap_or_default();
+                options.rewind_frames = text.parse::<usize>().unwrap_or_else(|_| {
+                    println!("ERROR:  Could not parse rewind depth '{}'", text);
+                    usage();
+                    exit(1);
+                });
+            }
+
+            "--record" | "--play" => {
+                let path = args.next().unwrap_or_else(|| {
+                    println!("ERROR:  {} needs a movie file", arg);
+                    usage();
+                    exit(1);
+                });
+
+                if arg == "--record" {
+                    options.record = Some(path.into());
+                } else {
+                    options.play = Some(path.into());
+                }
+            }
+
+            _ => positional.push(arg),
         }
-    } 
+    }
+
+    if options.trace.is_none() && options.trace_options != TraceOptions::default() {
+        println!("ERROR:  --trace-range and --trace-ring need --trace");
+        usage();
+        exit(1);
+    }
+
+    if options.record.is_some() && options.play.is_some() {
+        println!("ERROR:  Can't record and play a movie at the same time");
+        usage();
+        exit(1);
+    }
+
+    if positional.is_empty() || positional.len() > 2 {
+        println!("ERROR:  Error parsing cli arguments");
+        usage();
+        exit(1);
+    }
+
+    if let Some(scale) = positional.get(1) {
+        options.scale = scale.parse::<u32>().expect("Could not parse window_scale given. \n See cargo run help for usage.");
+    }
+
+    if let Err(err) = POK8::init(&positional[0], options) {
+        println!("ERROR:  {}", err);
+        exit(1);
+    }
 }
 
-fn usage() -> () {
+fn usage() {
     println!("USAGE (default scale):  cargo run path/to/game");
     println!("USAGE (custom scale):  cargo run path/to/game window_scale");
-}
\ No newline at end of file
+    println!();
+    println!("OPTIONS:");
+    emu_options_usage(None);
+    println!("  --palette <colours>  four RRGGBB colours for background, plane 1, plane 2 and both planes");
+    println!("  --tone <hz>          buzzer frequency, up to {} (default 440)", MAX_TONE_HZ);
+    println!("  --waveform <shape>   buzzer waveform, square or sine (default square)");
+    println!("  --volume <percent>   buzzer volume 0-100 (default 25)");
+    println!("  --mute               start muted, M toggles sound while playing");
+    println!("  --rewind <frames>    frames of history Backspace can rewind through (default 600, 0 is off)");
+    println!("  --record <movie>     record the keypad every frame to a movie file");
+    println!("  --play <movie>       play a recorded movie back, P pauses and N steps a frame");
+    println!("  --debug              start with the debug panels open, Tab shows and hides them");
+    println!("  --trace <file>       log every instruction with the registers before it ran");
+    println!("  --trace-range <a-b>  only trace instructions between two addresses, e.g. 0x200-0x2FF (repeatable)");
+    println!("  --trace-ring <n>     keep the last n trace lines and only write them if the ROM hits an error");
+    println!("  --profile <file>     write where the cycles went on exit, and folded stacks for flamegraphs to <file>.folded");
+}
@@ -1,3 +1,8 @@
+pub mod audio;
+pub mod overlay;
+pub mod panels;
+pub mod recorder;
+pub mod rewind;
 pub mod window;
 
 pub use window::*;
\ No newline at end of file
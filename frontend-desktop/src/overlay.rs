@@ -0,0 +1,42 @@
+use pok_8_core::emu::{FONTSET, SCREEN_HEIGHT, SCREEN_WIDTH};
+
+use sdl2::{pixels::Color, rect::Rect, render::Canvas, video::Window};
+
+// Overlay pixels are a quarter of a lores pixel, small enough to stay out of the way of the game
+const OVERLAY_WIDTH: u32 = SCREEN_WIDTH as u32 * 4;
+const OVERLAY_HEIGHT: u32 = SCREEN_HEIGHT as u32 * 4;
+const GLYPH_WIDTH: i32 = 5; // 4 wide plus a gap
+const GLYPH_HEIGHT: i32 = 5;
+
+// Writes a number in the top left corner with the CHIP-8 font, on a box so it shows on any palette
+pub fn draw_counter(canvas: &mut Canvas<Window>, number: usize, colours: (Color, Color)) {
+    let digits: Vec<usize> = number
+        .to_string()
+        .bytes()
+        .map(|digit| (digit - b'0') as usize)
+        .collect();
+
+    canvas.set_logical_size(OVERLAY_WIDTH, OVERLAY_HEIGHT).unwrap();
+
+    let (background, foreground) = colours;
+    let width = digits.len() as u32 * GLYPH_WIDTH as u32 + 1;
+    canvas.set_draw_color(background);
+    canvas.fill_rect(Rect::new(0, 0, width, GLYPH_HEIGHT as u32 + 2)).unwrap();
+
+    // each glyph is 5 rows of 4 pixels in the top nibble
+    let mut rects = Vec::new();
+    for (pos, digit) in digits.iter().enumerate() {
+        let glyph = &FONTSET[(digit * 5)..(digit * 5 + 5)];
+
+        for (y, row) in glyph.iter().enumerate() {
+            for x in 0..4 {
+                if row & (0x80 >> x) != 0 {
+                    rects.push(Rect::new(1 + pos as i32 * GLYPH_WIDTH + x, 1 + y as i32, 1, 1));
+                }
+            }
+        }
+    }
+
+    canvas.set_draw_color(foreground);
+    canvas.fill_rects(&rects).unwrap();
+}
@@ -0,0 +1,231 @@
+use pok_8_core::disasm::{disassemble, Line};
+use pok_8_core::emu::Emu;
+
+use sdl2::{pixels::Color, rect::Rect, render::Canvas, video::Window};
+
+// The debug layout is drawn on its own logical canvas: the game in the top left at 256x128 (4x lores, 2x hires),
+// registers and code to its right, memory below it and a status line along the bottom
+pub const LAYOUT_WIDTH: u32 = 512;
+pub const LAYOUT_HEIGHT: u32 = 288;
+pub const LAYOUT_SCALE: u32 = 2; // window pixels per layout pixel
+pub const GAME_WIDTH: u32 = 256; // the game's corner of the layout, at 0,0
+pub const GAME_HEIGHT: u32 = 128;
+
+const CHAR_WIDTH: i32 = 4; // 3 wide plus a gap
+const CHAR_HEIGHT: i32 = 6; // 5 high plus a gap
+const SIDE_X: i32 = 264;
+const MEMORY_Y: i32 = 136;
+const MEMORY_ROWS: usize = 23;
+const CODE_Y: i32 = 50;
+const CODE_LINES: usize = 37;
+const CODE_BEFORE: usize = 8; // lines shown above PC
+const STATUS_Y: i32 = LAYOUT_HEIGHT as i32 - CHAR_HEIGHT;
+
+const BACKGROUND: Color = Color::RGB(24, 24, 32);
+const TEXT: Color = Color::RGB(220, 220, 220);
+const DIM: Color = Color::RGB(120, 120, 140);
+const HIGHLIGHT: Color = Color::RGB(255, 200, 0); // PC, and the byte I points at
+
+// Registers, stack, code around PC and a hex view of RAM next to the game
+pub struct DebugPanels {
+    pub visible: bool,
+    memory_addr: usize, // first row of the hex view, always a multiple of 16
+}
+
+impl DebugPanels {
+    pub fn new(visible: bool) -> Self {
+        Self {
+            visible,
+            memory_addr: 0x200,
+        }
+    }
+
+    // by rows of 16 bytes, negative is up
+    pub fn scroll(&mut self, emu: &Emu, rows: i32) {
+        let last = emu.ram().len() - MEMORY_ROWS * 16;
+        let addr = self.memory_addr as i64 + rows as i64 * 16;
+        self.memory_addr = addr.clamp(0, last as i64) as usize;
+    }
+
+    // puts the byte I points at on the top row
+    pub fn follow_i(&mut self, emu: &Emu) {
+        self.memory_addr = 0;
+        self.scroll(emu, emu.i_reg() as i32 / 16);
+    }
+
+    // Everything but the game itself, which window.rs draws into its corner afterwards
+    pub fn draw(&self, emu: &Emu, canvas: &mut Canvas<Window>, status: &str) {
+        canvas.set_logical_size(LAYOUT_WIDTH, LAYOUT_HEIGHT).unwrap();
+        canvas.set_draw_color(BACKGROUND);
+        canvas.clear();
+
+        self.draw_registers(emu, canvas);
+        self.draw_code(emu, canvas);
+        self.draw_memory(emu, canvas);
+        draw_text(canvas, 0, STATUS_Y, status, DIM);
+    }
+
+    fn draw_registers(&self, emu: &Emu, canvas: &mut Canvas<Window>) {
+        for (row, values) in emu.v_reg().chunks(8).enumerate() {
+            let regs: Vec<String> = values
+                .iter()
+                .enumerate()
+                .map(|(idx, value)| format!("V{:X} {:02X}", row * 8 + idx, value))
+                .collect();
+            draw_text(canvas, SIDE_X, row as i32 * CHAR_HEIGHT, &regs.join("  "), TEXT);
+        }
+
+        let line = format!(
+            "I {:04X}  PC {:04X}  SP {:02X}  DT {:02X}  ST {:02X}",
+            emu.i_reg(),
+            emu.pc(),
+            emu.sp(),
+            emu.delay_timer(),
+            emu.sound_timer()
+        );
+        draw_text(canvas, SIDE_X, 2 * CHAR_HEIGHT, &line, TEXT);
+
+        // innermost return address first, 8 to a row
+        draw_text(canvas, SIDE_X, 4 * CHAR_HEIGHT, "STACK", DIM);
+        let stack: Vec<String> = emu.stack().iter().rev().map(|addr| format!("{:04X}", addr)).collect();
+        for (row, addrs) in stack.chunks(8).enumerate() {
+            draw_text(canvas, SIDE_X, (5 + row as i32) * CHAR_HEIGHT, &addrs.join(" "), TEXT);
+        }
+    }
+
+    fn draw_code(&self, emu: &Emu, canvas: &mut Canvas<Window>) {
+        draw_text(canvas, SIDE_X, CODE_Y - CHAR_HEIGHT, "CODE", DIM);
+
+        for (row, line) in code_around(emu).iter().enumerate() {
+            let y = CODE_Y + row as i32 * CHAR_HEIGHT;
+            let (marker, colour) = if line.addr == emu.pc() { ("> ", HIGHLIGHT) } else { ("  ", TEXT) };
+            draw_text(canvas, SIDE_X, y, &format!("{}{}", marker, line), colour);
+        }
+    }
+
+    fn draw_memory(&self, emu: &Emu, canvas: &mut Canvas<Window>) {
+        draw_text(canvas, 0, MEMORY_Y - CHAR_HEIGHT, "MEMORY  PGUP PGDN UP DOWN WHEEL SCROLL, HOME GOES TO I", DIM);
+
+        let ram = emu.ram();
+        let i_reg = emu.i_reg() as usize;
+        let pc = emu.pc() as usize;
+
+        for row in 0..MEMORY_ROWS {
+            let addr = self.memory_addr + row * 16;
+            let y = MEMORY_Y + row as i32 * CHAR_HEIGHT;
+            draw_text(canvas, 0, y, &format!("{:04X}", addr), DIM);
+
+            for (col, byte) in ram[addr..(addr + 16)].iter().enumerate() {
+                let colour = if addr + col == i_reg || (pc..(pc + 2)).contains(&(addr + col)) { HIGHLIGHT } else { TEXT };
+                let x = (6 + col as i32 * 3) * CHAR_WIDTH;
+                draw_text(canvas, x, y, &format!("{:02X}", byte), colour);
+            }
+        }
+    }
+}
+
+// A few lines before PC as well as after. There's no telling where instructions start going backwards, so this
+// sweeps from a little way back and only keeps that start if the sweep lands on PC
+fn code_around(emu: &Emu) -> Vec<Line> {
+    let ram = emu.ram();
+    let pc = emu.pc() as usize;
+    let lines_from = |start: usize| {
+        let end = (start + CODE_LINES * 4).min(ram.len());
+        disassemble(&ram[start.min(end)..end], start as u16)
+    };
+
+    let before = lines_from(pc.saturating_sub(CODE_BEFORE * 2));
+    let mut lines = match before.iter().position(|line| line.addr as usize == pc) {
+        Some(_) => before,
+        None => lines_from(pc),
+    };
+
+    lines.truncate(CODE_LINES);
+    lines
+}
+
+// Text in the layout's 3x5 font, lower case comes out as upper case except for the x in 0x
+fn draw_text(canvas: &mut Canvas<Window>, x: i32, y: i32, text: &str, colour: Color) {
+    let mut rects = Vec::new();
+
+    for (pos, c) in text.chars().enumerate() {
+        let glyph = glyph(c);
+        let left = x + pos as i32 * CHAR_WIDTH;
+
+        for row in 0..5 {
+            for col in 0..3 {
+                if glyph & (1 << (14 - row * 3 - col)) != 0 {
+                    rects.push(Rect::new(left + col, y + row, 1, 1));
+                }
+            }
+        }
+    }
+
+    canvas.set_draw_color(colour);
+    canvas.fill_rects(&rects).unwrap();
+}
+
+// 5 rows of 3 pixels, top row in the high bits
+fn glyph(c: char) -> u16 {
+    let c = if c == 'x' { c } else { c.to_ascii_uppercase() };
+
+    match c {
+        ' ' => 0,
+        '0' => 0b111_101_101_101_111,
+        '1' => 0b010_110_010_010_111,
+        '2' => 0b111_001_111_100_111,
+        '3' => 0b111_001_111_001_111,
+        '4' => 0b101_101_111_001_001,
+        '5' => 0b111_100_111_001_111,
+        '6' => 0b111_100_111_101_111,
+        '7' => 0b111_001_001_001_001,
+        '8' => 0b111_101_111_101_111,
+        '9' => 0b111_101_111_001_111,
+        'A' => 0b010_101_111_101_101,
+        'B' => 0b110_101_110_101_110,
+        'C' => 0b011_100_100_100_011,
+        'D' => 0b110_101_101_101_110,
+        'E' => 0b111_100_110_100_111,
+        'F' => 0b111_100_110_100_100,
+        'G' => 0b011_100_101_101_011,
+        'H' => 0b101_101_111_101_101,
+        'I' => 0b111_010_010_010_111,
+        'J' => 0b001_001_001_101_010,
+        'K' => 0b101_101_110_101_101,
+        'L' => 0b100_100_100_100_111,
+        'M' => 0b101_111_111_101_101,
+        'N' => 0b110_101_101_101_101,
+        'O' => 0b010_101_101_101_010,
+        'P' => 0b110_101_110_100_100,
+        'Q' => 0b010_101_101_110_011,
+        'R' => 0b110_101_110_101_101,
+        'S' => 0b011_100_010_001_110,
+        'T' => 0b111_010_010_010_010,
+        'U' => 0b101_101_101_101_111,
+        'V' => 0b101_101_101_101_010,
+        'W' => 0b101_101_111_111_101,
+        'X' => 0b101_101_010_101_101,
+        'Y' => 0b101_101_010_010_010,
+        'Z' => 0b111_001_010_100_111,
+        'x' => 0b000_000_101_010_101,
+        ':' => 0b000_010_000_010_000,
+        ',' => 0b000_000_000_010_100,
+        '.' => 0b000_000_000_000_010,
+        '=' => 0b000_111_000_111_000,
+        '>' => 0b100_010_001_010_100,
+        '<' => 0b001_010_100_010_001,
+        '-' => 0b000_000_111_000_000,
+        '+' => 0b000_010_111_010_000,
+        '*' => 0b101_010_111_010_101,
+        '#' => 0b101_111_101_111_101,
+        '$' => 0b011_110_010_011_110,
+        '[' => 0b110_100_100_100_110,
+        ']' => 0b011_001_001_001_011,
+        '(' => 0b010_100_100_100_010,
+        ')' => 0b010_001_001_001_010,
+        '/' => 0b001_001_010_100_100,
+        '_' => 0b000_000_000_000_111,
+        '!' => 0b010_010_010_000_010,
+        _ => 0b111_001_010_000_010, // ?
+    }
+}
@@ -0,0 +1,90 @@
+use pok_8_core::emu::Emu;
+use pok_8_core::movie::Movie;
+
+use std::error::Error;
+use std::fs;
+use std::path::PathBuf;
+
+// Sits between the keyboard and Emu. Live keys go straight through (and end up in the movie when recording),
+// during playback the movie drives the keypad and live keys are ignored until it runs out.
+pub enum Recorder {
+    Off,
+    Recording { movie: Movie, path: PathBuf },
+    Playing { movie: Movie, frame: usize },
+}
+
+impl Recorder {
+    pub fn key_down(&self, emu: &mut Emu, key: usize) {
+        if !self.is_playing() {
+            emu.key_down(key);
+        }
+    }
+
+    pub fn key_up(&self, emu: &mut Emu, key: usize) {
+        if !self.is_playing() {
+            emu.key_up(key);
+        }
+    }
+
+    pub fn is_playing(&self) -> bool {
+        matches!(self, Recorder::Playing { .. })
+    }
+
+    // Frames recorded or played so far, None with no movie going
+    pub fn frame(&self) -> Option<usize> {
+        match self {
+            Recorder::Off => None,
+            Recorder::Recording { movie, .. } => Some(movie.frames.len()),
+            Recorder::Playing { frame, .. } => Some(*frame),
+        }
+    }
+
+    // Call before running a frame. Returns true when playback has just run out, control goes back to the
+    // keyboard from here on
+    pub fn before_frame(&mut self, emu: &mut Emu) -> bool {
+        let Recorder::Playing { movie, frame } = self else {
+            return false;
+        };
+
+        if movie.apply(emu, *frame) {
+            return false;
+        }
+
+        match movie.end_hash {
+            Some(hash) if hash == emu.state_hash() => {
+                println!("Movie finished after {} frames, matching the recording", frame)
+            }
+            Some(_) => println!("WARNING:  Movie finished after {} frames but the state differs from the recording", frame),
+            None => println!("Movie finished after {} frames", frame),
+        }
+
+        *self = Recorder::Off;
+        true
+    }
+
+    pub fn after_frame(&mut self, emu: &Emu) {
+        match self {
+            Recorder::Off => (),
+            Recorder::Recording { movie, .. } => movie.record(emu),
+            Recorder::Playing { frame, .. } => *frame += 1,
+        }
+    }
+
+    // Rewinding while recording takes the frame back out of the movie too
+    pub fn rewound(&mut self) {
+        if let Recorder::Recording { movie, .. } = self {
+            movie.frames.pop();
+        }
+    }
+
+    // Write the movie out, stamped with the final state so playback can tell if it went the same way
+    pub fn finish(&mut self, emu: &Emu) -> Result<(), Box<dyn Error>> {
+        if let Recorder::Recording { movie, path } = self {
+            movie.end_hash = Some(emu.state_hash());
+            fs::write(&path, movie.to_bytes())?;
+            println!("Recorded {} frames to {}", movie.frames.len(), path.display());
+        }
+
+        Ok(())
+    }
+}
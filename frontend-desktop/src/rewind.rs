@@ -0,0 +1,138 @@
+use std::collections::VecDeque;
+
+// Rewind history, one save state per frame.
+// Only the newest state is kept whole, each older one is stored as the XOR against the state after it,
+// run length encoded since a frame hardly changes any of RAM. Stepping back XORs the newest state
+// with the next delta, so dropping the oldest entry when the buffer is full costs nothing.
+// States grow and shrink with the call stack, so a delta also records how long the older state was.
+pub struct Rewind {
+    newest: Option<Vec<u8>>,
+    deltas: VecDeque<Vec<u8>>, // front is the frame before newest
+    depth: usize,
+}
+
+impl Rewind {
+    // start is the state the history begins from, so rewinding can go all the way back to it
+    pub fn new(depth: usize, start: Vec<u8>) -> Self {
+        let mut rewind = Self {
+            newest: None,
+            deltas: VecDeque::with_capacity(depth),
+            depth,
+        };
+        rewind.push(start);
+
+        rewind
+    }
+
+    pub fn push(&mut self, state: Vec<u8>) {
+        if self.depth == 0 {
+            return;
+        }
+
+        if let Some(prev) = self.newest.take() {
+            self.deltas.push_front(encode(&prev, &state));
+            self.deltas.truncate(self.depth);
+        }
+
+        self.newest = Some(state);
+    }
+
+    // The state one frame further back, None once the history runs out
+    pub fn step_back(&mut self) -> Option<&[u8]> {
+        let delta = self.deltas.pop_front()?;
+        let newest = self.newest.as_mut()?;
+
+        apply(newest, &delta);
+        Some(newest)
+    }
+}
+
+// Delta = prev's length, then runs of (unchanged byte count, changed byte count, changed bytes XORed) over the
+// longer of the two with the shorter one padded out with zeros, all counts as LEB128
+fn encode(prev: &[u8], next: &[u8]) -> Vec<u8> {
+    let mut out = Vec::new();
+    write_len(&mut out, prev.len());
+
+    let len = prev.len().max(next.len());
+    let (prev, next) = (padded(prev, len), padded(next, len));
+    let mut pos = 0;
+
+    while pos < next.len() {
+        let same = prev[pos..]
+            .iter()
+            .zip(&next[pos..])
+            .take_while(|(a, b)| a == b)
+            .count();
+        pos += same;
+
+        let changed = prev[pos..]
+            .iter()
+            .zip(&next[pos..])
+            .take_while(|(a, b)| a != b)
+            .count();
+
+        write_len(&mut out, same);
+        write_len(&mut out, changed);
+        out.extend(prev[pos..(pos + changed)].iter().zip(&next[pos..]).map(|(a, b)| a ^ b));
+        pos += changed;
+    }
+
+    out
+}
+
+fn apply(state: &mut Vec<u8>, delta: &[u8]) {
+    let mut pos = 0;
+    let mut cursor = 0;
+
+    let len = read_len(delta, &mut cursor);
+    state.resize(state.len().max(len), 0);
+
+    while cursor < delta.len() {
+        pos += read_len(delta, &mut cursor);
+        let changed = read_len(delta, &mut cursor);
+
+        for (byte, xor) in state[pos..(pos + changed)].iter_mut().zip(&delta[cursor..]) {
+            *byte ^= xor;
+        }
+        pos += changed;
+        cursor += changed;
+    }
+
+    state.truncate(len);
+}
+
+fn padded(state: &[u8], len: usize) -> Vec<u8> {
+    let mut state = state.to_vec();
+    state.resize(len, 0);
+    state
+}
+
+fn write_len(out: &mut Vec<u8>, mut len: usize) {
+    loop {
+        let byte = (len & 0x7F) as u8;
+        len >>= 7;
+
+        if len == 0 {
+            out.push(byte);
+            return;
+        }
+        out.push(byte | 0x80);
+    }
+}
+
+fn read_len(data: &[u8], cursor: &mut usize) -> usize {
+    let mut len = 0;
+    let mut shift = 0;
+
+    while let Some(byte) = data.get(*cursor) {
+        *cursor += 1;
+        len |= ((byte & 0x7F) as usize) << shift;
+        shift += 7;
+
+        if byte & 0x80 == 0 {
+            break;
+        }
+    }
+
+    len
+}
@@ -1,36 +1,133 @@
+use crate::audio::SdlAudio;
+use crate::overlay;
+use crate::panels::{self, DebugPanels};
+use crate::recorder::Recorder;
+use crate::rewind::Rewind;
+
 use pok_8_core::emu::*;
+use pok_8_core::error::EmuError;
+use pok_8_core::movie::Movie;
+use pok_8_core::profile::Profiler;
+use pok_8_core::speaker::Tone;
+use pok_8_core::trace::{TraceOptions, Tracer};
 
 use sdl2::event::Event;
+use sdl2::EventPump;
 use sdl2::image::LoadSurface;
-use sdl2::keyboard::Keycode;
+use sdl2::keyboard::{Keycode, Mod};
 use sdl2::{pixels::Color, rect::Rect, render::Canvas, video::Window};
 
-use std::fs::File;
+use std::error::Error;
+use std::fs::{self, File};
 use std::io::Read;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 
 
 const BLACK: Color = Color::RGB(0, 0, 0);
 const WHITE: Color = Color::RGB(255, 255, 255);
+const LIGHT_GREY: Color = Color::RGB(170, 170, 170);
+const DARK_GREY: Color = Color::RGB(85, 85, 85);
+
+// background, plane 1, plane 2, both planes
+const DEFAULT_PALETTE: [Color; 4] = [BLACK, WHITE, LIGHT_GREY, DARK_GREY];
 
 const DEFAULT_SCALE: u32 = 15;
 const WINDOW_WIDTH: u32 = SCREEN_WIDTH as u32;
 const WINDOW_HEIGHT: u32 = SCREEN_HEIGHT as u32;
-const TICKS_PER_FRAME: usize = 10;
+const DEFAULT_REWIND_FRAMES: usize = 600; // 10 seconds
+const REWIND_KEY: Keycode = Keycode::Backspace;
+const PAUSE_KEY: Keycode = Keycode::P;
+const FRAME_ADVANCE_KEY: Keycode = Keycode::N; // runs a single frame while paused
+const DEBUG_KEY: Keycode = Keycode::Tab; // shows and hides the debug panels
+const STEP_KEY: Keycode = Keycode::I; // runs a single instruction while paused with the panels up
+const PROFILE_ROWS: usize = 20; // of each table in the profile summary
+
+// F1-F9 load the matching slot, Shift + F1-F9 saves to it
+const SAVE_SLOT_KEYS: [Keycode; 9] = [
+    Keycode::F1,
+    Keycode::F2,
+    Keycode::F3,
+    Keycode::F4,
+    Keycode::F5,
+    Keycode::F6,
+    Keycode::F7,
+    Keycode::F8,
+    Keycode::F9,
+];
+
+// Settings picked on the command line
+pub struct Options {
+    pub scale: u32,
+    pub config: EmuConfig, // quirks, seed and rng, a seed of None picks a new one every run
+    pub palette: [Color; 4],
+    pub tone: Tone,
+    pub muted: bool,
+    pub rewind_frames: usize, // frames of history kept for rewinding, 0 turns it off
+    pub record: Option<PathBuf>, // write the session out as a movie
+    pub play: Option<PathBuf>,   // play a movie back, its quirks and seed win over the ones above
+    pub debug: bool,             // start with the debug panels showing
+    pub trace: Option<PathBuf>,  // log every instruction to this file
+    pub trace_options: TraceOptions,
+    pub profile: Option<PathBuf>, // write a profile here on the way out, and folded stacks next to it
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            scale: DEFAULT_SCALE,
+            config: EmuConfig::default(),
+            palette: DEFAULT_PALETTE,
+            tone: Tone::default(),
+            muted: false,
+            rewind_frames: DEFAULT_REWIND_FRAMES,
+            record: None,
+            play: None,
+            debug: false,
+            trace: None,
+            trace_options: TraceOptions::default(),
+            profile: None,
+        }
+    }
+}
+
+impl Options {
+    // Four comma separated RRGGBB colours, e.g. 000000,FFFFFF,AAAAAA,555555
+    pub fn parse_palette(text: &str) -> Option<[Color; 4]> {
+        let mut palette = DEFAULT_PALETTE;
+        let colours: Vec<&str> = text.split(',').map(|c| c.trim().trim_start_matches('#')).collect();
+
+        if colours.len() != palette.len() {
+            return None;
+        }
+
+        for (slot, hex) in palette.iter_mut().zip(colours) {
+            if hex.len() != 6 {
+                return None;
+            }
+            let rgb = u32::from_str_radix(hex, 16).ok()?;
+            *slot = Color::RGB((rgb >> 16) as u8, (rgb >> 8) as u8, rgb as u8);
+        }
+
+        Some(palette)
+    }
+}
 
 pub struct POK8;
 
 impl POK8 {
     // TODO: try to refacto this into multiple functions? SDL doesn't seem to like that
-    pub fn init(path_to_rom: impl Into<PathBuf>, scale: Option<u32>) -> () {
+    pub fn init(path_to_rom: impl Into<PathBuf>, options: Options) -> Result<(), Box<dyn Error>> {
         
-        let scale = scale.unwrap_or(DEFAULT_SCALE);
+        let scale = options.scale;
         
         // Setup SDL
         let sdl_context = sdl2::init().unwrap();
         let video_subsystem = sdl_context.video().unwrap();
+        let (width, height) = Self::window_size(scale, options.debug);
         let mut window = video_subsystem
-            .window("POK8 Emulator", WINDOW_WIDTH * scale, WINDOW_HEIGHT * scale)
+            .window("POK8 Emulator", width, height)
             .position_centered()
             .opengl()
             .build()
@@ -45,15 +142,68 @@ impl POK8 {
 
         let mut event_pump = sdl_context.event_pump().unwrap();
 
-        let mut pok8 = Emu::init();
-
-        let mut rom = File::open(&path_to_rom.into()).expect("Unable to open file");
+        let path_to_rom = path_to_rom.into();
+        let mut rom = File::open(&path_to_rom)?;
         let mut buffer = Vec::new();
+        rom.read_to_end(&mut buffer)?;
+
+        // a movie only replays exactly from power on, so it builds the Emu itself
+        let (mut pok8, mut recorder) = match (&options.play, &options.record) {
+            (Some(path), _) => {
+                let movie = Movie::from_bytes(&fs::read(path)?)?;
+                (movie.start(&buffer)?, Recorder::Playing { movie, frame: 0 })
+            }
+            (None, record) => {
+                let mut pok8 = Emu::with_config(options.config);
+                pok8.load(&buffer)?;
+
+                let recorder = match record {
+                    Some(path) => Recorder::Recording {
+                        movie: Movie::new(&pok8, &buffer),
+                        path: path.clone(),
+                    },
+                    None => Recorder::Off,
+                };
+                (pok8, recorder)
+            }
+        };
+
+        if let Some(path) = &options.trace {
+            pok8.set_tracer(Tracer::to_file(path, options.trace_options.clone())?);
+        }
+        if options.profile.is_some() {
+            pok8.set_profiler(Profiler::new());
+        }
+
+        // no audio device just means no sound, M still flips the flag so nothing changes for the player
+        let mut muted = Arc::new(AtomicBool::new(options.muted));
+        match sdl_context.audio().and_then(|audio| SdlAudio::init(&audio, options.tone)) {
+            Ok(sink) => {
+                muted = sink.mute_handle();
+                muted.store(options.muted, Ordering::Relaxed);
+                pok8.set_audio_sink(Box::new(sink));
+            }
+            Err(err) => println!("WARNING:  Running without sound, {}", err),
+        }
+
+        // SUPER-CHIP RPL flags live next to the ROM so each game keeps its own, movies start without them
+        let use_rpl = matches!(recorder, Recorder::Off);
+        let rpl_path = Self::rom_sibling(&path_to_rom, "rpl");
+        if let (true, Ok(flags)) = (use_rpl, fs::read(&rpl_path)) {
+            pok8.set_rpl_flags(&flags);
+        }
+        let saved_rpl = pok8.rpl_flags().to_vec();
 
-        rom.read_to_end(&mut buffer).unwrap();
-        pok8.load(&buffer);
+        let mut rewind = Rewind::new(options.rewind_frames, pok8.save_state());
+        let mut rewinding = false;
+        let mut paused = false;
+        let mut advance = false;
+        let mut panels = DebugPanels::new(options.debug);
+        let mut frame_cycle = 0; // instructions of the current frame already stepped through with STEP_KEY
+        let mut slot_loaded = false; // the event loop has the pump borrowed, so the keys are put right after it
 
-        'gameloop: loop {
+        // a ROM error ends the loop like quitting does, so the movie, trace and profile still get written
+        let result: Result<(), Box<dyn Error>> = 'gameloop: loop {
             for evt in event_pump.poll_iter() {
                 match evt {
                     Event::Quit { .. }
@@ -61,56 +211,290 @@ impl POK8 {
                         keycode: Some(Keycode::Escape),
                         ..
                     } => {
-                        break 'gameloop;
+                        break 'gameloop Ok(());
+                    }
+                    Event::KeyDown {
+                        keycode: Some(REWIND_KEY),
+                        ..
+                    } => {
+                        // a movie can't be played backwards, only recorded over
+                        rewinding = !recorder.is_playing();
+                    }
+                    Event::KeyUp {
+                        keycode: Some(REWIND_KEY),
+                        ..
+                    } => {
+                        rewinding = false;
+                    }
+                    Event::KeyDown {
+                        keycode: Some(Keycode::M),
+                        repeat: false,
+                        ..
+                    } => {
+                        muted.fetch_xor(true, Ordering::Relaxed);
+                    }
+                    Event::KeyDown {
+                        keycode: Some(PAUSE_KEY),
+                        repeat: false,
+                        ..
+                    } => {
+                        paused = !paused;
+                    }
+                    Event::KeyDown {
+                        keycode: Some(FRAME_ADVANCE_KEY),
+                        ..
+                    } => {
+                        advance = paused;
+                    }
+                    Event::KeyDown {
+                        keycode: Some(DEBUG_KEY),
+                        repeat: false,
+                        ..
+                    } => {
+                        panels.visible = !panels.visible;
+                        let (width, height) = Self::window_size(scale, panels.visible);
+                        if let Err(err) = canvas.window_mut().set_size(width, height) {
+                            break 'gameloop Err(err.into());
+                        }
+                    }
+                    Event::KeyDown {
+                        keycode: Some(STEP_KEY),
+                        ..
+                    } if panels.visible && paused => {
+                        // a movie only knows whole frames, stepping inside one would throw it out of sync
+                        if !matches!(recorder, Recorder::Off) {
+                            println!("WARNING:  Can't step single instructions while a movie is recording or playing");
+                        } else {
+                            if let Err(err) = pok8.cycle() {
+                                break 'gameloop Err(err.into());
+                            }
+                            frame_cycle += 1;
+
+                            if frame_cycle == CYCLES_PER_FRAME {
+                                if let Err(err) = Self::finish_frame(&mut pok8, &mut frame_cycle) {
+                                    break 'gameloop Err(err.into());
+                                }
+                                rewind.push(pok8.save_state());
+                            }
+                        }
+                    }
+                    Event::KeyDown {
+                        keycode: Some(key @ (Keycode::PageUp | Keycode::PageDown | Keycode::Up | Keycode::Down | Keycode::Home)),
+                        ..
+                    } if panels.visible => match key {
+                        Keycode::PageUp => panels.scroll(&pok8, -16),
+                        Keycode::PageDown => panels.scroll(&pok8, 16),
+                        Keycode::Up => panels.scroll(&pok8, -1),
+                        Keycode::Down => panels.scroll(&pok8, 1),
+                        _ => panels.follow_i(&pok8),
+                    },
+                    Event::MouseWheel { y, .. } if panels.visible => {
+                        panels.scroll(&pok8, -y);
+                    }
+                    Event::KeyDown {
+                        keycode: Some(key),
+                        keymod,
+                        repeat: false,
+                        ..
+                    } if SAVE_SLOT_KEYS.contains(&key) => {
+                        let slot = SAVE_SLOT_KEYS.iter().position(|k| *k == key).unwrap_or(0) + 1;
+                        let saving = keymod.intersects(Mod::LSHIFTMOD | Mod::RSHIFTMOD);
+
+                        // loading would jump the movie somewhere its inputs never took it
+                        if !saving && !matches!(recorder, Recorder::Off) {
+                            println!("WARNING:  Save slots can't be loaded while a movie is recording or playing");
+                        // a bad slot shouldn't end the game, just say what happened
+                        } else if let Err(err) = Self::use_save_slot(&mut pok8, &path_to_rom, slot, saving) {
+                            println!("WARNING:  Save slot {}: {}", slot, err);
+                        // so rewinding comes back to the loaded state rather than jumping past it
+                        } else if !saving {
+                            rewind.push(pok8.save_state());
+                            slot_loaded = true;
+                        }
                     }
                     Event::KeyDown {
                         keycode: Some(key), ..
                     } => {
                         if let Some(k) = Self::key2btn(key) {
-                            pok8.key_down(k);
+                            recorder.key_down(&mut pok8, k);
                         }
                     }
                     Event::KeyUp {
                         keycode: Some(key), ..
                     } => {
                         if let Some(k) = Self::key2btn(key) {
-                            pok8.key_up(k);
+                            recorder.key_up(&mut pok8, k);
                         }
                     }
                     _ => (),
                 }
             }
 
-            for _ in 0..TICKS_PER_FRAME {
-                pok8.cycle();
+            // a state brings back the keys held when it was saved, the player's hands are where they are now
+            if slot_loaded {
+                pok8.set_keys_mask(Self::held_keys(&event_pump));
+                slot_loaded = false;
             }
-            pok8.tick_timers();
-            Self::draw_screen(&pok8, &mut canvas, scale);
-        }
+
+            // while the key is held go back a frame at a time, letting go carries on from there
+            if rewinding {
+                if let Some(state) = rewind.step_back() {
+                    if let Err(err) = pok8.load_state(state) {
+                        break 'gameloop Err(err.into());
+                    }
+                    pok8.set_keys_mask(Self::held_keys(&event_pump));
+                    recorder.rewound();
+                    frame_cycle = 0;
+                }
+            } else if !paused || advance {
+                // pause on the last frame of a movie so it can be looked over before playing on
+                if recorder.before_frame(&mut pok8) {
+                    paused = true;
+                } else {
+                    if let Err(err) = Self::finish_frame(&mut pok8, &mut frame_cycle) {
+                        break 'gameloop Err(err.into());
+                    }
+                    recorder.after_frame(&pok8);
+                    rewind.push(pok8.save_state());
+                }
+                advance = false;
+            }
+
+            if panels.visible {
+                let status = Self::debug_status(paused, frame_cycle, recorder.frame());
+                panels.draw(&pok8, &mut canvas, &status);
+                Self::draw_pixels(&pok8, &mut canvas, &options.palette, Rect::new(0, 0, panels::GAME_WIDTH, panels::GAME_HEIGHT));
+            } else {
+                Self::draw_screen(&pok8, &mut canvas, &options.palette);
+                if let Some(frame) = recorder.frame() {
+                    overlay::draw_counter(&mut canvas, frame, (options.palette[0], options.palette[1]));
+                }
+            }
+            canvas.present();
+
+            if pok8.is_halted() {
+                break 'gameloop Ok(());
+            }
+        };
+
+        let finished = (|| -> Result<(), Box<dyn Error>> {
+            recorder.finish(&pok8)?;
+
+            if let Some(tracer) = pok8.take_tracer() {
+                tracer.finish()?;
+            }
+
+            if let (Some(path), Some(profiler)) = (&options.profile, pok8.take_profiler()) {
+                fs::write(path, profiler.summary(PROFILE_ROWS))?;
+                fs::write(Self::rom_sibling(path, "folded"), profiler.folded())?;
+                println!("Wrote the profile to {}", path.display());
+            }
+
+            if use_rpl && pok8.rpl_flags() != saved_rpl.as_slice() {
+                fs::write(&rpl_path, pok8.rpl_flags())?;
+            }
+
+            Ok(())
+        })();
+
+        // the ROM's error is the one worth reporting, the files above are written either way
+        result.and(finished)
     }
 }
 
 impl POK8 {
-    fn draw_screen(emu: &Emu, canvas: &mut Canvas<Window>, scale: u32) {
-        // Clear canvas as black
-        canvas.set_draw_color(BLACK);
-        canvas.clear();
+    fn window_size(scale: u32, debug: bool) -> (u32, u32) {
+        if debug {
+            (panels::LAYOUT_WIDTH * panels::LAYOUT_SCALE, panels::LAYOUT_HEIGHT * panels::LAYOUT_SCALE)
+        } else {
+            (WINDOW_WIDTH * scale, WINDOW_HEIGHT * scale)
+        }
+    }
+
+    // Runs whatever of the frame STEP_KEY hasn't already, all of it most of the time, then ticks the timers
+    fn finish_frame(emu: &mut Emu, frame_cycle: &mut usize) -> Result<(), EmuError> {
+        for _ in *frame_cycle..CYCLES_PER_FRAME {
+            emu.cycle()?;
+        }
+        emu.tick_timers();
+        *frame_cycle = 0;
+
+        Ok(())
+    }
+
+    fn debug_status(paused: bool, frame_cycle: usize, movie_frame: Option<usize>) -> String {
+        let mut status = if paused { format!("PAUSED {}/{}", frame_cycle, CYCLES_PER_FRAME) } else { "RUNNING".to_string() };
+        if let Some(frame) = movie_frame {
+            status.push_str(&format!("  MOVIE FRAME {}", frame));
+        }
 
+        status.push_str("    TAB PANELS  P PAUSE  N FRAME  I INSTRUCTION  ESC QUIT");
+        status
+    }
+
+    fn draw_screen(emu: &Emu, canvas: &mut Canvas<Window>, palette: &[Color; 4]) {
         let screen_buf = emu.get_display();
-        // Now set draw color to white, iterate through each point and see if it should be drawn
-        canvas.set_draw_color(WHITE);
-        for (i, pixel) in screen_buf.iter().enumerate() {
-            if *pixel {
-                // Convert our 1D array's index into a 2D (x,y) position
-                let x = (i % SCREEN_WIDTH) as u32;
-                let y = (i / SCREEN_WIDTH) as u32;
-
-                // Draw a rectangle at (x,y), scaled up by our SCALE value
-                let rect = Rect::new((x * scale) as i32, (y * scale) as i32, scale, scale);
-                canvas.fill_rect(rect).unwrap();
-            }
+
+        // Let SDL stretch the emulated resolution over the window, lores and hires share the 2:1 aspect
+        canvas
+            .set_logical_size(screen_buf.width as u32, screen_buf.height as u32)
+            .unwrap();
+
+        Self::draw_pixels(emu, canvas, palette, Rect::new(0, 0, screen_buf.width as u32, screen_buf.height as u32));
+    }
+
+    // The framebuffer over area, which should be a whole multiple of the resolution
+    fn draw_pixels(emu: &Emu, canvas: &mut Canvas<Window>, palette: &[Color; 4], area: Rect) {
+        let screen_buf = emu.get_display();
+        let size = area.width() / screen_buf.width as u32;
+
+        // Fill with the background colour
+        canvas.set_draw_color(palette[0]);
+        canvas.fill_rect(area).unwrap();
+
+        // Now draw every pixel of each of the other colours in one go
+        for (colour, draw_color) in palette.iter().enumerate().skip(1) {
+            let rects: Vec<Rect> = screen_buf
+                .pixels
+                .iter()
+                .enumerate()
+                .filter(|(_, pixel)| **pixel as usize == colour)
+                .map(|(i, _)| {
+                    // Convert our 1D array's index into a 2D (x,y) position
+                    let x = (i % screen_buf.width) as i32;
+                    let y = (i / screen_buf.width) as i32;
+                    Rect::new(area.x() + x * size as i32, area.y() + y * size as i32, size, size)
+                })
+                .collect();
+
+            canvas.set_draw_color(*draw_color);
+            canvas.fill_rects(&rects).unwrap();
         }
-        canvas.present();
+    }
+
+    // path/to/GAME + ext => path/to/GAME.ext
+    fn rom_sibling(rom: &Path, ext: &str) -> PathBuf {
+        let mut path = rom.as_os_str().to_owned();
+        path.push(".");
+        path.push(ext);
+        PathBuf::from(path)
+    }
+
+    // Slots are GAME.stateN, saving also writes GAME.stateN.json for poking around in
+    fn use_save_slot(emu: &mut Emu, rom: &Path, slot: usize, saving: bool) -> Result<(), Box<dyn Error>> {
+        let path = Self::rom_sibling(rom, &format!("state{}", slot));
+
+        if saving {
+            let state = emu.snapshot();
+            fs::write(&path, state.to_bytes())?;
+            fs::write(Self::rom_sibling(&path, "json"), state.to_json())?;
+            println!("Saved slot {}", slot);
+        } else {
+            emu.load_state(&fs::read(&path)?)?;
+            println!("Loaded slot {}", slot);
+        }
+
+        Ok(())
     }
 
     /*
@@ -126,6 +510,16 @@ impl POK8 {
        +---+---+---+---+           +---+---+---+---+
     */
 
+    // The keypad keys down on the keyboard right now, as a mask for Emu::set_keys_mask
+    fn held_keys(event_pump: &EventPump) -> u16 {
+        event_pump
+            .keyboard_state()
+            .pressed_scancodes()
+            .filter_map(Keycode::from_scancode)
+            .filter_map(Self::key2btn)
+            .fold(0, |mask, key| mask | (1 << key))
+    }
+
     fn key2btn(key: Keycode) -> Option<usize> {
         match key {
             Keycode::Num1 => Some(0x1),
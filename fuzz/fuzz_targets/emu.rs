@@ -0,0 +1,6 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use pok_8_fuzz::EmuInput;
+
+fuzz_target!(|input: EmuInput| pok_8_fuzz::emu(&input));
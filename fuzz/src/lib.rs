@@ -0,0 +1,113 @@
+// What the fuzz targets run, kept out of fuzz_targets/ so tests/regressions.rs replays saved inputs through exactly
+// the same code. Errors from the core are fine, the ROMs are garbage after all, panics and broken invariants aren't
+use arbitrary::Arbitrary;
+use pok_8_core::emu::*;
+use pok_8_core::instruction::Instruction;
+use pok_8_core::quirks::{IncrementI, Quirks};
+
+#[derive(Debug, Clone, Arbitrary)]
+pub struct EmuInput {
+    pub quirks: QuirkFlags,
+    pub seed: u64,
+    pub rom: Vec<u8>,
+    pub steps: Vec<Step>,
+}
+
+// Quirks as plain fields, so every combination gets tried rather than just the presets
+#[derive(Debug, Clone, Arbitrary)]
+pub struct QuirkFlags {
+    pub shift_uses_vy: bool,
+    pub increment_i: u8, // Unchanged, ByX or ByXPlusOne, mod 3
+    pub jump_uses_vx: bool,
+    pub vf_reset: bool,
+    pub clip_sprites: bool,
+    pub display_wait: bool,
+    pub stack_depth: u8, // none at all up to far deeper than any real interpreter's
+}
+
+#[derive(Debug, Clone, Arbitrary)]
+pub enum Step {
+    Cycles(u8),
+    TickTimers,
+    KeyDown(u8), // any index, key_down ignores the ones past F
+    KeyUp(u8),
+}
+
+impl QuirkFlags {
+    pub fn quirks(&self) -> Quirks {
+        Quirks {
+            shift_uses_vy: self.shift_uses_vy,
+            increment_i: match self.increment_i % 3 {
+                0 => IncrementI::Unchanged,
+                1 => IncrementI::ByX,
+                _ => IncrementI::ByXPlusOne,
+            },
+            jump_uses_vx: self.jump_uses_vx,
+            vf_reset: self.vf_reset,
+            clip_sprites: self.clip_sprites,
+            display_wait: self.display_wait,
+            stack_depth: self.stack_depth as usize,
+        }
+    }
+}
+
+// Loads the ROM and plays the steps, a run of cycles stopping at the first error
+pub fn emu(input: &EmuInput) {
+    let quirks = input.quirks.quirks();
+    let mut emu = Emu::with_config(EmuConfig {
+        quirks,
+        seed: Some(input.seed),
+        ..EmuConfig::default()
+    });
+
+    if emu.load(&input.rom).is_err() {
+        return;
+    }
+
+    for step in &input.steps {
+        match step {
+            Step::Cycles(count) => {
+                for _ in 0..*count {
+                    if emu.cycle().is_err() {
+                        break;
+                    }
+                }
+            }
+            Step::TickTimers => emu.tick_timers(),
+            Step::KeyDown(key) => emu.key_down(*key as usize),
+            Step::KeyUp(key) => emu.key_up(*key as usize),
+        }
+
+        check(&emu, &quirks);
+    }
+
+    emu.state_hash();
+    let _ = emu.next_instruction();
+}
+
+// What has to hold whatever the ROM did
+fn check(emu: &Emu, quirks: &Quirks) {
+    assert!(
+        emu.stack().len() <= quirks.stack_depth,
+        "{} return addresses on a stack {} deep",
+        emu.stack().len(),
+        quirks.stack_depth
+    );
+
+    let display = emu.get_display();
+    assert_eq!((display.width, display.height), emu.resolution());
+    assert_eq!(display.pixels.len(), display.width * display.height);
+    assert!(display.pixels.iter().all(|pixel| *pixel < 4), "pixel outside the four plane combinations");
+}
+
+// Every opcode that decodes has to encode back to itself, read as big endian words like the ROM would be
+pub fn decode(data: &[u8]) {
+    for pair in data.chunks_exact(2) {
+        let word = u16::from_be_bytes([pair[0], pair[1]]);
+
+        if let Ok(op) = Instruction::decode(word) {
+            assert_eq!(op.encode(), word, "{:04X} decodes to {:?}, which encodes to {:04X}", word, op, op.encode());
+            assert_eq!(Instruction::decode(op.encode()), Ok(op));
+        }
+    }
+}
@@ -0,0 +1,108 @@
+// Replays inputs through the fuzz harness with plain `cargo test`, no nightly needed. Anything the fuzzer finds goes
+// in regressions/<target>/ as it came out of artifacts/, once it's fixed. The ROMs below are the edge cases it has to
+// get through, written out so they stay covered whatever the corpus looks like
+use arbitrary::{Arbitrary, Unstructured};
+use pok_8_core::asm::assemble;
+use pok_8_fuzz::{EmuInput, QuirkFlags, Step};
+
+use std::fs;
+use std::path::Path;
+
+// Every saved file for a target, skipping dotfiles like .gitkeep
+fn saved(target: &str) -> Vec<(String, Vec<u8>)> {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("regressions").join(target);
+    let mut files: Vec<_> = fs::read_dir(&dir)
+        .unwrap_or_else(|err| panic!("could not read {}: {}", dir.display(), err))
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| !path.file_name().unwrap().to_string_lossy().starts_with('.'))
+        .collect();
+    files.sort();
+
+    files
+        .into_iter()
+        .map(|path| (path.display().to_string(), fs::read(&path).unwrap()))
+        .collect()
+}
+
+#[test]
+fn saved_emu_inputs() {
+    for (name, data) in saved("emu") {
+        // how libfuzzer-sys builds a fuzz_target's argument
+        let input = EmuInput::arbitrary_take_rest(Unstructured::new(&data))
+            .unwrap_or_else(|err| panic!("{} doesn't make an input: {}", name, err));
+        pok_8_fuzz::emu(&input);
+    }
+}
+
+#[test]
+fn saved_decode_inputs() {
+    for (_, data) in saved("decode") {
+        pok_8_fuzz::decode(&data);
+    }
+}
+
+#[test]
+fn every_opcode_round_trips() {
+    let words: Vec<u8> = (0..=u16::MAX).flat_map(u16::to_be_bytes).collect();
+    pok_8_fuzz::decode(&words);
+}
+
+// Each ROM under every combination of quirks, with stacks from none at all to deeper than any real interpreter's
+#[test]
+fn edge_case_roms() {
+    let roms = [
+        ("recursion", "start:\n    CALL start"),
+        ("RET with nothing to return to", "    RET"),
+        ("VTORAM at the top of RAM", "    LONGI 0xFFFC\n    VTORAM V3\n    VTORAM V3\n    LONGI 0xFFFA\n    VTORAM VF"),
+        ("RAMTOV at the top of RAM", "    LONGI 0xFFFC\n    RAMTOV V3\n    RAMTOV V3\n    LONGI 0xFFFA\n    RAMTOV VF"),
+        ("SAVERANGE and LOADRANGE at the top of RAM", "    LONGI 0xFFFE\n    LOADRANGE V0, VF\n    SAVERANGE VF, V0"),
+        ("BCDTORAM at the last byte", "    LONGI 0xFFFF\n    BCDTORAM V0"),
+        ("INCSETIV past the top of RAM", "    LONGI 0xFFFF\n    SETVNN V1, 0xFF\n    INCSETIV V1\n    RAMTOV VF"),
+        ("LOADAUDIO at the top of RAM", "    LONGI 0xFFF8\n    LOADAUDIO"),
+        (
+            "DRAW reading past the end of RAM",
+            "    SETPLANES 3\n    LONGI 0xFFFF\n    DRAW V0, V0, 0\n    HIRES\n    DRAW V0, V0, 15",
+        ),
+        (
+            "sprites and scrolls at the edges",
+            "    HIRES\n    SETVNN V0, 127\n    SETVNN V1, 63\n    SETIBIGFONT V1\n    DRAW V0, V1, 0\n    SCROLLDOWN 15\n    SCROLLLEFT\n    SCROLLUP 15\n    SCROLLRIGHT\n    LORES\n    SETVNN V0, 255\n    SETVNN V1, 255\n    DRAW V0, V1, 0\n    SCROLLDOWN 15",
+        ),
+        ("JMPV to an odd address", "    SETVNN V0, 0xFF\n    SETVNN VF, 0xFF\n    JMPV 0xF02"),
+        ("WAITFORKEY", "    WAITFORKEY V3\n    SKIPIFKEY V3\n    SKIPIFNOTKEY V3\n    JMP 0x200"),
+        ("EXIT", "    EXIT\n    RET"),
+    ];
+
+    for (name, source) in roms {
+        let rom = assemble(source).unwrap_or_else(|err| panic!("{}: {}", name, err));
+        let steps = vec![
+            Step::KeyDown(3),
+            Step::KeyDown(200),
+            Step::Cycles(255),
+            Step::TickTimers,
+            Step::KeyUp(3),
+            Step::Cycles(255),
+        ];
+
+        for flags in 0..(1 << 5) {
+            for increment_i in 0..3 {
+                for stack_depth in [0, 12, 255] {
+                    let bit = |n: u32| flags & (1 << n) != 0;
+                    pok_8_fuzz::emu(&EmuInput {
+                        quirks: QuirkFlags {
+                            shift_uses_vy: bit(0),
+                            increment_i,
+                            jump_uses_vx: bit(1),
+                            vf_reset: bit(2),
+                            clip_sprites: bit(3),
+                            display_wait: bit(4),
+                            stack_depth,
+                        },
+                        seed: 0,
+                        rom: rom.clone(),
+                        steps: steps.clone(),
+                    });
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,691 @@
+use crate::instruction::*;
+
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+// Source syntax, one statement per line, ; starts a comment:
+//   name:                  label, the address of whatever comes next
+//   NAME = expr            constant, can use labels and other constants defined anywhere
+//   MNEMONIC operands      the Instruction variant names, operands as the disassembler prints them:
+//                          V0-VF for registers, numbers as 0x1F, $1F, 0b11111 or 31, labels, a + b - c
+//   db expr, expr, ...     raw bytes
+//   sprite "#..#", ...     one row per string, # or X is a lit pixel, up to 8 wide is a byte and 16 is two
+//   include "file"         assembles another file in place, relative to the one including it
+// Programs are assembled to run from 0x200, where Emu::load puts them.
+const BASE_ADDR: u32 = 0x200;
+const END_ADDR: u32 = 0x10000;
+const MAX_INCLUDE_DEPTH: usize = 16;
+
+// Each mnemonic by the operands it takes, with the variant to build out of them
+type NibbleOp = fn(Nibble) -> Instruction;
+type AddrOp = fn(Addr) -> Instruction;
+type RegOp = fn(Reg) -> Instruction;
+type RegByteOp = fn(Reg, Byte) -> Instruction;
+type RegRegOp = fn(Reg, Reg) -> Instruction;
+
+const NO_OPERANDS: [(&str, Instruction); 9] = [
+    ("NOP", Instruction::NOP),
+    ("CLR", Instruction::CLR),
+    ("RET", Instruction::RET),
+    ("SCROLLRIGHT", Instruction::SCROLLRIGHT),
+    ("SCROLLLEFT", Instruction::SCROLLLEFT),
+    ("EXIT", Instruction::EXIT),
+    ("LORES", Instruction::LORES),
+    ("HIRES", Instruction::HIRES),
+    ("LOADAUDIO", Instruction::LOADAUDIO),
+];
+
+const NIBBLE_OPERAND: [(&str, NibbleOp); 3] = [
+    ("SCROLLDOWN", Instruction::SCROLLDOWN),
+    ("SCROLLUP", Instruction::SCROLLUP),
+    ("SETPLANES", Instruction::SETPLANES),
+];
+
+const ADDR_OPERAND: [(&str, AddrOp); 4] = [
+    ("JMP", Instruction::JMP),
+    ("CALL", Instruction::CALL),
+    ("SETINNN", Instruction::SETINNN),
+    ("JMPV", Instruction::JMPV),
+];
+
+const REG_OPERAND: [(&str, RegOp); 15] = [
+    ("SKIPIFKEY", Instruction::SKIPIFKEY),
+    ("SKIPIFNOTKEY", Instruction::SKIPIFNOTKEY),
+    ("SETVDT", Instruction::SETVDT),
+    ("WAITFORKEY", Instruction::WAITFORKEY),
+    ("SETDTV", Instruction::SETDTV),
+    ("SETSTV", Instruction::SETSTV),
+    ("INCSETIV", Instruction::INCSETIV),
+    ("SETIFONT", Instruction::SETIFONT),
+    ("SETIBIGFONT", Instruction::SETIBIGFONT),
+    ("BCDTORAM", Instruction::BCDTORAM),
+    ("SETPITCH", Instruction::SETPITCH),
+    ("VTORAM", Instruction::VTORAM),
+    ("RAMTOV", Instruction::RAMTOV),
+    ("STORERPL", Instruction::STORERPL),
+    ("LOADRPL", Instruction::LOADRPL),
+];
+
+const REG_BYTE_OPERANDS: [(&str, RegByteOp); 5] = [
+    ("SKIPIFVNN", Instruction::SKIPIFVNN),
+    ("SKIPIFNOTVNN", Instruction::SKIPIFNOTVNN),
+    ("SETVNN", Instruction::SETVNN),
+    ("INCSETVNN", Instruction::INCSETVNN),
+    ("RAND", Instruction::RAND),
+];
+
+const REG_REG_OPERANDS: [(&str, RegRegOp); 13] = [
+    ("SKIPIFVV", Instruction::SKIPIFVV),
+    ("SAVERANGE", Instruction::SAVERANGE),
+    ("LOADRANGE", Instruction::LOADRANGE),
+    ("SETVV", Instruction::SETVV),
+    ("ORSETVV", Instruction::ORSETVV),
+    ("ANDSETVV", Instruction::ANDSETVV),
+    ("XORSETVV", Instruction::XORSETVV),
+    ("INCSETVV", Instruction::INCSETVV),
+    ("DECSETVV", Instruction::DECSETVV),
+    ("SHIFTRV", Instruction::SHIFTRV),
+    ("DIFFSETVV", Instruction::DIFFSETVV),
+    ("SHIFTLV", Instruction::SHIFTLV),
+    ("SKIPIFNOTVV", Instruction::SKIPIFNOTVV),
+];
+
+// Where in which file something went wrong, lines and columns count from 1
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AsmError {
+    pub file: String,
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl fmt::Display for AsmError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // line 0 is the file as a whole, e.g. it couldn't be read
+        if self.line == 0 {
+            write!(f, "{}: {}", self.file, self.message)
+        } else {
+            write!(f, "{}:{}:{}: {}", self.file, self.line, self.column, self.message)
+        }
+    }
+}
+
+impl std::error::Error for AsmError {}
+
+// Assemble source text, includes are looked up from the current directory
+pub fn assemble(source: &str) -> Result<Vec<u8>, AsmError> {
+    let mut asm = Assembler::default();
+    asm.read_source(source, "<source>", Path::new("."), 0)?;
+    asm.finish()
+}
+
+pub fn assemble_file(path: &Path) -> Result<Vec<u8>, AsmError> {
+    let mut asm = Assembler::default();
+    asm.read_file(path, None, 0)?;
+    asm.finish()
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Tok {
+    Ident(String),
+    Num(i64),
+    Str(String),
+    Comma,
+    Colon,
+    Plus,
+    Minus,
+    Equals,
+}
+
+#[derive(Debug, Clone)]
+struct Token {
+    tok: Tok,
+    column: usize,
+}
+
+#[derive(Debug, Clone)]
+struct Loc {
+    file: String,
+    line: usize,
+    column: usize,
+}
+
+impl Loc {
+    fn error(&self, message: impl Into<String>) -> AsmError {
+        AsmError {
+            file: self.file.clone(),
+            line: self.line,
+            column: self.column,
+            message: message.into(),
+        }
+    }
+
+    fn at(&self, column: usize) -> Loc {
+        Loc { column, ..self.clone() }
+    }
+}
+
+// An operand is the tokens between commas, kept as is until every label is known
+#[derive(Debug, Clone)]
+struct Operand {
+    tokens: Vec<Token>,
+    loc: Loc,
+}
+
+#[derive(Debug)]
+enum Kind {
+    Instruction(String, Vec<Operand>),
+    Data(Vec<Operand>),
+    Bytes(Vec<u8>), // sprites are known up front
+}
+
+#[derive(Debug)]
+struct Statement {
+    loc: Loc,
+    kind: Kind,
+}
+
+#[derive(Debug)]
+enum Symbol {
+    Label(u32),
+    Constant(Operand),
+}
+
+// First pass reads every file, placing labels as it goes, the second evaluates operands and encodes
+#[derive(Default)]
+struct Assembler {
+    statements: Vec<Statement>,
+    symbols: HashMap<String, Symbol>,
+    addr: u32,
+}
+
+impl Assembler {
+    fn read_file(&mut self, path: &Path, from: Option<&Loc>, depth: usize) -> Result<(), AsmError> {
+        let name = path.display().to_string();
+        let source = fs::read_to_string(path).map_err(|err| {
+            let message = format!("could not read '{}': {}", name, err);
+            match from {
+                Some(loc) => loc.error(message),
+                None => Loc { file: name.clone(), line: 0, column: 0 }.error(message),
+            }
+        })?;
+
+        let dir = path.parent().unwrap_or_else(|| Path::new("."));
+        self.read_source(&source, &name, dir, depth)
+    }
+
+    fn read_source(&mut self, source: &str, file: &str, dir: &Path, depth: usize) -> Result<(), AsmError> {
+        for (idx, text) in source.lines().enumerate() {
+            let loc = Loc {
+                file: file.to_string(),
+                line: idx + 1,
+                column: 1,
+            };
+
+            let mut tokens = lex(text, &loc)?;
+
+            // any number of labels can lead a line
+            while let [Token { tok: Tok::Ident(name), column }, Token { tok: Tok::Colon, .. }, ..] = tokens.as_slice() {
+                let label = Symbol::Label(BASE_ADDR + self.addr);
+                self.define(name.clone(), label, &loc.at(*column))?;
+                tokens.drain(0..2);
+            }
+
+            let (first, rest) = match tokens.split_first() {
+                Some(split) => split,
+                None => continue,
+            };
+            let loc = loc.at(first.column);
+
+            let name = match &first.tok {
+                Tok::Ident(name) => name.clone(),
+                _ => return Err(loc.error("expected a label, constant, mnemonic or directive")),
+            };
+
+            if let Some(Token { tok: Tok::Equals, column }) = rest.first() {
+                let tokens = rest[1..].to_vec();
+                if tokens.is_empty() {
+                    return Err(loc.at(*column).error("constant needs a value"));
+                }
+
+                let value = Operand { loc: loc.at(tokens[0].column), tokens };
+                self.define(name, Symbol::Constant(value), &loc)?;
+                continue;
+            }
+
+            let operands = split_operands(rest, &loc)?;
+
+            let (kind, len) = match name.to_ascii_lowercase().as_str() {
+                "include" => {
+                    let path = match operands.as_slice() {
+                        [Operand { tokens, .. }] => match tokens.as_slice() {
+                            [Token { tok: Tok::Str(path), .. }] => dir.join(path),
+                            _ => return Err(loc.error("include takes one quoted file name")),
+                        },
+                        _ => return Err(loc.error("include takes one quoted file name")),
+                    };
+
+                    if depth >= MAX_INCLUDE_DEPTH {
+                        return Err(loc.error("includes nested too deep, is a file including itself?"));
+                    }
+                    self.read_file(&path, Some(&loc), depth + 1)?;
+                    continue;
+                }
+
+                "db" => {
+                    let len = operands.len() as u32;
+                    (Kind::Data(operands), len)
+                }
+
+                "sprite" => {
+                    let bytes = sprite(&operands)?;
+                    let len = bytes.len() as u32;
+                    (Kind::Bytes(bytes), len)
+                }
+
+                _ => {
+                    let mnemonic = name.to_ascii_uppercase();
+                    let len = if mnemonic == "LONGI" { 4 } else { 2 };
+                    (Kind::Instruction(mnemonic, operands), len)
+                }
+            };
+
+            if BASE_ADDR + self.addr + len > END_ADDR {
+                return Err(loc.error("program doesn't fit in memory"));
+            }
+            self.addr += len;
+            self.statements.push(Statement { loc, kind });
+        }
+
+        Ok(())
+    }
+
+    fn define(&mut self, name: String, symbol: Symbol, loc: &Loc) -> Result<(), AsmError> {
+        if register(&name).is_some() {
+            return Err(loc.error(format!("'{}' is a register", name)));
+        }
+        if self.symbols.contains_key(&name) {
+            return Err(loc.error(format!("'{}' is already defined", name)));
+        }
+
+        self.symbols.insert(name, symbol);
+        Ok(())
+    }
+
+    fn finish(&self) -> Result<Vec<u8>, AsmError> {
+        let mut rom = Vec::with_capacity(self.addr as usize);
+
+        for statement in &self.statements {
+            match &statement.kind {
+                Kind::Instruction(mnemonic, operands) if mnemonic == "LONGI" => {
+                    let [addr] = operands.as_slice() else {
+                        return Err(statement.loc.error("LONGI takes 1 operand"));
+                    };
+                    let addr = self.value(addr, 0, 0xFFFF)? as u16;
+
+                    rom.extend_from_slice(&Instruction::LONGI.encode().to_be_bytes());
+                    rom.extend_from_slice(&addr.to_be_bytes());
+                }
+
+                Kind::Instruction(mnemonic, operands) => {
+                    let op = self.instruction(mnemonic, operands, &statement.loc)?;
+                    rom.extend_from_slice(&op.encode().to_be_bytes());
+                }
+
+                Kind::Data(operands) => {
+                    for operand in operands {
+                        rom.push(self.value(operand, -128, 0xFF)? as u8);
+                    }
+                }
+
+                Kind::Bytes(bytes) => rom.extend_from_slice(bytes),
+            }
+        }
+
+        Ok(rom)
+    }
+
+    fn instruction(&self, mnemonic: &str, operands: &[Operand], loc: &Loc) -> Result<Instruction, AsmError> {
+        let count = |n: usize| {
+            if operands.len() == n {
+                Ok(())
+            } else {
+                let s = if n == 1 { "" } else { "s" };
+                Err(loc.error(format!("{} takes {} operand{}", mnemonic, n, s)))
+            }
+        };
+
+        if let Some(op) = lookup(&NO_OPERANDS, mnemonic) {
+            count(0)?;
+            return Ok(op);
+        }
+        if let Some(make) = lookup(&NIBBLE_OPERAND, mnemonic) {
+            count(1)?;
+            return Ok(make(self.nibble(&operands[0])?));
+        }
+        if let Some(make) = lookup(&ADDR_OPERAND, mnemonic) {
+            count(1)?;
+            return Ok(make(self.addr(&operands[0])?));
+        }
+        if let Some(make) = lookup(&REG_OPERAND, mnemonic) {
+            count(1)?;
+            return Ok(make(reg(&operands[0])?));
+        }
+        if let Some(make) = lookup(&REG_BYTE_OPERANDS, mnemonic) {
+            count(2)?;
+            return Ok(make(reg(&operands[0])?, self.byte(&operands[1])?));
+        }
+        if let Some(make) = lookup(&REG_REG_OPERANDS, mnemonic) {
+            count(2)?;
+            return Ok(make(reg(&operands[0])?, reg(&operands[1])?));
+        }
+        if mnemonic == "DRAW" {
+            count(3)?;
+            let n = self.nibble(&operands[2])?;
+            return Ok(Instruction::DRAW(reg(&operands[0])?, reg(&operands[1])?, n));
+        }
+
+        Err(loc.error(format!("unknown mnemonic '{}'", mnemonic)))
+    }
+
+    fn nibble(&self, operand: &Operand) -> Result<Nibble, AsmError> {
+        let n = self.value(operand, 0, 0xF)?;
+        Ok(Nibble::new(n as u8).expect("checked by value"))
+    }
+
+    // negative bytes are written as their two's complement
+    fn byte(&self, operand: &Operand) -> Result<Byte, AsmError> {
+        Ok(Byte::new(self.value(operand, -128, 0xFF)? as u8))
+    }
+
+    fn addr(&self, operand: &Operand) -> Result<Addr, AsmError> {
+        let addr = self.value(operand, 0, 0xFFF)?;
+        Ok(Addr::new(addr as u16).expect("checked by value"))
+    }
+
+    // Evaluate an operand and check it fits between min and max
+    fn value(&self, operand: &Operand, min: i64, max: i64) -> Result<i64, AsmError> {
+        let value = self.eval(operand, &mut Vec::new())?;
+
+        if value < min || value > max {
+            return Err(operand.loc.error(format!("{} doesn't fit, it has to be {} to {:#X}", value, min, max)));
+        }
+        Ok(value)
+    }
+
+    // term (+|- term)*, a term is a number, label or constant. resolving holds the constants whose values are being
+    // worked out, one of them turning up again inside its own value is a loop
+    fn eval(&self, operand: &Operand, resolving: &mut Vec<String>) -> Result<i64, AsmError> {
+        let mut total: i64 = 0;
+        let mut sign = Some(1); // None while waiting for an operator
+
+        for token in &operand.tokens {
+            let loc = operand.loc.at(token.column);
+
+            let value = match (&token.tok, sign) {
+                (Tok::Plus, None) => {
+                    sign = Some(1);
+                    continue;
+                }
+                (Tok::Minus, None) => {
+                    sign = Some(-1);
+                    continue;
+                }
+                (Tok::Minus, Some(s)) => {
+                    sign = Some(-s);
+                    continue;
+                }
+                (Tok::Num(num), Some(_)) => *num,
+                (Tok::Ident(name), Some(_)) => match self.symbols.get(name) {
+                    Some(Symbol::Label(addr)) => *addr as i64,
+                    Some(Symbol::Constant(_)) if resolving.contains(name) => {
+                        return Err(loc.error("constants refer to each other in a loop"));
+                    }
+                    Some(Symbol::Constant(value)) => {
+                        resolving.push(name.clone());
+                        let value = self.eval(value, resolving)?;
+                        resolving.pop();
+                        value
+                    }
+                    None if register(name).is_some() => return Err(loc.error("expected a value, not a register")),
+                    None => return Err(loc.error(format!("'{}' isn't defined", name))),
+                },
+                (_, None) => return Err(loc.error("expected + or -")),
+                (_, Some(_)) => return Err(loc.error("expected a number, label or constant")),
+            };
+
+            total = value
+                .checked_mul(sign.unwrap_or(1))
+                .and_then(|value| total.checked_add(value))
+                .ok_or_else(|| loc.error("value out of range"))?;
+            sign = None;
+        }
+
+        match sign {
+            None => Ok(total),
+            Some(_) => Err(operand.loc.error("expression is incomplete")),
+        }
+    }
+}
+
+fn lex(text: &str, loc: &Loc) -> Result<Vec<Token>, AsmError> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut tokens = Vec::new();
+    let mut pos = 0;
+
+    while pos < chars.len() {
+        let column = pos + 1;
+        let c = chars[pos];
+
+        let tok = match c {
+            ';' => break,
+            c if c.is_whitespace() => {
+                pos += 1;
+                continue;
+            }
+            ',' => Tok::Comma,
+            ':' => Tok::Colon,
+            '+' => Tok::Plus,
+            '-' => Tok::Minus,
+            '=' => Tok::Equals,
+
+            '"' => {
+                let len = chars[(pos + 1)..].iter().position(|c| *c == '"');
+                let len = len.ok_or_else(|| loc.at(column).error("string is never closed"))?;
+                let text = chars[(pos + 1)..(pos + 1 + len)].iter().collect();
+                pos += len + 1;
+                Tok::Str(text)
+            }
+
+            c if c.is_ascii_alphanumeric() || c == '_' || c == '$' => {
+                let len = chars[pos..]
+                    .iter()
+                    .skip(1)
+                    .take_while(|c| c.is_ascii_alphanumeric() || **c == '_' || **c == '.')
+                    .count();
+                let word: String = chars[pos..=(pos + len)].iter().collect();
+                pos += len;
+
+                if c.is_ascii_digit() || c == '$' {
+                    let num = number(&word).ok_or_else(|| loc.at(column).error(format!("'{}' isn't a number", word)))?;
+                    Tok::Num(num)
+                } else {
+                    Tok::Ident(word)
+                }
+            }
+
+            _ => return Err(loc.at(column).error(format!("unexpected '{}'", c))),
+        };
+
+        tokens.push(Token { tok, column });
+        pos += 1;
+    }
+
+    Ok(tokens)
+}
+
+fn number(word: &str) -> Option<i64> {
+    let lower = word.to_ascii_lowercase();
+
+    if let Some(hex) = lower.strip_prefix("0x").or_else(|| lower.strip_prefix('$')) {
+        i64::from_str_radix(hex, 16).ok()
+    } else if let Some(bin) = lower.strip_prefix("0b") {
+        i64::from_str_radix(bin, 2).ok()
+    } else {
+        lower.parse().ok()
+    }
+}
+
+fn split_operands(tokens: &[Token], loc: &Loc) -> Result<Vec<Operand>, AsmError> {
+    if tokens.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mut operands = Vec::new();
+    let mut start = 0;
+
+    for end in (0..tokens.len()).filter(|idx| tokens[*idx].tok == Tok::Comma).chain([tokens.len()]) {
+        let part = &tokens[start..end];
+        let column = tokens.get(start).or(tokens.last()).map_or(loc.column, |token| token.column);
+
+        if part.is_empty() {
+            return Err(loc.at(column).error("missing operand"));
+        }
+        operands.push(Operand {
+            tokens: part.to_vec(),
+            loc: loc.at(column),
+        });
+        start = end + 1;
+    }
+
+    Ok(operands)
+}
+
+fn lookup<T: Copy>(table: &[(&str, T)], mnemonic: &str) -> Option<T> {
+    table.iter().find(|(name, _)| *name == mnemonic).map(|(_, entry)| *entry)
+}
+
+fn register(name: &str) -> Option<Reg> {
+    let mut chars = name.chars();
+
+    match (chars.next(), chars.next(), chars.next()) {
+        (Some('v' | 'V'), Some(digit), None) => digit.to_digit(16).and_then(|x| Reg::new(x as u8)),
+        _ => None,
+    }
+}
+
+fn reg(operand: &Operand) -> Result<Reg, AsmError> {
+    match operand.tokens.as_slice() {
+        [Token { tok: Tok::Ident(name), .. }] => register(name),
+        _ => None,
+    }
+    .ok_or_else(|| operand.loc.error("expected a register, V0 to VF"))
+}
+
+fn sprite(operands: &[Operand]) -> Result<Vec<u8>, AsmError> {
+    let mut bytes = Vec::new();
+
+    for operand in operands {
+        let row = match operand.tokens.as_slice() {
+            [Token { tok: Tok::Str(row), .. }] => row,
+            _ => return Err(operand.loc.error("sprite rows are quoted strings like \"#..#\"")),
+        };
+
+        let width = row.chars().count();
+        if width == 0 || width > 16 {
+            return Err(operand.loc.error("sprite rows are 1 to 16 pixels wide"));
+        }
+
+        let bits = row.chars().fold(0u16, |bits, pixel| (bits << 1) | matches!(pixel, '#' | 'X' | 'x') as u16);
+        if width <= 8 {
+            bytes.push((bits << (8 - width)) as u8);
+        } else {
+            bytes.extend_from_slice(&(bits << (16 - width)).to_be_bytes());
+        }
+    }
+
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::disasm::{disassemble, labels, source};
+
+    // assemble, disassemble as --labels --source would print it, assemble that again
+    fn round_trip(rom: &[u8]) -> Vec<u8> {
+        let lines = disassemble(rom, BASE_ADDR as u16);
+        let text = source(&lines, &labels(&lines));
+        assemble(&text).unwrap_or_else(|err| panic!("{}\n{}", err, text))
+    }
+
+    #[test]
+    fn source_round_trips() {
+        let rom = assemble(
+            "
+            FONT = 0xA
+            start:
+                CLR
+                SETVNN V0, FONT + 1
+                CALL draw
+                LONGI data
+                LOADAUDIO
+                SKIPIFVNN V0, -1
+                JMP start
+            draw:
+                SETIFONT V0
+                DRAW V0, V1, 5
+                RET
+            data:
+                db 1, 2, $FF
+                sprite \"#..#\", \"...#....#\"
+            ",
+        )
+        .unwrap();
+
+        assert_eq!(round_trip(&rom), rom);
+    }
+
+    #[test]
+    fn bundled_games_round_trip() {
+        let games = Path::new(env!("CARGO_MANIFEST_DIR")).join("../games");
+        for entry in fs::read_dir(&games).unwrap() {
+            let path = entry.unwrap().path();
+            if path.is_file() && path.extension().is_none() {
+                let rom = fs::read(&path).unwrap();
+                assert_eq!(round_trip(&rom), rom, "{}", path.display());
+            }
+        }
+    }
+
+    #[test]
+    fn constant_chains_are_not_loops() {
+        let mut source = String::from("c0 = 7\n");
+        for n in 1..40 {
+            source += &format!("c{} = c{}\n", n, n - 1);
+        }
+        source += "SETVNN V0, c39\n";
+
+        assert_eq!(assemble(&source).unwrap(), [0x60, 0x07]);
+    }
+
+    #[test]
+    fn constant_loops_are_errors() {
+        let err = assemble("a = b\nb = c + 1\nc = a\nSETVNN V0, a").unwrap_err();
+        assert_eq!(err.message, "constants refer to each other in a loop");
+
+        let err = assemble("a = a\nSETVNN V0, a").unwrap_err();
+        assert_eq!(err.message, "constants refer to each other in a loop");
+    }
+
+    #[test]
+    fn overflow_is_out_of_range() {
+        let err = assemble("SETVNN V0, 0x7FFFFFFFFFFFFFFF + 1").unwrap_err();
+        assert_eq!((err.line, err.column, err.message.as_str()), (1, 33, "value out of range"));
+
+        let err = assemble("BIG = 0x7FFFFFFFFFFFFFFF\nSETVNN V0, -BIG - BIG").unwrap_err();
+        assert_eq!(err.message, "value out of range");
+    }
+}
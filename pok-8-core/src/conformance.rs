@@ -0,0 +1,499 @@
+use crate::asm::*;
+use crate::debugger::*;
+use crate::emu::*;
+use crate::error::*;
+use crate::quirks::*;
+
+use std::fmt::Write;
+
+// Self-checking ROMs that put every Instruction through its paces, flag edge cases included. Each is built for one
+// set of quirks, expecting what those quirks say should happen, and runs every case in turn before drawing a mark
+// per case (a tick or a cross, 13 to a row) and the number of failures in the bottom left, then stopping with EXIT.
+// The cases record themselves in RAM as well, which is how run() reads them back. No instruction reads the sound
+// timer or the XO-CHIP audio registers, so run() checks those itself as each case records its result, marking the
+// case failed in RAM before the report is drawn. Run anywhere else, those cases only check that their code runs.
+pub const RESULTS_ADDR: u16 = 0x202; // one byte per case: 0 never ran, 1 passed, 2 failed
+pub const HELD_KEY: usize = 5; // the key tests want this held down the whole time, WAITFORKEY waits for it
+const FRAME_LIMIT: usize = 3600; // a minute, far more than any profile needs even waiting on every DRAW
+
+const PASSED: u8 = 1;
+const FAILED: u8 = 2;
+const MARKS_PER_ROW: usize = 13; // 5 rows of them fit above the failure count
+
+// How a case ended, passed is None if the ROM never got to it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CaseResult {
+    pub name: &'static str,
+    pub passed: Option<bool>,
+}
+
+// What a case expects once its code has run, of a register in the ROM or of what only run() can see
+enum Check {
+    Eq(&'static str, u8),
+    Ne(&'static str, u8),
+    SoundTimer(u8, u8), // min to max, a timer tick can land anywhere in the case
+    Pitch(u8),
+    Pattern([u8; 16]),
+}
+
+impl Check {
+    // true for the ones the ROM can't make itself
+    fn by_harness(&self) -> bool {
+        !matches!(self, Check::Eq(..) | Check::Ne(..))
+    }
+
+    fn holds(&self, emu: &Emu) -> bool {
+        match self {
+            Check::Eq(..) | Check::Ne(..) => true,
+            Check::SoundTimer(min, max) => (*min..=*max).contains(&emu.sound_timer()),
+            Check::Pitch(pitch) => emu.pitch() == *pitch,
+            Check::Pattern(pattern) => emu.audio_pattern() == Some(pattern),
+        }
+    }
+}
+
+struct Case {
+    name: &'static str,
+    code: String, // @ stands for a label prefix of the case's own
+    checks: Vec<Check>,
+}
+
+// The ROM's pok8-asm source
+pub fn source(quirks: &Quirks) -> String {
+    let cases = cases(quirks);
+    let mut out = String::new();
+
+    writeln!(out, "; pok-8 conformance ROM, generated by pok_8_core::conformance. Hold key {:X} while it runs", HELD_KEY).unwrap();
+    writeln!(out, "    JMP start").unwrap();
+    writeln!(out, "results:").unwrap();
+    writeln!(out, "    db {}", vec!["0"; cases.len() + cases.len() % 2].join(", ")).unwrap();
+    writeln!(out, "start:").unwrap();
+
+    for (idx, case) in cases.iter().enumerate() {
+        let prefix = format!("c{}_", idx);
+        writeln!(out, "\n; {}", case.name).unwrap();
+        for line in case.code.lines() {
+            writeln!(out, "{}", line.replace('@', &prefix)).unwrap();
+        }
+
+        for check in &case.checks {
+            match check {
+                Check::Eq(reg, value) => writeln!(out, "    SKIPIFVNN {}, {:#04X}", reg, value).unwrap(),
+                Check::Ne(reg, value) => writeln!(out, "    SKIPIFNOTVNN {}, {:#04X}", reg, value).unwrap(),
+                _ => continue,
+            }
+            writeln!(out, "    JMP {}failed", prefix).unwrap();
+        }
+
+        writeln!(out, "    SETVNN V0, {}", PASSED).unwrap();
+        writeln!(out, "    JMP {}record", prefix).unwrap();
+        writeln!(out, "{}failed:", prefix).unwrap();
+        writeln!(out, "    SETVNN V0, {}", FAILED).unwrap();
+        writeln!(out, "{}record:", prefix).unwrap();
+        writeln!(out, "    SETINNN results + {}", idx).unwrap();
+        writeln!(out, "    VTORAM V0").unwrap();
+    }
+
+    // a mark per case, V3 counting the crosses
+    writeln!(out, "\n; the report").unwrap();
+    writeln!(out, "    SETPLANES 1\n    LORES\n    SETVNN V3, 0").unwrap();
+    for idx in 0..cases.len() {
+        writeln!(out, "    SETINNN results + {}", idx).unwrap();
+        writeln!(out, "    RAMTOV V0").unwrap();
+        writeln!(out, "    SETINNN tick").unwrap();
+        writeln!(out, "    SKIPIFVNN V0, {}", PASSED).unwrap();
+        writeln!(out, "    CALL cross").unwrap();
+        writeln!(out, "    SETVNN V1, {}", (idx % MARKS_PER_ROW) * 5).unwrap();
+        writeln!(out, "    SETVNN V2, {}", (idx / MARKS_PER_ROW) * 5).unwrap();
+        writeln!(out, "    DRAW V1, V2, 4").unwrap();
+    }
+
+    // the failure count in three digits
+    writeln!(out, "    SETINNN digits\n    BCDTORAM V3\n    RAMTOV V2\n    SETVNN V4, 0\n    SETVNN V5, 26").unwrap();
+    for reg in ["V0", "V1", "V2"] {
+        writeln!(out, "    SETIFONT {}\n    DRAW V4, V5, 5\n    INCSETVNN V4, 5", reg).unwrap();
+    }
+    writeln!(out, "    EXIT").unwrap();
+
+    writeln!(out, "\ncross:\n    SETINNN cross_sprite\n    INCSETVNN V3, 1\n    RET").unwrap();
+    writeln!(out, "tick:\n    sprite \"...#\", \"..#.\", \"#.#.\", \".#..\"").unwrap();
+    writeln!(out, "cross_sprite:\n    sprite \"#..#\", \".##.\", \".##.\", \"#..#\"").unwrap();
+    writeln!(out, "digits:\n    db 0, 0, 0, 0").unwrap();
+
+    out
+}
+
+// Generated source always assembles, anything else is a bug in this file
+pub fn rom(quirks: &Quirks) -> Vec<u8> {
+    assemble(&source(quirks)).unwrap_or_else(|err| panic!("conformance source doesn't assemble: {}", err))
+}
+
+// Names of the cases, in the order their results are stored
+pub fn case_names(quirks: &Quirks) -> Vec<&'static str> {
+    cases(quirks).iter().map(|case| case.name).collect()
+}
+
+// Builds the ROM for these quirks and runs it under them with HELD_KEY down until it stops. Every write to the results
+// stops the run so the checks the ROM can't make get made while the case's state is still there
+pub fn run(quirks: &Quirks) -> Result<Vec<CaseResult>, EmuError> {
+    let cases = cases(quirks);
+    let mut emu = Emu::with_config(EmuConfig {
+        quirks: *quirks,
+        seed: Some(0),
+        ..EmuConfig::default()
+    });
+    emu.load(&rom(quirks))?;
+    emu.key_down(HELD_KEY);
+
+    let mut debugger = Debugger::new(emu);
+    debugger.add_watchpoint(Watchpoint::Ram {
+        start: RESULTS_ADDR,
+        end: RESULTS_ADDR + cases.len() as u16 - 1,
+        access: Access::Write,
+    });
+
+    let limit = (FRAME_LIMIT * CYCLES_PER_FRAME) as u64;
+    while debugger.cycles() < limit {
+        match debugger.run_until_break(limit - debugger.cycles()) {
+            StopReason::RamWatch { addr, write: true } => {
+                let case = &cases[addr - RESULTS_ADDR as usize];
+                let emu = debugger.emu_mut();
+                if emu.ram()[addr] == PASSED && !case.checks.iter().filter(|check| check.by_harness()).all(|check| check.holds(emu)) {
+                    emu.set_ram(addr, &[FAILED])?;
+                }
+            }
+            StopReason::Error(err) => return Err(err),
+            _ => break,
+        }
+    }
+
+    let results = &debugger.emu().ram()[RESULTS_ADDR as usize..];
+    Ok(cases
+        .iter()
+        .zip(results)
+        .map(|(case, result)| CaseResult {
+            name: case.name,
+            passed: match *result {
+                PASSED => Some(true),
+                FAILED => Some(false),
+                _ => None,
+            },
+        })
+        .collect())
+}
+
+fn case(name: &'static str, code: &str, checks: Vec<Check>) -> Case {
+    Case {
+        name,
+        code: code.to_string(),
+        checks,
+    }
+}
+
+// Every case, with what these quirks expect. Cases that draw leave the screen clear for the next one
+fn cases(q: &Quirks) -> Vec<Case> {
+    use Check::*;
+
+    let vf_after_logic = if q.vf_reset { 0x00 } else { 0x33 };
+    let shift_right = if q.shift_uses_vy { (0x41, 0) } else { (0x02, 1) }; // of 0x82 or 0x05
+    let shift_left = if q.shift_uses_vy { (0x84, 0) } else { (0x02, 1) }; // of 0x42 or 0x81, VF is bit 7
+    let after_store = match q.increment_i {
+        IncrementI::Unchanged => 0x01,
+        IncrementI::ByX => 0x03,
+        IncrementI::ByXPlusOne => 0x44,
+    };
+    let after_load = match q.increment_i {
+        IncrementI::Unchanged => 0x11,
+        IncrementI::ByX => 0x22,
+        IncrementI::ByXPlusOne => 0x33,
+    };
+
+    vec![
+        case("NOP", "    SETVNN V1, 0x12\n    NOP", vec![Eq("V1", 0x12)]),
+        case("SETVNN", "    SETVNN V1, 0x5A", vec![Eq("V1", 0x5A)]),
+        case(
+            "INCSETVNN wraps and leaves VF",
+            "    SETVNN VF, 0x33\n    SETVNN V1, 0xFF\n    INCSETVNN V1, 0x02",
+            vec![Eq("V1", 0x01), Eq("VF", 0x33)],
+        ),
+        case("SETVV", "    SETVNN V2, 0x77\n    SETVV V1, V2", vec![Eq("V1", 0x77)]),
+        case(
+            "ORSETVV",
+            "    SETVNN VF, 0x33\n    SETVNN V1, 0xF0\n    SETVNN V2, 0x0F\n    ORSETVV V1, V2",
+            vec![Eq("V1", 0xFF), Eq("VF", vf_after_logic)],
+        ),
+        case(
+            "ANDSETVV",
+            "    SETVNN VF, 0x33\n    SETVNN V1, 0xFC\n    SETVNN V2, 0x3F\n    ANDSETVV V1, V2",
+            vec![Eq("V1", 0x3C), Eq("VF", vf_after_logic)],
+        ),
+        case(
+            "XORSETVV",
+            "    SETVNN VF, 0x33\n    SETVNN V1, 0xFF\n    SETVNN V2, 0x0F\n    XORSETVV V1, V2",
+            vec![Eq("V1", 0xF0), Eq("VF", vf_after_logic)],
+        ),
+        case(
+            "INCSETVV without carry",
+            "    SETVNN V1, 0x10\n    SETVNN V2, 0x20\n    INCSETVV V1, V2",
+            vec![Eq("V1", 0x30), Eq("VF", 0)],
+        ),
+        case(
+            "INCSETVV with carry",
+            "    SETVNN V1, 0xF0\n    SETVNN V2, 0x20\n    INCSETVV V1, V2",
+            vec![Eq("V1", 0x10), Eq("VF", 1)],
+        ),
+        case("INCSETVV into VF, the flag wins", "    SETVNN VF, 0xF0\n    SETVNN V2, 0x20\n    INCSETVV VF, V2", vec![Eq("VF", 1)]),
+        case(
+            "DECSETVV without borrow",
+            "    SETVNN V1, 0x30\n    SETVNN V2, 0x10\n    DECSETVV V1, V2",
+            vec![Eq("V1", 0x20), Eq("VF", 1)],
+        ),
+        case(
+            "DECSETVV with borrow",
+            "    SETVNN V1, 0x10\n    SETVNN V2, 0x30\n    DECSETVV V1, V2",
+            vec![Eq("V1", 0xE0), Eq("VF", 0)],
+        ),
+        case(
+            "DECSETVV of equal values",
+            "    SETVNN V1, 0x20\n    SETVNN V2, 0x20\n    DECSETVV V1, V2",
+            vec![Eq("V1", 0), Eq("VF", 1)],
+        ),
+        case("DECSETVV into VF, the flag wins", "    SETVNN VF, 0x10\n    SETVNN V2, 0x30\n    DECSETVV VF, V2", vec![Eq("VF", 0)]),
+        case(
+            "DIFFSETVV without borrow",
+            "    SETVNN V1, 0x10\n    SETVNN V2, 0x30\n    DIFFSETVV V1, V2",
+            vec![Eq("V1", 0x20), Eq("VF", 1)],
+        ),
+        case(
+            "DIFFSETVV with borrow",
+            "    SETVNN V1, 0x30\n    SETVNN V2, 0x10\n    DIFFSETVV V1, V2",
+            vec![Eq("V1", 0xE0), Eq("VF", 0)],
+        ),
+        case(
+            "SHIFTRV, VF is bit 0",
+            "    SETVNN V1, 0x05\n    SETVNN V2, 0x82\n    SHIFTRV V1, V2",
+            vec![Eq("V1", shift_right.0), Eq("VF", shift_right.1)],
+        ),
+        case("SHIFTRV into VF, the flag wins", "    SETVNN VF, 0x03\n    SHIFTRV VF, VF", vec![Eq("VF", 1)]),
+        case(
+            "SHIFTLV, VF is bit 7",
+            "    SETVNN V1, 0x81\n    SETVNN V2, 0x42\n    SHIFTLV V1, V2",
+            vec![Eq("V1", shift_left.0), Eq("VF", shift_left.1)],
+        ),
+        case("SHIFTLV into VF, the flag wins", "    SETVNN VF, 0x80\n    SHIFTLV VF, VF", vec![Eq("VF", 1)]),
+        case(
+            "SKIPIFVNN",
+            "    SETVNN V1, 0x10\n    SETVNN V2, 0\n    SKIPIFVNN V1, 0x10\n    INCSETVNN V2, 1\n    SKIPIFVNN V1, 0x11\n    INCSETVNN V2, 2",
+            vec![Eq("V2", 2)],
+        ),
+        case(
+            "SKIPIFNOTVNN",
+            "    SETVNN V1, 0x10\n    SETVNN V2, 0\n    SKIPIFNOTVNN V1, 0x11\n    INCSETVNN V2, 1\n    SKIPIFNOTVNN V1, 0x10\n    INCSETVNN V2, 2",
+            vec![Eq("V2", 2)],
+        ),
+        case(
+            "SKIPIFVV",
+            "    SETVNN V1, 0x10\n    SETVNN V3, 0x10\n    SETVNN V4, 0x11\n    SETVNN V2, 0\n    SKIPIFVV V1, V3\n    INCSETVNN V2, 1\n    SKIPIFVV V1, V4\n    INCSETVNN V2, 2",
+            vec![Eq("V2", 2)],
+        ),
+        case(
+            "SKIPIFNOTVV",
+            "    SETVNN V1, 0x10\n    SETVNN V3, 0x11\n    SETVNN V4, 0x10\n    SETVNN V2, 0\n    SKIPIFNOTVV V1, V3\n    INCSETVNN V2, 1\n    SKIPIFNOTVV V1, V4\n    INCSETVNN V2, 2",
+            vec![Eq("V2", 2)],
+        ),
+        // skipping only half of it would run the address word, which is INCSETVNN V2, 1
+        case(
+            "skips step over all of LONGI",
+            "    SETVNN V1, 1\n    SETVNN V2, 0\n    SKIPIFVNN V1, 1\n    LONGI 0x7201\n    INCSETVNN V2, 4",
+            vec![Eq("V2", 4)],
+        ),
+        case(
+            "SKIPIFKEY and SKIPIFNOTKEY",
+            &format!(
+                "    SETVNN V2, 0\n    SETVNN V1, {:#04X}\n    SKIPIFKEY V1\n    INCSETVNN V2, 1\n    SKIPIFNOTKEY V1\n    INCSETVNN V2, 2\n    SETVNN V1, {:#04X}\n    SKIPIFKEY V1\n    INCSETVNN V2, 4\n    SKIPIFNOTKEY V1\n    INCSETVNN V2, 8",
+                HELD_KEY,
+                (HELD_KEY + 1) % 16
+            ),
+            vec![Eq("V2", 6)],
+        ),
+        case("WAITFORKEY", "    SETVNN V1, 0xFF\n    WAITFORKEY V1", vec![Eq("V1", HELD_KEY as u8)]),
+        case("JMP", "    SETVNN V1, 0\n    JMP @over\n    SETVNN V1, 1\n@over:", vec![Eq("V1", 0)]),
+        case(
+            "CALL and RET",
+            "    SETVNN V1, 0\n    CALL @sub\n    INCSETVNN V1, 1\n    JMP @after\n@sub:\n    INCSETVNN V1, 0x10\n    RET\n@after:",
+            vec![Eq("V1", 0x11)],
+        ),
+        // V0 is 0 and every other register 2, so BXNN lands 2 further on than BNNN whichever X the address has
+        case(
+            "JMPV",
+            "    SETVNN V0, 0\n    SETVNN V1, 2\n    SETVNN V2, 2\n    SETVNN V3, 2\n    SETVNN V4, 2\n    SETVNN V5, 2\n    SETVNN V6, 2\n    SETVNN V7, 2\n    SETVNN V8, 2\n    SETVNN V9, 2\n    SETVNN VA, 2\n    SETVNN VB, 2\n    SETVNN VC, 2\n    SETVNN VD, 2\n    SETVNN VE, 2\n    SETVNN VF, 2\n    JMPV @table\n@table:\n    JMP @v0\n    JMP @vx\n@v0:\n    SETVNN V1, 0xA0\n    JMP @done\n@vx:\n    SETVNN V1, 0xB0\n@done:",
+            vec![Eq("V1", if q.jump_uses_vx { 0xB0 } else { 0xA0 })],
+        ),
+        case(
+            "SETINNN and RAMTOV",
+            "    SETINNN @data\n    RAMTOV V1\n    JMP @after\n@data:\n    db 0xA5, 0x5A\n@after:",
+            vec![Eq("V0", 0xA5), Eq("V1", 0x5A)],
+        ),
+        case(
+            "LONGI",
+            "    LONGI @data\n    RAMTOV V0\n    JMP @after\n@data:\n    db 0xC3, 0x00\n@after:",
+            vec![Eq("V0", 0xC3)],
+        ),
+        case(
+            "VTORAM and I afterwards",
+            "    SETINNN @data\n    SETVNN V0, 1\n    SETVNN V1, 2\n    SETVNN V2, 3\n    VTORAM V2\n    SETVNN V0, 0\n    RAMTOV V0\n    JMP @after\n@data:\n    db 0, 0, 0, 0x44\n@after:",
+            vec![Eq("V0", after_store)],
+        ),
+        case(
+            "RAMTOV and I afterwards",
+            "    SETINNN @data\n    RAMTOV V1\n    SETVV V3, V1\n    RAMTOV V0\n    JMP @after\n@data:\n    db 0x11, 0x22, 0x33, 0x44\n@after:",
+            vec![Eq("V3", 0x22), Eq("V0", after_load)],
+        ),
+        case(
+            "SAVERANGE",
+            "    SETINNN @data\n    SETVNN V1, 1\n    SETVNN V2, 2\n    SETVNN V3, 3\n    SAVERANGE V1, V3\n    RAMTOV V2\n    JMP @after\n@data:\n    db 0, 0, 0, 0\n@after:",
+            vec![Eq("V0", 1), Eq("V1", 2), Eq("V2", 3)],
+        ),
+        case(
+            "SAVERANGE backwards",
+            "    SETINNN @data\n    SETVNN V1, 1\n    SETVNN V2, 2\n    SETVNN V3, 3\n    SAVERANGE V3, V1\n    RAMTOV V2\n    JMP @after\n@data:\n    db 0, 0, 0, 0\n@after:",
+            vec![Eq("V0", 3), Eq("V1", 2), Eq("V2", 1)],
+        ),
+        case(
+            "LOADRANGE",
+            "    SETINNN @data\n    LOADRANGE V4, V6\n    JMP @after\n@data:\n    db 7, 8, 9, 0\n@after:",
+            vec![Eq("V4", 7), Eq("V5", 8), Eq("V6", 9)],
+        ),
+        case(
+            "LOADRANGE backwards",
+            "    SETINNN @data\n    LOADRANGE V6, V4\n    JMP @after\n@data:\n    db 7, 8, 9, 0\n@after:",
+            vec![Eq("V4", 9), Eq("V5", 8), Eq("V6", 7)],
+        ),
+        case(
+            "INCSETIV",
+            "    SETINNN @data\n    SETVNN V1, 2\n    INCSETIV V1\n    RAMTOV V0\n    JMP @after\n@data:\n    db 0x10, 0x20, 0x30, 0x40\n@after:",
+            vec![Eq("V0", 0x30)],
+        ),
+        case(
+            "SETIFONT",
+            "    SETVNN V1, 1\n    SETIFONT V1\n    RAMTOV V0\n    SETVV V2, V0\n    SETVNN V1, 0xB\n    SETIFONT V1\n    RAMTOV V0",
+            vec![Eq("V2", 0x20), Eq("V0", 0xE0)],
+        ),
+        case(
+            "SETIBIGFONT",
+            "    SETVNN V1, 1\n    SETIBIGFONT V1\n    RAMTOV V0\n    SETVV V2, V0\n    SETVNN V1, 0xC\n    SETIBIGFONT V1\n    RAMTOV V0",
+            vec![Eq("V2", 0x18), Eq("V0", 0x3C)],
+        ),
+        case(
+            "BCDTORAM",
+            "    SETINNN @data\n    SETVNN V3, 137\n    BCDTORAM V3\n    RAMTOV V2\n    JMP @after\n@data:\n    db 0, 0, 0, 0\n@after:",
+            vec![Eq("V0", 1), Eq("V1", 3), Eq("V2", 7)],
+        ),
+        case(
+            "BCDTORAM of 255",
+            "    SETINNN @data\n    SETVNN V3, 255\n    BCDTORAM V3\n    RAMTOV V2\n    JMP @after\n@data:\n    db 9, 9, 9, 9\n@after:",
+            vec![Eq("V0", 2), Eq("V1", 5), Eq("V2", 5)],
+        ),
+        case(
+            "BCDTORAM of 0",
+            "    SETINNN @data\n    SETVNN V3, 0\n    BCDTORAM V3\n    RAMTOV V2\n    JMP @after\n@data:\n    db 9, 9, 9, 9\n@after:",
+            vec![Eq("V0", 0), Eq("V1", 0), Eq("V2", 0)],
+        ),
+        case(
+            "STORERPL and LOADRPL",
+            "    SETVNN V0, 9\n    SETVNN V1, 8\n    SETVNN V2, 7\n    STORERPL V2\n    SETVNN V0, 0\n    SETVNN V1, 0\n    SETVNN V2, 0\n    LOADRPL V2",
+            vec![Eq("V0", 9), Eq("V1", 8), Eq("V2", 7)],
+        ),
+        case(
+            "RAND masks its byte",
+            "    RAND V1, 0x00\n    RAND V2, 0x0F\n    SETVNN V3, 0xF0\n    ANDSETVV V3, V2",
+            vec![Eq("V1", 0), Eq("V3", 0)],
+        ),
+        case(
+            "SETDTV and SETVDT",
+            "    SETVNN V1, 0\n    SETDTV V1\n    SETVDT V2\n    SETVNN V1, 200\n    SETDTV V1\n    SETVDT V3",
+            vec![Eq("V2", 0), Ne("V3", 0)],
+        ),
+        // 3 ticks of the delay timer, plus whichever tick lands between the two SETs or before the result is recorded
+        case(
+            "SETSTV counts down",
+            "    SETVNN V1, 200\n    SETSTV V1\n    SETVNN V1, 3\n    SETDTV V1\n@wait:\n    SETVDT V1\n    SKIPIFVNN V1, 0\n    JMP @wait",
+            vec![SoundTimer(195, 197)],
+        ),
+        case("SETSTV of 0 stops the sound", "    SETVNN V1, 0\n    SETSTV V1", vec![SoundTimer(0, 0)]),
+        case(
+            "LOADAUDIO and SETPITCH",
+            "    SETINNN @data\n    LOADAUDIO\n    SETVNN V1, 112\n    SETPITCH V1\n    JMP @after\n@data:\n    db 0xFF, 0, 0xF0, 0x0F, 0xFF, 0, 0xF0, 0x0F, 0xFF, 0, 0xF0, 0x0F, 0xFF, 0, 0xF0, 0x0F\n@after:",
+            vec![Pitch(112), Pattern([0xFF, 0, 0xF0, 0x0F, 0xFF, 0, 0xF0, 0x0F, 0xFF, 0, 0xF0, 0x0F, 0xFF, 0, 0xF0, 0x0F])],
+        ),
+        case(
+            "DRAW sets VF on collision",
+            "    CLR\n    SETINNN @dot\n    SETVNN V1, 10\n    SETVNN V2, 10\n    DRAW V1, V2, 1\n    SETVV V3, VF\n    DRAW V1, V2, 1\n    JMP @after\n@dot:\n    db 0x80, 0x80\n@after:",
+            vec![Eq("V3", 0), Eq("VF", 1)],
+        ),
+        case(
+            "CLR",
+            "    SETINNN @dot\n    SETVNN V1, 10\n    SETVNN V2, 10\n    DRAW V1, V2, 1\n    CLR\n    DRAW V1, V2, 1\n    CLR\n    JMP @after\n@dot:\n    db 0x80, 0x80\n@after:",
+            vec![Eq("VF", 0)],
+        ),
+        case(
+            "DRAW wraps its starting position",
+            "    SETINNN @dot\n    SETVNN V1, 74\n    SETVNN V2, 35\n    DRAW V1, V2, 1\n    SETVNN V1, 10\n    SETVNN V2, 3\n    DRAW V1, V2, 1\n    JMP @after\n@dot:\n    db 0x80, 0x80\n@after:",
+            vec![Eq("VF", 1)],
+        ),
+        case(
+            if q.clip_sprites { "DRAW clips at the edge" } else { "DRAW wraps at the edge" },
+            "    SETINNN @row\n    SETVNN V1, 60\n    SETVNN V2, 0\n    DRAW V1, V2, 1\n    SETINNN @dot\n    SETVNN V1, 1\n    DRAW V1, V2, 1\n    CLR\n    JMP @after\n@row:\n    db 0xFF, 0xFF\n@dot:\n    db 0x80, 0x80\n@after:",
+            vec![Eq("VF", if q.clip_sprites { 0 } else { 1 })],
+        ),
+        case(
+            "DRAW 16x16",
+            "    SETINNN @big\n    SETVNN V1, 8\n    SETVNN V2, 8\n    DRAW V1, V2, 0\n    SETINNN @dot\n    SETVNN V1, 23\n    SETVNN V2, 23\n    DRAW V1, V2, 1\n    CLR\n    JMP @after\n@big:\n    db 0x80, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0\n    db 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0x01\n@dot:\n    db 0x80, 0x80\n@after:",
+            vec![Eq("VF", 1)],
+        ),
+        case(
+            "SCROLLDOWN",
+            "    SETINNN @dot\n    SETVNN V1, 5\n    SETVNN V2, 5\n    DRAW V1, V2, 1\n    SCROLLDOWN 2\n    SETVNN V2, 7\n    DRAW V1, V2, 1\n    CLR\n    JMP @after\n@dot:\n    db 0x80, 0x80\n@after:",
+            vec![Eq("VF", 1)],
+        ),
+        case(
+            "SCROLLUP",
+            "    SETINNN @dot\n    SETVNN V1, 5\n    SETVNN V2, 5\n    DRAW V1, V2, 1\n    SCROLLUP 3\n    SETVNN V2, 2\n    DRAW V1, V2, 1\n    CLR\n    JMP @after\n@dot:\n    db 0x80, 0x80\n@after:",
+            vec![Eq("VF", 1)],
+        ),
+        case(
+            "SCROLLRIGHT and SCROLLLEFT",
+            "    SETINNN @dot\n    SETVNN V1, 5\n    SETVNN V2, 5\n    DRAW V1, V2, 1\n    SCROLLRIGHT\n    SETVNN V1, 9\n    DRAW V1, V2, 1\n    SETVV V3, VF\n    DRAW V1, V2, 1\n    SCROLLLEFT\n    SETVNN V1, 1\n    DRAW V1, V2, 1\n    CLR\n    JMP @after\n@dot:\n    db 0x80, 0x80\n@after:",
+            vec![Eq("V3", 1), Eq("VF", 0)],
+        ),
+        // 100,40 lands on 36,8 in lores, and switching modes clears the screen
+        case(
+            "HIRES and LORES",
+            "    HIRES\n    SETINNN @dot\n    SETVNN V1, 100\n    SETVNN V2, 40\n    DRAW V1, V2, 1\n    SETVNN V1, 36\n    SETVNN V2, 8\n    DRAW V1, V2, 1\n    SETVV V3, VF\n    LORES\n    DRAW V1, V2, 1\n    CLR\n    JMP @after\n@dot:\n    db 0x80, 0x80\n@after:",
+            vec![Eq("V3", 0), Eq("VF", 0)],
+        ),
+        case(
+            "SETPLANES",
+            "    SETINNN @dot\n    SETVNN V1, 20\n    SETVNN V2, 20\n    SETPLANES 2\n    DRAW V1, V2, 1\n    SETPLANES 1\n    DRAW V1, V2, 1\n    SETVV V3, VF\n    SETPLANES 3\n    DRAW V1, V2, 1\n    CLR\n    SETPLANES 1\n    JMP @after\n@dot:\n    db 0x80, 0x80\n@after:",
+            vec![Eq("V3", 0), Eq("VF", 1)],
+        ),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_case_passes_under_every_profile() {
+        for name in Quirks::PRESETS {
+            let quirks = Quirks::from_name(name).unwrap();
+            let results = run(&quirks).unwrap_or_else(|err| panic!("{} stopped: {}", name, err));
+
+            assert_eq!(results.len(), case_names(&quirks).len());
+            for result in results {
+                assert_eq!(result.passed, Some(true), "{} under {}", result.name, name);
+            }
+        }
+    }
+}
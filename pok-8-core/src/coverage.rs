@@ -0,0 +1,253 @@
+use crate::disasm::*;
+use crate::error::*;
+use crate::instruction::*;
+use crate::state::fnv1a;
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+// Which addresses ever ran and which way every skip went, set up with Emu::set_coverage.
+// Runs are merged by adding their counts, and saved as text tied to the ROM they ran
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Coverage {
+    hits: BTreeMap<u16, u64>,         // executions of each address
+    skips: BTreeMap<u16, (u64, u64)>, // SKIP* at each address: times it skipped, times it fell through
+}
+
+impl Coverage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn hits(&self, addr: u16) -> u64 {
+        self.hits.get(&addr).copied().unwrap_or(0)
+    }
+
+    // (skipped, fell through), None if no skip ever ran there
+    pub fn skip(&self, addr: u16) -> Option<(u64, u64)> {
+        self.skips.get(&addr).copied()
+    }
+
+    pub fn merge(&mut self, other: &Coverage) {
+        for (addr, count) in &other.hits {
+            self.add_hits(*addr, *count);
+        }
+        for (addr, (skipped, fell)) in &other.skips {
+            self.add_skips(*addr, *skipped, *fell);
+        }
+    }
+
+    // Counts read from a file can be anything, so every count stops at u64::MAX rather than overflow
+    fn add_hits(&mut self, addr: u16, count: u64) {
+        let hits = self.hits.entry(addr).or_insert(0);
+        *hits = hits.saturating_add(count);
+    }
+
+    fn add_skips(&mut self, addr: u16, skipped: u64, fell: u64) {
+        let outcomes = self.skips.entry(addr).or_insert((0, 0));
+        outcomes.0 = outcomes.0.saturating_add(skipped);
+        outcomes.1 = outcomes.1.saturating_add(fell);
+    }
+
+    // `rom <fnv1a>` and then one line per address: `hit 0200 12` or `skip 0206 1 2`
+    pub fn to_text(&self, rom: &[u8]) -> String {
+        let mut out = String::new();
+
+        writeln!(out, "# pok8 coverage").unwrap();
+        writeln!(out, "rom {:016x}", fnv1a(rom)).unwrap();
+        for (addr, count) in &self.hits {
+            writeln!(out, "hit {:04X} {}", addr, count).unwrap();
+        }
+        for (addr, (skipped, fell)) in &self.skips {
+            writeln!(out, "skip {:04X} {} {}", addr, skipped, fell).unwrap();
+        }
+
+        out
+    }
+
+    // Refuses coverage saved against a different ROM, the addresses wouldn't mean anything
+    pub fn from_text(text: &str, rom: &[u8]) -> Result<Self, EmuError> {
+        let mut coverage = Self::new();
+        let mut rom_hash = None;
+
+        for (idx, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let bad = || invalid(&format!("can't read line {}: '{}'", idx + 1, line));
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let addr = || fields.get(1).and_then(|text| u16::from_str_radix(text, 16).ok()).ok_or_else(bad);
+            let count = |at: usize| fields.get(at).and_then(|text| text.parse::<u64>().ok()).ok_or_else(bad);
+
+            match fields[0] {
+                "rom" if fields.len() == 2 => {
+                    rom_hash = Some(u64::from_str_radix(fields[1], 16).map_err(|_| bad())?);
+                }
+                "hit" if fields.len() == 3 => coverage.add_hits(addr()?, count(2)?),
+                "skip" if fields.len() == 4 => coverage.add_skips(addr()?, count(2)?, count(3)?),
+                _ => return Err(bad()),
+            }
+        }
+
+        match rom_hash {
+            Some(hash) if hash == fnv1a(rom) => Ok(coverage),
+            Some(_) => Err(invalid("recorded against a different ROM")),
+            None => Err(invalid("no rom line")),
+        }
+    }
+
+    // Lines of the ROM's disassembly that ran and skip outcomes that were seen, out of how many there are
+    pub fn summary(&self, rom: &[u8], base_addr: u16) -> String {
+        let lines = disassemble(rom, base_addr);
+        let code: Vec<&Line> = lines.iter().filter(|line| line.instruction.is_some()).collect();
+        let executed = code.iter().filter(|line| self.hits(line.addr) > 0).count();
+        let skips: Vec<&&Line> = code.iter().filter(|line| line.instruction.is_some_and(is_skip)).collect();
+        let seen: usize = skips
+            .iter()
+            .map(|line| self.skip(line.addr).map_or(0, |(skipped, fell)| (skipped > 0) as usize + (fell > 0) as usize))
+            .sum();
+        let percent = |count: usize, total: usize| count as f64 * 100.0 / total.max(1) as f64;
+
+        let mut out = String::new();
+        writeln!(out, "lines executed: {} of {} ({:.1}%)", executed, code.len(), percent(executed, code.len())).unwrap();
+        writeln!(out, "skip outcomes:  {} of {} ({:.1}%)", seen, skips.len() * 2, percent(seen, skips.len() * 2))
+            .unwrap();
+
+        let stray = self.stray(&lines, rom, base_addr);
+        if stray > 0 {
+            writeln!(out, "{} executed addresses start partway into a line of the disassembly", stray).unwrap();
+        }
+
+        out
+    }
+
+    // The ROM as pok8-asm source, the file an lcov report points at. One label or instruction per line
+    pub fn listing(rom: &[u8], base_addr: u16) -> String {
+        source_lines(rom, base_addr).into_iter().map(|(text, _)| format!("{}\n", text)).collect()
+    }
+
+    // The listing with a count in front of every line, gcov style: - for labels and data,
+    // ##### for code that never ran, and the skip outcomes after every SKIP*
+    pub fn annotate(&self, rom: &[u8], base_addr: u16) -> String {
+        let mut out = String::new();
+
+        for (text, line) in source_lines(rom, base_addr) {
+            let (count, outcomes) = match line {
+                Some(line) => {
+                    let hits = self.hits(line.addr);
+                    let count = match (line.instruction, hits) {
+                        (None, 0) => "-".to_string(),
+                        (_, 0) => "#####".to_string(),
+                        _ => hits.to_string(),
+                    };
+
+                    let outcomes = match line.instruction {
+                        Some(op) if is_skip(op) => {
+                            let (skipped, fell) = self.skip(line.addr).unwrap_or((0, 0));
+                            format!("  skipped {}, fell through {}", skipped, fell)
+                        }
+                        _ => String::new(),
+                    };
+
+                    (count, outcomes)
+                }
+                None => ("-".to_string(), String::new()),
+            };
+
+            writeln!(out, "{:>9}:  {}{}", count, text, outcomes).unwrap();
+        }
+
+        out
+    }
+
+    // An lcov tracefile against listing(), source is the path it was written to. Every instruction is a line and
+    // every SKIP* a block with two branches, skipped first and fell through second
+    pub fn lcov(&self, rom: &[u8], base_addr: u16, source: &str) -> String {
+        let mut out = String::new();
+        let (mut found, mut hit, mut branches, mut branches_hit) = (0, 0, 0, 0);
+
+        writeln!(out, "TN:\nSF:{}", source).unwrap();
+        for (number, (_, line)) in source_lines(rom, base_addr).into_iter().enumerate() {
+            let Some(line) = line.filter(|line| line.instruction.is_some()) else {
+                continue;
+            };
+            let number = number + 1;
+            let count = self.hits(line.addr);
+
+            found += 1;
+            hit += (count > 0) as usize;
+            writeln!(out, "DA:{},{}", number, count).unwrap();
+
+            if line.instruction.is_some_and(is_skip) {
+                let (skipped, fell) = self.skip(line.addr).unwrap_or((0, 0));
+                for (branch, taken) in [skipped, fell].into_iter().enumerate() {
+                    // lcov's - is a branch whose block never ran, 0 is one that ran but never went this way
+                    let taken = if count == 0 { "-".to_string() } else { taken.to_string() };
+                    writeln!(out, "BRDA:{},0,{},{}", number, branch, taken).unwrap();
+                }
+                branches += 2;
+                branches_hit += (skipped > 0) as usize + (fell > 0) as usize;
+            }
+        }
+
+        writeln!(out, "BRF:{}\nBRH:{}\nLF:{}\nLH:{}\nend_of_record", branches, branches_hit, found, hit).unwrap();
+        out
+    }
+
+    // Called after op ran, a skip that went ahead left PC further on than the next instruction
+    pub(crate) fn record(&mut self, addr: u16, op: Instruction, pc: u16) {
+        self.add_hits(addr, 1);
+
+        if is_skip(op) {
+            let fell = pc == addr.wrapping_add(2);
+            self.add_skips(addr, !fell as u64, fell as u64);
+        }
+    }
+
+    // Addresses inside the ROM that ran without being where the linear sweep thought an instruction starts
+    fn stray(&self, lines: &[Line], rom: &[u8], base_addr: u16) -> usize {
+        let end = base_addr as usize + rom.len();
+        self.hits
+            .keys()
+            .filter(|addr| (base_addr as usize..end).contains(&(**addr as usize)))
+            .filter(|addr| lines.binary_search_by_key(*addr, |line| line.addr).is_err())
+            .count()
+    }
+}
+
+fn is_skip(op: Instruction) -> bool {
+    matches!(
+        op,
+        Instruction::SKIPIFVNN(..)
+            | Instruction::SKIPIFNOTVNN(..)
+            | Instruction::SKIPIFVV(..)
+            | Instruction::SKIPIFNOTVV(..)
+            | Instruction::SKIPIFKEY(..)
+            | Instruction::SKIPIFNOTKEY(..)
+    )
+}
+
+// pok8-disasm --source, labels included, paired with the disassembled line each one came from
+fn source_lines(rom: &[u8], base_addr: u16) -> Vec<(String, Option<Line>)> {
+    let lines = disassemble(rom, base_addr);
+    let labels = labels(&lines);
+    let mut source = Vec::new();
+
+    for line in lines {
+        if let Some(label) = labels.get(&line.addr) {
+            source.push((format!("{}:", label), None));
+        }
+        let text = format!("    {:<28}; {:04X}  {}", line.text(&labels), line.addr, line.opcode());
+        source.push((text, Some(line)));
+    }
+
+    source
+}
+
+fn invalid(reason: &str) -> EmuError {
+    EmuError::InvalidCoverage {
+        reason: reason.to_string(),
+    }
+}
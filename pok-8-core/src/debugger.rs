@@ -0,0 +1,319 @@
+use crate::emu::*;
+use crate::error::*;
+use crate::instruction::*;
+
+// How a conditional breakpoint compares its register
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compare {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl Compare {
+    pub fn from_symbol(symbol: &str) -> Option<Self> {
+        match symbol {
+            "==" => Some(Compare::Eq),
+            "!=" => Some(Compare::Ne),
+            "<" => Some(Compare::Lt),
+            "<=" => Some(Compare::Le),
+            ">" => Some(Compare::Gt),
+            ">=" => Some(Compare::Ge),
+            _ => None,
+        }
+    }
+
+    pub fn symbol(self) -> &'static str {
+        match self {
+            Compare::Eq => "==",
+            Compare::Ne => "!=",
+            Compare::Lt => "<",
+            Compare::Le => "<=",
+            Compare::Gt => ">",
+            Compare::Ge => ">=",
+        }
+    }
+
+    pub fn holds<T: Ord>(self, lhs: T, rhs: T) -> bool {
+        match self {
+            Compare::Eq => lhs == rhs,
+            Compare::Ne => lhs != rhs,
+            Compare::Lt => lhs < rhs,
+            Compare::Le => lhs <= rhs,
+            Compare::Gt => lhs > rhs,
+            Compare::Ge => lhs >= rhs,
+        }
+    }
+}
+
+// VX <compare> value
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Condition {
+    pub reg: Reg,
+    pub compare: Compare,
+    pub value: u8,
+}
+
+// Stops before the instruction at addr runs, if the condition (when there is one) holds
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Breakpoint {
+    pub addr: u16,
+    pub condition: Option<Condition>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Access {
+    Read,
+    Write,
+    ReadWrite,
+}
+
+impl Access {
+    fn matches(self, write: bool) -> bool {
+        match self {
+            Access::Read => !write,
+            Access::Write => write,
+            Access::ReadWrite => true,
+        }
+    }
+}
+
+// Stops after an instruction that touched the watched RAM or I register
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Watchpoint {
+    Ram { start: u16, end: u16, access: Access }, // start to end inclusive
+    I { access: Access },
+}
+
+// Why a step or run handed control back
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StopReason {
+    Step,                                  // the step asked for is done
+    Breakpoint { addr: u16 },              // about to run the instruction at addr
+    RamWatch { addr: usize, write: bool }, // the last instruction read or wrote addr
+    IWatch { write: bool },                // the last instruction used or changed I
+    Halted,                                // 00FD ran
+    Error(EmuError),                       // the last instruction failed
+    Limit,                                 // ran the number of cycles allowed without stopping
+}
+
+// Runs an Emu an instruction at a time, checking breakpoints before each one and watchpoints after.
+// The timers tick every CYCLES_PER_FRAME instructions so a ROM keeps the pace it has in a frontend.
+pub struct Debugger {
+    emu: Emu,
+    breakpoints: Vec<Breakpoint>,
+    watchpoints: Vec<Watchpoint>,
+    cycles: u64,        // instructions run under the debugger
+    frame_cycle: usize, // instructions since the timers last ticked
+}
+
+impl Debugger {
+    pub fn new(mut emu: Emu) -> Self {
+        emu.set_access_log(true);
+
+        Self {
+            emu,
+            breakpoints: Vec::new(),
+            watchpoints: Vec::new(),
+            cycles: 0,
+            frame_cycle: 0,
+        }
+    }
+
+    pub fn emu(&self) -> &Emu {
+        &self.emu
+    }
+
+    pub fn emu_mut(&mut self) -> &mut Emu {
+        &mut self.emu
+    }
+
+    pub fn into_emu(mut self) -> Emu {
+        self.emu.set_access_log(false);
+        self.emu
+    }
+
+    pub fn cycles(&self) -> u64 {
+        self.cycles
+    }
+
+    pub fn breakpoints(&self) -> &[Breakpoint] {
+        &self.breakpoints
+    }
+
+    pub fn add_breakpoint(&mut self, breakpoint: Breakpoint) {
+        self.breakpoints.push(breakpoint);
+    }
+
+    // by position in breakpoints()
+    pub fn remove_breakpoint(&mut self, idx: usize) -> Option<Breakpoint> {
+        (idx < self.breakpoints.len()).then(|| self.breakpoints.remove(idx))
+    }
+
+    pub fn watchpoints(&self) -> &[Watchpoint] {
+        &self.watchpoints
+    }
+
+    pub fn add_watchpoint(&mut self, watchpoint: Watchpoint) {
+        self.watchpoints.push(watchpoint);
+    }
+
+    pub fn remove_watchpoint(&mut self, idx: usize) -> Option<Watchpoint> {
+        (idx < self.watchpoints.len()).then(|| self.watchpoints.remove(idx))
+    }
+
+    // Step into, one instruction whatever it is
+    pub fn step(&mut self) -> StopReason {
+        self.run(1, |_| true)
+    }
+
+    // A CALL runs the whole subroutine, anything else is a single step
+    pub fn step_over(&mut self, limit: u64) -> StopReason {
+        match self.emu.next_instruction() {
+            Ok(Instruction::CALL(_)) => {
+                let ret = self.emu.pc().wrapping_add(2);
+                let depth = self.emu.sp();
+                self.run(limit, |emu| emu.pc() == ret && emu.sp() == depth)
+            }
+            _ => self.step(),
+        }
+    }
+
+    // Runs until the current subroutine returns, with nothing on the stack that's never
+    pub fn step_out(&mut self, limit: u64) -> StopReason {
+        let depth = self.emu.sp();
+        self.run(limit, |emu| emu.sp() < depth)
+    }
+
+    // Runs until a breakpoint or watchpoint hits, the ROM halts or fails, or limit instructions have run
+    pub fn run_until_break(&mut self, limit: u64) -> StopReason {
+        self.run(limit, |_| false)
+    }
+
+    fn run(&mut self, limit: u64, done: impl Fn(&Emu) -> bool) -> StopReason {
+        for count in 0..limit {
+            // whatever is at PC to begin with runs, otherwise nothing gets past a breakpoint
+            if count > 0 {
+                if let Some(addr) = self.breakpoint_hit() {
+                    return StopReason::Breakpoint { addr };
+                }
+            }
+
+            if let Some(reason) = self.cycle() {
+                return reason;
+            }
+            if done(&self.emu) {
+                return StopReason::Step;
+            }
+        }
+
+        // so a run picked up again after Limit can't slip past a breakpoint it stopped on
+        match self.breakpoint_hit() {
+            Some(addr) => StopReason::Breakpoint { addr },
+            None => StopReason::Limit,
+        }
+    }
+
+    fn breakpoint_hit(&self) -> Option<u16> {
+        let pc = self.emu.pc();
+        let v_reg = self.emu.v_reg();
+
+        self.breakpoints
+            .iter()
+            .filter(|bp| bp.addr == pc)
+            .any(|bp| match bp.condition {
+                Some(cond) => cond.compare.holds(v_reg[cond.reg.index()], cond.value),
+                None => true,
+            })
+            .then_some(pc)
+    }
+
+    // One instruction plus the timers when a frame's worth has run, Some when that has to stop the run
+    fn cycle(&mut self) -> Option<StopReason> {
+        if self.emu.is_halted() {
+            return Some(StopReason::Halted);
+        }
+
+        let op = self.emu.next_instruction().ok();
+        let i_before = self.emu.i_reg();
+
+        if let Err(err) = self.emu.cycle() {
+            return Some(StopReason::Error(err));
+        }
+
+        self.cycles += 1;
+        self.frame_cycle += 1;
+        if self.frame_cycle == CYCLES_PER_FRAME {
+            self.frame_cycle = 0;
+            self.emu.tick_timers();
+        }
+
+        if let Some(reason) = self.watchpoint_hit(op, i_before) {
+            return Some(reason);
+        }
+        self.emu.is_halted().then_some(StopReason::Halted)
+    }
+
+    fn watchpoint_hit(&self, op: Option<Instruction>, i_before: u16) -> Option<StopReason> {
+        for watch in &self.watchpoints {
+            match *watch {
+                Watchpoint::Ram { start, end, access } => {
+                    let hit = self.emu.accesses().iter().find(|mem| {
+                        (start as usize..=end as usize).contains(&mem.addr) && access.matches(mem.write)
+                    });
+
+                    if let Some(mem) = hit {
+                        return Some(StopReason::RamWatch {
+                            addr: mem.addr,
+                            write: mem.write,
+                        });
+                    }
+                }
+
+                Watchpoint::I { access } => {
+                    let writes = self.emu.i_reg() != i_before || op.is_some_and(writes_i);
+                    let reads = op.is_some_and(reads_i);
+
+                    if writes && access.matches(true) {
+                        return Some(StopReason::IWatch { write: true });
+                    }
+                    if reads && access.matches(false) {
+                        return Some(StopReason::IWatch { write: false });
+                    }
+                }
+            }
+        }
+
+        None
+    }
+}
+
+// Instructions that use I as an address or operand
+fn reads_i(op: Instruction) -> bool {
+    matches!(
+        op,
+        Instruction::DRAW(..)
+            | Instruction::LOADAUDIO
+            | Instruction::BCDTORAM(_)
+            | Instruction::VTORAM(_)
+            | Instruction::RAMTOV(_)
+            | Instruction::SAVERANGE(..)
+            | Instruction::LOADRANGE(..)
+            | Instruction::INCSETIV(_)
+    )
+}
+
+// Instructions that set I, even to the value it already had
+fn writes_i(op: Instruction) -> bool {
+    matches!(
+        op,
+        Instruction::SETINNN(_)
+            | Instruction::LONGI
+            | Instruction::INCSETIV(_)
+            | Instruction::SETIFONT(_)
+            | Instruction::SETIBIGFONT(_)
+    )
+}
@@ -0,0 +1,114 @@
+use crate::instruction::*;
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+// One decoded instruction, or the bytes that didn't decode
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Line {
+    pub addr: u16,
+    pub bytes: Vec<u8>,                   // 2 for an instruction, 4 for LONGI, 1 or 2 for data
+    pub instruction: Option<Instruction>, // None means data
+}
+
+impl Line {
+    pub fn opcode(&self) -> String {
+        self.bytes.iter().map(|b| format!("{:02X}", b)).collect()
+    }
+
+    // The mnemonic with jump and call targets swapped for their label where there is one
+    pub fn text(&self, labels: &BTreeMap<u16, String>) -> String {
+        match self.instruction {
+            Some(Instruction::JMP(nnn)) if labels.contains_key(&nnn.get()) => format!("JMP {}", labels[&nnn.get()]),
+            Some(Instruction::CALL(nnn)) if labels.contains_key(&nnn.get()) => format!("CALL {}", labels[&nnn.get()]),
+            Some(Instruction::LONGI) => format!("LONGI {:#06X}", u16::from_be_bytes([self.bytes[2], self.bytes[3]])),
+            Some(op) => op.to_string(),
+            None => {
+                let bytes: Vec<String> = self.bytes.iter().map(|b| format!("{:#04X}", b)).collect();
+                format!("db {}", bytes.join(", "))
+            }
+        }
+    }
+}
+
+// ADDR  OPCODE    MNEMONIC
+impl fmt::Display for Line {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:04X}  {:<8}  {}", self.addr, self.opcode(), self.text(&BTreeMap::new()))
+    }
+}
+
+// Linear sweep from the start of the ROM, base_addr is where it sits in RAM (0x200 for Emu::load).
+// Sprite data mixed in with the code decodes as whatever it happens to look like.
+pub fn disassemble(rom: &[u8], base_addr: u16) -> Vec<Line> {
+    let mut lines = Vec::new();
+    let mut pos = 0;
+
+    while pos < rom.len() {
+        let addr = base_addr.wrapping_add(pos as u16);
+
+        // an odd byte at the end can only be data
+        if pos + 1 == rom.len() {
+            lines.push(Line { addr, bytes: vec![rom[pos]], instruction: None });
+            break;
+        }
+
+        let word = u16::from_be_bytes([rom[pos], rom[pos + 1]]);
+        let instruction = Instruction::decode(word).ok();
+
+        // LONGI needs its address word, without it the opcode is just data
+        let len = match instruction {
+            Some(Instruction::LONGI) if pos + 4 <= rom.len() => 4,
+            Some(Instruction::LONGI) => {
+                lines.push(Line { addr, bytes: rom[pos..(pos + 2)].to_vec(), instruction: None });
+                pos += 2;
+                continue;
+            }
+            _ => 2,
+        };
+
+        lines.push(Line {
+            addr,
+            bytes: rom[pos..(pos + len)].to_vec(),
+            instruction,
+        });
+        pos += len;
+    }
+
+    lines
+}
+
+// Names for every JMP/CALL target that starts a line, sub_ for subroutines and loc_ for everything else
+pub fn labels(lines: &[Line]) -> BTreeMap<u16, String> {
+    let starts: BTreeSet<u16> = lines.iter().map(|line| line.addr).collect();
+    let mut labels = BTreeMap::new();
+
+    for line in lines {
+        match line.instruction {
+            Some(Instruction::CALL(nnn)) if starts.contains(&nnn.get()) => {
+                labels.insert(nnn.get(), format!("sub_{:03X}", nnn.get()));
+            }
+            Some(Instruction::JMP(nnn)) if starts.contains(&nnn.get()) => {
+                labels.entry(nnn.get()).or_insert_with(|| format!("loc_{:03X}", nnn.get()));
+            }
+            _ => (),
+        }
+    }
+
+    labels
+}
+
+// What pok8-disasm --source prints: labels on their own line, then the mnemonic with the address and opcode riding
+// along as a comment. pok8-asm reads it back into the same bytes
+pub fn source(lines: &[Line], labels: &BTreeMap<u16, String>) -> String {
+    let mut text = String::new();
+
+    for line in lines {
+        if let Some(label) = labels.get(&line.addr) {
+            text += &format!("{}:\n", label);
+        }
+        text += &format!("    {:<28}; {:04X}  {}\n", line.text(labels), line.addr, line.opcode());
+    }
+
+    text
+}
@@ -1,21 +1,39 @@
+use crate::coverage::*;
+use crate::error::*;
 use crate::instruction::*;
+use crate::profile::*;
+use crate::quirks::*;
+use crate::rng::*;
 use crate::speaker::*;
+use crate::state::*;
+use crate::trace::*;
 
 use rand::random;
 
 pub const SCREEN_WIDTH: usize = 64;
 pub const SCREEN_HEIGHT: usize = 32;
 
-const RAM_SIZE: usize = 4096;
+// SUPER-CHIP high resolution mode
+pub const HIRES_WIDTH: usize = 128;
+pub const HIRES_HEIGHT: usize = 64;
+
+pub const CYCLES_PER_FRAME: usize = 10; // instructions run between 60Hz timer ticks
+
+const RAM_SIZE: usize = 65536; // XO-CHIP, the original 4K is the bottom of it
 const NUM_REGS: usize = 16;
-const STACK_SIZE: usize = 16;
 const NUM_KEYS: usize = 16;
 const START_ADDR: u16 = 0x200;
+const NUM_RPL: usize = 16;
+const NUM_PLANES: u8 = 2;
+const PATTERN_SIZE: usize = 16;
+const DEFAULT_PITCH: u8 = 64; // 4000 samples a second
 
-const FONTSET_SIZE: usize = 80;
+pub const FONTSET_SIZE: usize = 80;
+const BIGFONT_ADDR: usize = FONTSET_SIZE; // 8x10 digits sit right after the small font
+const BIGFONT_SIZE: usize = 160;
 
 // TODO: Refactor fontset into a 2D array
-const FONTSET: [u8; FONTSET_SIZE] = [
+pub const FONTSET: [u8; FONTSET_SIZE] = [
     0xF0, 0x90, 0x90, 0x90, 0xF0, // 0
     0x20, 0x60, 0x20, 0x20, 0x70, // 1
     0xF0, 0x10, 0xF0, 0x80, 0xF0, // 2
@@ -34,356 +52,937 @@ const FONTSET: [u8; FONTSET_SIZE] = [
     0xF0, 0x80, 0xF0, 0x80, 0x80, // F
 ];
 
+// SUPER-CHIP 8x10 digits for FX30
+const BIGFONT: [u8; BIGFONT_SIZE] = [
+    0xFF, 0xFF, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xFF, 0xFF, // 0
+    0x18, 0x78, 0x78, 0x18, 0x18, 0x18, 0x18, 0x18, 0xFF, 0xFF, // 1
+    0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, // 2
+    0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, // 3
+    0xC3, 0xC3, 0xC3, 0xC3, 0xFF, 0xFF, 0x03, 0x03, 0x03, 0x03, // 4
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, // 5
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, // 6
+    0xFF, 0xFF, 0x03, 0x03, 0x06, 0x0C, 0x18, 0x18, 0x18, 0x18, // 7
+    0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, // 8
+    0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, // 9
+    0x7E, 0xFF, 0xC3, 0xC3, 0xC3, 0xFF, 0xFF, 0xC3, 0xC3, 0xC3, // A
+    0xFC, 0xFC, 0xC3, 0xC3, 0xFC, 0xFC, 0xC3, 0xC3, 0xFC, 0xFC, // B
+    0x3C, 0xFF, 0xC3, 0xC0, 0xC0, 0xC0, 0xC0, 0xC3, 0xFF, 0x3C, // C
+    0xFC, 0xFE, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xFE, 0xFC, // D
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, // E
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC0, 0xC0, 0xC0, 0xC0, // F
+];
+
+// The framebuffer at the resolution currently in use, row major.
+// Each pixel is a colour index 0-3: bit 0 is set by plane 1 and bit 1 by plane 2 (XO-CHIP),
+// plain CHIP-8 and SUPER-CHIP programs only ever produce 0 and 1
+pub struct Display<'a> {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: &'a [u8],
+}
+
+impl Display<'_> {
+    pub fn pixel(&self, x: usize, y: usize) -> u8 {
+        self.pixels[x + self.width * y]
+    }
+
+    pub fn is_lit(&self, x: usize, y: usize) -> bool {
+        self.pixel(x, y) != 0
+    }
+
+    // One line per row: . for off, # for plane 1, + for plane 2 and @ for both
+    pub fn to_text(&self) -> String {
+        let mut text = String::with_capacity((self.width + 1) * self.height);
+
+        for row in self.pixels.chunks(self.width) {
+            text.extend(row.iter().map(|pixel| match pixel {
+                0 => '.',
+                1 => '#',
+                2 => '+',
+                _ => '@',
+            }));
+            text.push('\n');
+        }
+        text
+    }
+}
+
+// How to build an Emu, Default is the modern quirks and a fresh random seed every run
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EmuConfig {
+    pub quirks: Quirks,
+    pub seed: Option<u64>, // fix this to make CXNN (and so the whole run) repeatable
+    pub rng: RngKind,
+}
+
+impl Default for EmuConfig {
+    fn default() -> Self {
+        Self {
+            quirks: Quirks::default(),
+            seed: None,
+            rng: RngKind::Xorshift,
+        }
+    }
+}
+
+// One RAM read or write made by an instruction, see Emu::set_access_log
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MemAccess {
+    pub addr: usize,
+    pub write: bool,
+}
+
 // Core Emulator Structure
 pub struct Emu {
     pc: u16,                                      // one byte program counter
-    ram: [u8; RAM_SIZE],                          // 4 kilobytes of ram
-    screen: [bool; SCREEN_WIDTH * SCREEN_HEIGHT], // screen of "single bits"
+    ram: [u8; RAM_SIZE],                          // 64 kilobytes of ram
+    screen: [u8; HIRES_WIDTH * HIRES_HEIGHT],     // one bit per plane for every pixel, only width * height of it in use
+    hires: bool,                                  // 128x64 SUPER-CHIP mode
+    planes: u8,                                   // XO-CHIP planes that draw/clear/scroll act on
     v_reg: [u8; NUM_REGS],                        // V REgisters
     i_reg: u16,                                   // indexing register
-    sp: u16,                                      // 2byte stack pointer
-    stack: [u16; STACK_SIZE], // CPU LIFO stack (could be a Deque but CHIP would've used this sp system)
+    stack: Vec<u16>,          // CPU LIFO stack of return addresses, as deep as the stack_depth quirk allows
     keys: [bool; NUM_KEYS],   // input handling
     dt: u8,                   // Delay Timer
     st: u8,                   //Sound Timer
-    buzzer: Buzzer,
+    audio: Box<dyn AudioSink>, // told when the sound timer starts and stops
+    quirks: Quirks,           // platform specific opcode behaviour
+    vblank: bool,             // set once per frame, lets DXYN go ahead under the display wait quirk
+    halted: bool,             // 00FD ran, nothing left to execute
+    rpl: [u8; NUM_RPL],       // SUPER-CHIP RPL user flags, kept across resets
+    pattern: [u8; PATTERN_SIZE], // XO-CHIP 1-bit audio samples
+    pitch: u8,                // XO-CHIP playback rate of the pattern
+    pattern_set: bool,        // F002 or FX3A has run, until then the sink plays its own tone
+    rng: Rng,                 // CXNN's random bytes, part of the state so runs can be replayed
+    log_access: bool,         // keep track of the RAM the last instruction touched, for watchpoints
+    accesses: Vec<MemAccess>,
+    tracer: Option<Tracer>,   // logs every instruction cycle() runs when set
+    profiler: Option<Profiler>, // counts where the cycles go when set
+    coverage: Option<Coverage>, // records what ran and which way skips went when set
 }
 
 impl Emu {
     pub fn init() -> Self {
+        Self::with_quirks(Quirks::default())
+    }
+
+    pub fn with_quirks(quirks: Quirks) -> Self {
+        Self::with_config(EmuConfig {
+            quirks,
+            ..EmuConfig::default()
+        })
+    }
+
+    pub fn with_config(config: EmuConfig) -> Self {
+        let seed = config.seed.unwrap_or_else(random);
+
         let mut new_emu = Self {
             pc: START_ADDR, // CHIP-8 standard for program start
             ram: [0; RAM_SIZE],
-            screen: [false; SCREEN_WIDTH * SCREEN_HEIGHT],
+            screen: [0; HIRES_WIDTH * HIRES_HEIGHT],
+            hires: false,
+            planes: 1,
             v_reg: [0; NUM_REGS],
             i_reg: 0,
-            sp: 0,
-            stack: [0; STACK_SIZE],
+            stack: Vec::new(),
             keys: [false; NUM_KEYS],
             dt: 0,
             st: 0,
-            buzzer: Buzzer::init(),
+            audio: Box::new(NullSink),
+            quirks: config.quirks,
+            vblank: false,
+            halted: false,
+            rpl: [0; NUM_RPL],
+            pattern: [0; PATTERN_SIZE],
+            pitch: DEFAULT_PITCH,
+            pattern_set: false,
+            rng: Rng::new(config.rng, seed),
+            log_access: false,
+            accesses: Vec::new(),
+            tracer: None,
+            profiler: None,
+            coverage: None,
         };
         new_emu.ram[0..FONTSET_SIZE].copy_from_slice(&FONTSET);
+        new_emu.ram[BIGFONT_ADDR..(BIGFONT_ADDR + BIGFONT_SIZE)].copy_from_slice(&BIGFONT);
 
         new_emu
     }
 
-    pub fn reset(&mut self) -> () {
+    pub fn reset(&mut self) {
         self.pc = START_ADDR;
         self.ram = [0; RAM_SIZE];
-        self.screen = [false; SCREEN_WIDTH * SCREEN_HEIGHT];
+        self.screen = [0; HIRES_WIDTH * HIRES_HEIGHT];
+        self.hires = false;
+        self.planes = 1;
         self.v_reg = [0; NUM_REGS];
         self.i_reg = 0;
-        self.sp = 0;
-        self.stack = [0; STACK_SIZE];
+        self.stack.clear();
         self.keys = [false; NUM_KEYS];
         self.dt = 0;
-        self.st = 0;
+        self.set_sound_timer(0);
+        self.vblank = false;
+        self.halted = false;
+        self.pattern = [0; PATTERN_SIZE];
+        self.pitch = DEFAULT_PITCH;
+        self.pattern_set = false;
+        self.send_pattern();
         self.ram[0..FONTSET_SIZE].copy_from_slice(&FONTSET);
+        self.ram[BIGFONT_ADDR..(BIGFONT_ADDR + BIGFONT_SIZE)].copy_from_slice(&BIGFONT);
+    }
+
+    // Emu stays silent (NullSink) until a frontend hands it an output
+    pub fn set_audio_sink(&mut self, sink: Box<dyn AudioSink>) {
+        self.audio = sink;
+    }
+
+    pub fn quirks(&self) -> Quirks {
+        self.quirks
+    }
+
+    pub fn set_quirks(&mut self, quirks: Quirks) {
+        self.quirks = quirks;
+    }
+
+    pub fn rng(&self) -> Rng {
+        self.rng
+    }
+
+    // reset() leaves the generator running on, reseed here to repeat a run from the top
+    pub fn set_rng(&mut self, rng: Rng) {
+        self.rng = rng;
     }
 
     // Main Intepreter Cycle => Fetch -> Decode -> Execute
-    pub fn cycle(&mut self) -> () {
+    pub fn cycle(&mut self) -> Result<(), EmuError> {
+        if self.halted {
+            return Ok(());
+        }
+
+        self.accesses.clear();
+        let addr = self.pc;
+
         // Fetch
-        let byte = self.fetch();
+        let byte = self.fetch().inspect_err(|err| self.trace_error(err))?;
 
         // Decode
         let op = self.decode(byte);
+        self.trace(addr, byte, op.as_ref().ok().copied());
+        let op = op.inspect_err(|err| self.trace_error(err))?;
+        self.profile(addr, op);
 
         // Execute
-        self.execute(op);
+        self.execute(op).inspect_err(|err| self.trace_error(err))?;
+        self.cover(addr, op);
+        Ok(())
     }
 
-    pub fn tick_timers(&mut self) -> () {
+    // One 60Hz frame, the unit movies and headless runs count in
+    pub fn run_frame(&mut self) -> Result<(), EmuError> {
+        for _ in 0..CYCLES_PER_FRAME {
+            self.cycle()?;
+        }
+        self.tick_timers();
+
+        Ok(())
+    }
+
+    pub fn tick_timers(&mut self) {
+        // timers tick at 60Hz, so this doubles as the vertical blank
+        self.vblank = true;
+        self.rng.tick();
+
         if self.dt > 0 {
             self.dt -= 1;
         }
 
         if self.st > 0 {
-            if self.st == 1 {
-                self.buzzer.play();
-            }
-            self.st -= 1;
+            self.set_sound_timer(self.st - 1);
         }
     }
 
-    pub fn get_display(&self) -> &[bool] {
-        &self.screen
+    pub fn get_display(&self) -> Display<'_> {
+        let (width, height) = self.resolution();
+
+        Display {
+            width,
+            height,
+            pixels: &self.screen[0..(width * height)],
+        }
     }
 
-    pub fn key_down(&mut self, idx: usize) -> () {
-        self.keys[idx] = true;
+    pub fn resolution(&self) -> (usize, usize) {
+        if self.hires {
+            (HIRES_WIDTH, HIRES_HEIGHT)
+        } else {
+            (SCREEN_WIDTH, SCREEN_HEIGHT)
+        }
     }
 
-    pub fn key_up(&mut self, idx: usize) -> () {
-        self.keys[idx] = false;
+    // Read only views for debuggers and tools
+    pub fn pc(&self) -> u16 {
+        self.pc
     }
 
-    pub fn load(&mut self, data: &[u8]) -> () {
-        self.ram[(START_ADDR as usize)..((START_ADDR as usize) + data.len())].copy_from_slice(data);
+    pub fn v_reg(&self) -> &[u8; NUM_REGS] {
+        &self.v_reg
     }
 
-}
+    pub fn i_reg(&self) -> u16 {
+        self.i_reg
+    }
 
-impl Emu {
+    // how many return addresses are on the stack
+    pub fn sp(&self) -> u16 {
+        self.stack.len() as u16
+    }
 
-    fn clear_screen(&mut self) -> () {
-        // change all screen bits to 0
-        self.screen = [false; SCREEN_WIDTH * SCREEN_HEIGHT];
+    // return addresses, oldest first, only the ones in use
+    pub fn stack(&self) -> &[u16] {
+        &self.stack
     }
 
-    // Push value to CPU stack
-    fn stack_push(&mut self, val: u16) -> () {
-        self.stack[self.sp as usize] = val;
-        self.sp += 1;
+    pub fn delay_timer(&self) -> u8 {
+        self.dt
     }
 
-    // Pop value from CPU stack
-    fn stack_pop(&mut self) -> u16 {
-        self.sp -= 1;
-        self.stack[self.sp as usize]
+    pub fn sound_timer(&self) -> u8 {
+        self.st
     }
 
-    fn fetch(&mut self) -> u16 {
-        // retrieve the two byte instruction from the ram
-        let higher_byte = self.ram[self.pc as usize] as u16;
-        let lower_byte = self.ram[(self.pc + 1) as usize] as u16;
+    pub fn ram(&self) -> &[u8] {
+        &self.ram
+    }
 
-        //bitshift + or to combine the bytes
-        let op = (higher_byte << 8) | lower_byte;
+    // Setters for debuggers poking at a paused Emu, nothing is checked beyond staying in bounds
+    pub fn set_pc(&mut self, pc: u16) {
+        self.pc = pc;
+    }
 
-        //move the pc
-        self.pc += 2;
+    pub fn set_v_reg(&mut self, reg: Reg, value: u8) {
+        self.v_reg[reg.index()] = value;
+    }
+
+    pub fn set_i_reg(&mut self, value: u16) {
+        self.i_reg = value;
+    }
+
+    pub fn set_delay_timer(&mut self, value: u8) {
+        self.dt = value;
+    }
+
+    // All of bytes or nothing, MemoryOutOfRange names the first address that doesn't fit
+    pub fn set_ram(&mut self, addr: usize, bytes: &[u8]) -> Result<(), EmuError> {
+        let end = addr.saturating_add(bytes.len());
+        if end > RAM_SIZE {
+            return Err(EmuError::MemoryOutOfRange { addr: addr.max(RAM_SIZE) });
+        }
+
+        self.ram[addr..end].copy_from_slice(bytes);
+        Ok(())
+    }
+
+    // The instruction at PC, without running it
+    pub fn next_instruction(&self) -> Result<Instruction, EmuError> {
+        let hi = self.read_code(self.pc as usize)? as u16;
+        let lo = self.read_code(self.pc as usize + 1)? as u16;
+
+        Instruction::try_from((hi << 8) | lo).map_err(|err| EmuError::UnknownOpcode {
+            opcode: err.opcode,
+            addr: self.pc,
+        })
+    }
+
+    // Replaces any tracer already set without finishing it, take_tracer() first to keep its output
+    pub fn set_tracer(&mut self, tracer: Tracer) {
+        self.tracer = Some(tracer);
+    }
+
+    pub fn take_tracer(&mut self) -> Option<Tracer> {
+        self.tracer.take()
+    }
+
+    pub fn set_profiler(&mut self, profiler: Profiler) {
+        self.profiler = Some(profiler);
+    }
+
+    // for a look at the numbers so far without stopping
+    pub fn profiler(&self) -> Option<&Profiler> {
+        self.profiler.as_ref()
+    }
+
+    pub fn take_profiler(&mut self) -> Option<Profiler> {
+        self.profiler.take()
+    }
+
+    pub fn set_coverage(&mut self, coverage: Coverage) {
+        self.coverage = Some(coverage);
+    }
+
+    pub fn coverage(&self) -> Option<&Coverage> {
+        self.coverage.as_ref()
+    }
+
+    pub fn take_coverage(&mut self) -> Option<Coverage> {
+        self.coverage.take()
+    }
+
+    // Off by default, when on accesses() lists every RAM read and write of the last cycle
+    pub fn set_access_log(&mut self, on: bool) {
+        self.log_access = on;
+        self.accesses.clear();
+    }
+
+    pub fn accesses(&self) -> &[MemAccess] {
+        &self.accesses
+    }
+
+    // true once the ROM has run 00FD
+    pub fn is_halted(&self) -> bool {
+        self.halted
+    }
+
+    // XO-CHIP audio, 128 1-bit samples played back at pattern_rate() while the sound timer runs.
+    // None until the ROM loads a pattern or sets the pitch, plain CHIP-8 keeps the sink's buzzer tone
+    pub fn audio_pattern(&self) -> Option<&[u8; PATTERN_SIZE]> {
+        self.pattern_set.then_some(&self.pattern)
+    }
+
+    pub fn pitch(&self) -> u8 {
+        self.pitch
+    }
+
+    // samples per second for the current pitch
+    pub fn pattern_rate(&self) -> f32 {
+        pattern_rate(self.pitch)
+    }
+
+    pub fn rpl_flags(&self) -> &[u8] {
+        &self.rpl
+    }
+
+    // frontends restore these from disk, extra bytes are ignored
+    pub fn set_rpl_flags(&mut self, flags: &[u8]) {
+        let len = flags.len().min(NUM_RPL);
+        self.rpl[0..len].copy_from_slice(&flags[0..len]);
+    }
+
+    // out of range keys are ignored rather than trusted
+    pub fn key_down(&mut self, idx: usize) {
+        if let Some(key) = self.keys.get_mut(idx) {
+            *key = true;
+        }
+    }
 
-        op
+    pub fn key_up(&mut self, idx: usize) {
+        if let Some(key) = self.keys.get_mut(idx) {
+            *key = false;
+        }
     }
 
-    fn decode(&mut self, byte: u16) -> Instruction {
-        //unpack the digits so we can pattern match them
-        let dig1 = (byte & 0xF000) >> 12;
-        let dig2 = (byte & 0x0F00) >> 8;
-        let dig3 = (byte & 0x00F0) >> 4;
-        let dig4 = byte & 0x000F;
+    // keypad as one bit per key, bit 0 is key 0
+    pub fn keys_mask(&self) -> u16 {
+        self.keys
+            .iter()
+            .enumerate()
+            .fold(0, |mask, (idx, down)| mask | ((*down as u16) << idx))
+    }
 
-        match (dig1, dig2, dig3, dig4) {
-            (0, 0, 0, 0) => Instruction::NOP,
+    pub fn set_keys_mask(&mut self, mask: u16) {
+        for (idx, key) in self.keys.iter_mut().enumerate() {
+            *key = mask & (1 << idx) != 0;
+        }
+    }
 
-            (0, 0, 0xE, 0) => Instruction::CLR,
+    pub fn load(&mut self, data: &[u8]) -> Result<(), EmuError> {
+        let start = START_ADDR as usize;
+        let max = RAM_SIZE - start;
 
-            (0, 0, 0xE, 0xE) => Instruction::RET,
+        if data.len() > max {
+            return Err(EmuError::RomTooLarge {
+                size: data.len(),
+                max,
+            });
+        }
 
-            (1, _, _, _) => Instruction::JMP(byte & 0xFFF),
+        self.ram[start..(start + data.len())].copy_from_slice(data);
+        Ok(())
+    }
 
-            (2, _, _, _) => Instruction::CALL(byte & 0xFFF),
+    pub fn snapshot(&self) -> SaveState {
+        SaveState {
+            pc: self.pc,
+            ram: self.ram.to_vec(),
+            screen: self.screen.to_vec(),
+            hires: self.hires,
+            planes: self.planes,
+            v_reg: self.v_reg,
+            i_reg: self.i_reg,
+            stack: self.stack.clone(),
+            keys: self.keys,
+            dt: self.dt,
+            st: self.st,
+            vblank: self.vblank,
+            halted: self.halted,
+            rpl: self.rpl,
+            pattern: self.pattern,
+            pitch: self.pitch,
+            pattern_set: self.pattern_set,
+            quirks: self.quirks,
+            rng: self.rng,
+        }
+    }
 
-            (3, _, _, _) => Instruction::SKIPIFVNN(dig2, byte & 0xFF),
+    pub fn restore(&mut self, state: &SaveState) -> Result<(), EmuError> {
+        if state.ram.len() != RAM_SIZE || state.screen.len() != HIRES_WIDTH * HIRES_HEIGHT {
+            return Err(EmuError::InvalidSaveState {
+                reason: "memory or screen size doesn't match this build".to_string(),
+            });
+        }
+        if state.stack.len() > state.quirks.stack_depth {
+            return Err(EmuError::InvalidSaveState {
+                reason: "more return addresses than its stack depth allows".to_string(),
+            });
+        }
 
-            (4, _, _, _) => Instruction::SKIPIFNOTVNN(dig2, byte & 0xFF),
+        self.pc = state.pc;
+        self.ram.copy_from_slice(&state.ram);
+        self.screen.copy_from_slice(&state.screen);
+        self.hires = state.hires;
+        self.planes = state.planes;
+        self.v_reg = state.v_reg;
+        self.i_reg = state.i_reg;
+        self.stack = state.stack.clone();
+        self.keys = state.keys;
+        self.dt = state.dt;
+        self.set_sound_timer(state.st);
+        self.vblank = state.vblank;
+        self.halted = state.halted;
+        self.rpl = state.rpl;
+        self.pattern = state.pattern;
+        self.pitch = state.pitch;
+        self.pattern_set = state.pattern_set;
+        self.quirks = state.quirks;
+        self.rng = state.rng;
+
+        self.send_pattern();
+        Ok(())
+    }
 
-            (5, _, _, 0) => Instruction::SKIPIFVV(dig2, dig3),
+    // snapshot() in the versioned binary format from state.rs
+    pub fn save_state(&self) -> Vec<u8> {
+        self.snapshot().to_bytes()
+    }
 
-            (6, _, _, _) => Instruction::SETVNN(dig2, byte & 0xFF),
+    pub fn load_state(&mut self, data: &[u8]) -> Result<(), EmuError> {
+        self.restore(&SaveState::from_bytes(data)?)
+    }
 
-            (7, _, _, _) => Instruction::INCSETVNN(dig2, byte & 0xFF),
+    // Fingerprint of what the program can see and change, two runs that end on the same hash ended the same. Laid out
+    // here rather than taken from save_state(), so the golden scenarios only change when behaviour does, not the format
+    pub fn state_hash(&self) -> u64 {
+        let (width, height) = self.resolution();
+        let mut bytes = Vec::with_capacity(RAM_SIZE + width * height + 64);
+
+        bytes.extend_from_slice(&self.pc.to_le_bytes());
+        bytes.extend_from_slice(&self.i_reg.to_le_bytes());
+        bytes.extend_from_slice(&self.v_reg);
+        bytes.extend_from_slice(&(self.stack.len() as u64).to_le_bytes());
+        for addr in &self.stack {
+            bytes.extend_from_slice(&addr.to_le_bytes());
+        }
+        bytes.extend_from_slice(&[self.dt, self.st, self.hires as u8, self.planes, self.halted as u8]);
+        bytes.extend_from_slice(&self.ram);
+        bytes.extend_from_slice(&self.screen[..(width * height)]);
 
-            (8, _, _, 0) => Instruction::SETVV(dig2, dig3),
+        fnv1a(&bytes)
+    }
+
+}
 
-            (8, _, _, 1) => Instruction::ORSETVV(dig2, dig3),
+impl Emu {
+
+    fn clear_screen(&mut self) {
+        // change the selected planes' bits to 0
+        for pixel in self.screen.iter_mut() {
+            *pixel &= !self.planes;
+        }
+    }
 
-            (8, _, _, 2) => Instruction::ANDSETVV(dig2, dig3),
+    // Move the selected planes by (dx, dy), pixels pushed off the edge are lost
+    fn scroll(&mut self, dx: isize, dy: isize) {
+        let (width, height) = self.resolution();
+        let old = self.screen;
+
+        self.clear_screen();
+        for y in 0..height {
+            for x in 0..width {
+                let src_x = x as isize - dx;
+                let src_y = y as isize - dy;
+
+                if src_x >= 0 && src_y >= 0 && (src_x as usize) < width && (src_y as usize) < height {
+                    self.screen[x + width * y] |= old[src_x as usize + width * src_y as usize] & self.planes;
+                }
+            }
+        }
+    }
 
-            (8, _, _, 3) => Instruction::XORSETVV(dig2, dig3),
+    fn set_hires(&mut self, hires: bool) {
+        // the stride changes with the mode so the old picture can't be kept
+        self.hires = hires;
+        self.screen = [0; HIRES_WIDTH * HIRES_HEIGHT];
+    }
 
-            (8, _, _, 4) => Instruction::INCSETVV(dig2, dig3),
+    // Skip the next instruction, which is twice as long if it is F000 NNNN
+    fn skip_next(&mut self) {
+        let next = self.ram.get(self.pc as usize..(self.pc as usize + 2));
+        let len = if next == Some(&[0xF0, 0x00][..]) { 4 } else { 2 };
 
-            (8, _, _, 5) => Instruction::DECSETVV(dig2, dig3),
+        self.pc = self.pc.wrapping_add(len);
+    }
 
-            (8, _, _, 6) => Instruction::SHIFTRV(dig2),
+    // Tells the sink what to play, the pattern once there is one and its own tone before that
+    fn send_pattern(&mut self) {
+        let pattern = self.pattern_set.then_some(&self.pattern);
+        self.audio.pattern(pattern, self.pitch);
+    }
 
-            (8, _, _, 7) => Instruction::DIFFSETVV(dig2, dig3),
+    // Sound plays for as long as ST is non-zero, the sink only hears about the edges
+    pub fn set_sound_timer(&mut self, st: u8) {
+        match (self.st > 0, st > 0) {
+            (false, true) => self.audio.sound_on(),
+            (true, false) => self.audio.sound_off(),
+            _ => (),
+        }
 
-            (8, _, _, 0xE) => Instruction::SHIFTLV(dig2),
+        self.st = st;
+    }
 
-            (9, _, _, 0) => Instruction::SKIPIFNOTVV(dig2, dig3),
+    // Address of the instruction currently being executed (pc has already moved past it)
+    fn op_addr(&self) -> u16 {
+        self.pc.wrapping_sub(2)
+    }
 
-            (0xA, _, _, _) => Instruction::SETINNN(byte & 0xFFF),
+    // Push value to CPU stack
+    fn stack_push(&mut self, val: u16) -> Result<(), EmuError> {
+        if self.stack.len() >= self.quirks.stack_depth {
+            return Err(EmuError::StackOverflow {
+                addr: self.op_addr(),
+            });
+        }
 
-            (0xB, _, _, _) => Instruction::JMPV(byte & 0xfFF),
+        self.stack.push(val);
+        Ok(())
+    }
 
-            (0xC, _, _, _) => Instruction::RAND(dig2, byte & 0xFF),
+    // Pop value from CPU stack
+    fn stack_pop(&mut self) -> Result<u16, EmuError> {
+        let addr = self.op_addr();
+        self.stack.pop().ok_or(EmuError::StackUnderflow { addr })
+    }
 
-            (0xD, _, _, _) => Instruction::DRAW(dig2, dig3, dig4),
+    // Bounds checked RAM access
+    // Opcodes and their operand words, never logged as accesses
+    fn read_code(&self, addr: usize) -> Result<u8, EmuError> {
+        self.ram
+            .get(addr)
+            .copied()
+            .ok_or(EmuError::MemoryOutOfRange { addr })
+    }
 
-            (0xE, _, 9, 0xE) => Instruction::SKIPIFKEY(dig2),
+    fn read_ram(&mut self, addr: usize) -> Result<u8, EmuError> {
+        let val = self.read_code(addr)?;
 
-            (0xE, _, 0xA, 1) => Instruction::SKIPIFNOTKEY(dig2),
+        if self.log_access {
+            self.accesses.push(MemAccess { addr, write: false });
+        }
+        Ok(val)
+    }
 
-            (0xF, _, 0, 7) => Instruction::SETVDT(dig2),
+    fn write_ram(&mut self, addr: usize, val: u8) -> Result<(), EmuError> {
+        let cell = self
+            .ram
+            .get_mut(addr)
+            .ok_or(EmuError::MemoryOutOfRange { addr })?;
+        *cell = val;
 
-            (0xF, _, 0, 0xA) => Instruction::WAITFORKEY(dig2),
+        if self.log_access {
+            self.accesses.push(MemAccess { addr, write: true });
+        }
+        Ok(())
+    }
 
-            (0xF, _, 1, 5) => Instruction::SETDTV(dig2),
+    fn fetch(&mut self) -> Result<u16, EmuError> {
+        // retrieve the two byte instruction from the ram
+        let higher_byte = self.read_code(self.pc as usize)? as u16;
+        let lower_byte = self.read_code(self.pc as usize + 1)? as u16;
 
-            (0xF, _, 1, 8) => Instruction::SETSTV(dig2),
+        //bitshift + or to combine the bytes
+        let op = (higher_byte << 8) | lower_byte;
 
-            (0xF, _, 1, 0xE) => Instruction::INCSETIV(dig2),
+        //move the pc
+        self.pc = self.pc.wrapping_add(2);
 
-            (0xF, _, 2, 9) => Instruction::SETIFONT(dig2),
+        Ok(op)
+    }
 
-            (0xF, _, 3, 3) => Instruction::BCDTORAM(dig2),
+    // the tracer is lifted out while it reads the state it's logging
+    fn trace(&mut self, addr: u16, opcode: u16, op: Option<Instruction>) {
+        if let Some(mut tracer) = self.tracer.take() {
+            tracer.record(self, addr, opcode, op);
+            self.tracer = Some(tracer);
+        }
+    }
 
-            (0xF, _, 5, 5) => Instruction::VTORAM(dig2),
+    fn profile(&mut self, addr: u16, op: Instruction) {
+        if let Some(mut profiler) = self.profiler.take() {
+            profiler.record(self, addr, op);
+            self.profiler = Some(profiler);
+        }
+    }
 
-            (0xF, _, 6, 5) => Instruction::RAMTOV(dig2),
+    fn cover(&mut self, addr: u16, op: Instruction) {
+        if let Some(coverage) = &mut self.coverage {
+            coverage.record(addr, op, self.pc);
+        }
+    }
 
-            (_, _, _, _) => unimplemented!(
-                "DECODING: Error parsing unknown byte {:#02X} into byte code",
-                byte
-            ),
+    fn trace_error(&mut self, err: &EmuError) {
+        if let Some(tracer) = &mut self.tracer {
+            tracer.fail(err);
         }
     }
 
-    fn execute(&mut self, op: Instruction) -> () {
+    fn decode(&self, byte: u16) -> Result<Instruction, EmuError> {
+        Instruction::try_from(byte).map_err(|err| EmuError::UnknownOpcode {
+            opcode: err.opcode,
+            addr: self.op_addr(),
+        })
+    }
+
+    fn execute(&mut self, op: Instruction) -> Result<(), EmuError> {
         match op {
-            Instruction::NOP => return,
+            Instruction::NOP => (),
 
             Instruction::CLR => self.clear_screen(),
 
             Instruction::RET => {
-                self.pc = self.stack_pop();
+                self.pc = self.stack_pop()?;
+            }
+
+            Instruction::SCROLLDOWN(n) => self.scroll(0, n.get() as isize),
+
+            Instruction::SCROLLUP(n) => self.scroll(0, -(n.get() as isize)),
+
+            Instruction::SCROLLRIGHT => self.scroll(4, 0),
+
+            Instruction::SCROLLLEFT => self.scroll(-4, 0),
+
+            Instruction::EXIT => {
+                self.halted = true;
             }
 
+            Instruction::LORES => self.set_hires(false),
+
+            Instruction::HIRES => self.set_hires(true),
+
             Instruction::JMP(address) => {
-                self.pc = address;
+                self.pc = address.get();
             }
 
             Instruction::CALL(address) => {
-                self.stack_push(self.pc);
-                self.pc = address;
+                self.stack_push(self.pc)?;
+                self.pc = address.get();
             }
 
             Instruction::SKIPIFVNN(x, nn) => {
-                if self.v_reg[x as usize] == nn as u8 {
-                    self.pc += 2;
+                if self.v_reg[x.index()] == nn.get() {
+                    self.skip_next();
                 }
             }
 
             Instruction::SKIPIFNOTVNN(x, nn) => {
-                if self.v_reg[x as usize] != nn as u8 {
-                    self.pc += 2;
+                if self.v_reg[x.index()] != nn.get() {
+                    self.skip_next();
                 }
             }
 
             Instruction::SKIPIFVV(x, y) => {
-                if self.v_reg[x as usize] == self.v_reg[y as usize] {
-                    self.pc += 2;
+                if self.v_reg[x.index()] == self.v_reg[y.index()] {
+                    self.skip_next();
+                }
+            }
+
+            // 5XY2 - V registers X through Y into RAM[I], either direction, I unchanged
+            Instruction::SAVERANGE(x, y) => {
+                for (offset, idx) in Self::register_range(x, y) {
+                    self.write_ram(self.i_reg as usize + offset, self.v_reg[idx])?;
+                }
+            }
+
+            // 5XY3 - RAM[I] into V registers X through Y
+            Instruction::LOADRANGE(x, y) => {
+                for (offset, idx) in Self::register_range(x, y) {
+                    self.v_reg[idx] = self.read_ram(self.i_reg as usize + offset)?;
                 }
             }
 
             Instruction::SETVNN(x, nn) => {
-                self.v_reg[x as usize] = nn as u8;
+                self.v_reg[x.index()] = nn.get();
             }
 
             Instruction::INCSETVNN(x, nn) => {
-                self.v_reg[x as usize] = self.v_reg[x as usize].wrapping_add(nn as u8);
+                self.v_reg[x.index()] = self.v_reg[x.index()].wrapping_add(nn.get());
                 //wrapping avoid panic at overflow, VF not set
             }
 
             Instruction::SETVV(x, y) => {
-                self.v_reg[x as usize] = self.v_reg[y as usize];
+                self.v_reg[x.index()] = self.v_reg[y.index()];
             }
 
             Instruction::ORSETVV(x, y) => {
-                self.v_reg[x as usize] |= self.v_reg[y as usize];
+                self.v_reg[x.index()] |= self.v_reg[y.index()];
+                self.logic_vf_reset();
             }
 
             Instruction::ANDSETVV(x, y) => {
-                self.v_reg[x as usize] &= self.v_reg[y as usize];
+                self.v_reg[x.index()] &= self.v_reg[y.index()];
+                self.logic_vf_reset();
             }
 
             Instruction::XORSETVV(x, y) => {
-                self.v_reg[x as usize] ^= self.v_reg[y as usize];
+                self.v_reg[x.index()] ^= self.v_reg[y.index()];
+                self.logic_vf_reset();
             }
 
             Instruction::INCSETVV(x, y) => {
                 let (new_vx, carry) =
-                    self.v_reg[x as usize].overflowing_add(self.v_reg[y as usize]);
+                    self.v_reg[x.index()].overflowing_add(self.v_reg[y.index()]);
                 let new_vf = if carry { 1 } else { 0 }; // set VF if overflow
 
-                self.v_reg[x as usize] = new_vx;
+                self.v_reg[x.index()] = new_vx;
                 self.v_reg[0xF] = new_vf;
             }
 
             Instruction::DECSETVV(x, y) => {
                 let (new_vx, borrow) =
-                    self.v_reg[x as usize].overflowing_sub(self.v_reg[y as usize]);
+                    self.v_reg[x.index()].overflowing_sub(self.v_reg[y.index()]);
                 let new_vf = if borrow { 0 } else { 1 }; // reset VF if borrow
 
-                self.v_reg[x as usize] = new_vx;
+                self.v_reg[x.index()] = new_vx;
                 self.v_reg[0xF] = new_vf;
             }
 
-            Instruction::SHIFTRV(x) => {
-                self.v_reg[0xF] = self.v_reg[x as usize] & 1;
-                self.v_reg[x as usize] >>= 1;
+            Instruction::SHIFTRV(x, y) => {
+                let src = self.shift_source(x, y);
+
+                self.v_reg[x.index()] = src >> 1;
+                self.v_reg[0xF] = src & 1;
             }
 
             Instruction::DIFFSETVV(x, y) => {
                 let (new_vx, borrow) =
-                    self.v_reg[y as usize].overflowing_sub(self.v_reg[x as usize]);
+                    self.v_reg[y.index()].overflowing_sub(self.v_reg[x.index()]);
                 let new_vf = if borrow { 0 } else { 1 }; // reset if borrow
 
-                self.v_reg[x as usize] = new_vx;
+                self.v_reg[x.index()] = new_vx;
                 self.v_reg[0xF] = new_vf;
             }
 
-            Instruction::SHIFTLV(x) => {
-                self.v_reg[0xF] = (self.v_reg[x as usize] >> 1) & 1;
-                self.v_reg[x as usize] <<= 1;
+            Instruction::SHIFTLV(x, y) => {
+                let src = self.shift_source(x, y);
+
+                self.v_reg[x.index()] = src << 1;
+                self.v_reg[0xF] = src >> 7;
             }
 
             Instruction::SKIPIFNOTVV(x, y) => {
-                if self.v_reg[x as usize] != self.v_reg[y as usize] {
-                    self.pc += 2;
+                if self.v_reg[x.index()] != self.v_reg[y.index()] {
+                    self.skip_next();
                 }
             }
 
             Instruction::SETINNN(nnn) => {
-                self.i_reg = nnn;
+                self.i_reg = nnn.get();
+            }
+
+            // F000 NNNN - the address is the word after the opcode
+            Instruction::LONGI => {
+                let hi = self.read_code(self.pc as usize)? as u16;
+                let lo = self.read_code(self.pc as usize + 1)? as u16;
+
+                self.i_reg = (hi << 8) | lo;
+                self.pc = self.pc.wrapping_add(2);
             }
 
             Instruction::JMPV(nnn) => {
-                self.pc = (self.v_reg[0] as u16) + nnn;
+                // BXNN reads the register out of the top nibble of the address
+                let x = if self.quirks.jump_uses_vx { (nnn.get() >> 8) as usize } else { 0 };
+                self.pc = (self.v_reg[x] as u16) + nnn.get();
             }
 
             Instruction::RAND(x, nn) => {
-                let rng: u8 = random();
-                self.v_reg[x as usize] = rng & (nn as u8);
+                self.v_reg[x.index()] = self.rng.next_byte() & (nn.get());
             }
 
             // DXYN - Draw Sprite, XY are VX/VY coord of sprite, N is the height of sprite
+            // DXY0 - SUPER-CHIP 16x16 sprite, two bytes per row
             // TODO: loook at this algorithm
             Instruction::DRAW(x, y, n) => {
-                let x_coord = self.v_reg[x as usize] as u16;
-                let y_coord = self.v_reg[y as usize] as u16;
+                if self.quirks.display_wait {
+                    if !self.vblank {
+                        // hold on this opcode until the next frame starts
+                        self.pc = self.op_addr();
+                        return Ok(());
+                    }
+                    self.vblank = false;
+                }
 
-                let num_rows = n;
+                let (width, height) = self.resolution();
 
-                let mut flipped = false;
-                for y_line in 0..num_rows {
-                    let addr = self.i_reg + y_line as u16;
-                    let pixels = self.ram[addr as usize];
+                // the starting position always wraps, the rest of the sprite clips or wraps per quirk
+                let x_coord = self.v_reg[x.index()] as usize % width;
+                let y_coord = self.v_reg[y.index()] as usize % height;
 
-                    for x_line in 0..8 {
-                        if (pixels & (0b1000_0000 >> x_line)) != 0 {
-                            let x = (x_coord + x_line) as usize % SCREEN_WIDTH;
-                            let y = (y_coord + y_line) as usize % SCREEN_HEIGHT;
+                let (num_rows, row_bytes) = match n.get() {
+                    0 => (16, 2),
+                    n => (n as usize, 1),
+                };
 
-                            let idx = x + SCREEN_WIDTH * y;
+                // with both XO-CHIP planes selected the second plane's sprite follows the first one's
+                let mut addr = self.i_reg as usize;
+                let mut flipped = false;
+                for plane in (0..NUM_PLANES).map(|p| 1 << p) {
+                    if self.planes & plane == 0 {
+                        continue;
+                    }
 
-                            flipped |= self.screen[idx];
-                            self.screen[idx] ^= true;
+                    for y_line in 0..num_rows {
+                        let mut pixels = self.read_ram(addr)? as u16;
+                        if row_bytes == 2 {
+                            pixels = (pixels << 8) | self.read_ram(addr + 1)? as u16;
+                        }
+                        addr += row_bytes;
+
+                        let row_width = 8 * row_bytes;
+                        for x_line in 0..row_width {
+                            if (pixels & (1 << (row_width - 1 - x_line))) != 0 {
+                                let mut x = x_coord + x_line;
+                                let mut y = y_coord + y_line;
+
+                                if x >= width || y >= height {
+                                    if self.quirks.clip_sprites {
+                                        continue;
+                                    }
+                                    x %= width;
+                                    y %= height;
+                                }
+
+                                let idx = x + width * y;
+
+                                flipped |= self.screen[idx] & plane != 0;
+                                self.screen[idx] ^= plane;
+                            }
                         }
                     }
                 }
@@ -396,21 +995,36 @@ impl Emu {
             }
 
             Instruction::SKIPIFKEY(x) => {
-                let key = self.keys[self.v_reg[x as usize] as usize];
+                // only the low nibble of VX names a key
+                let key = self.keys[(self.v_reg[x.index()] & 0xF) as usize];
                 if key {
-                    self.pc += 2;
+                    self.skip_next();
                 }
             }
 
             Instruction::SKIPIFNOTKEY(x) => {
-                let key = self.keys[self.v_reg[x as usize] as usize];
+                let key = self.keys[(self.v_reg[x.index()] & 0xF) as usize];
                 if !key {
-                    self.pc += 2;
+                    self.skip_next();
                 }
             }
 
+            // FN01 - N is a bitmask of the planes to draw on
+            Instruction::SETPLANES(n) => {
+                self.planes = n.get() & 0b11;
+            }
+
+            // F002 - 16 bytes at RAM[I] become the audio pattern
+            Instruction::LOADAUDIO => {
+                for idx in 0..PATTERN_SIZE {
+                    self.pattern[idx] = self.read_ram(self.i_reg as usize + idx)?;
+                }
+                self.pattern_set = true;
+                self.send_pattern();
+            }
+
             Instruction::SETVDT(x) => {
-                self.v_reg[x as usize] = self.dt;
+                self.v_reg[x.index()] = self.dt;
             }
 
             Instruction::WAITFORKEY(x) => {
@@ -419,7 +1033,7 @@ impl Emu {
                 let mut pressed = false;
                 for i in 0..self.keys.len() {
                     if self.keys[i] {
-                        self.v_reg[x as usize] = i as u8;
+                        self.v_reg[x.index()] = i as u8;
                         pressed = true;
                         break;
                     }
@@ -427,50 +1041,114 @@ impl Emu {
 
                 if !pressed {
                     // redo opcode until key presesd
-                    self.pc -= 2;
+                    self.pc = self.op_addr();
                 }
             }
 
             Instruction::SETDTV(x) => {
-                self.dt = self.v_reg[x as usize];
+                self.dt = self.v_reg[x.index()];
             }
 
             Instruction::SETSTV(x) => {
-                self.st = self.v_reg[x as usize];
+                self.set_sound_timer(self.v_reg[x.index()]);
             }
 
             Instruction::INCSETIV(x) => {
-                self.i_reg = self.i_reg.wrapping_add(self.v_reg[x as usize] as u16);
+                self.i_reg = self.i_reg.wrapping_add(self.v_reg[x.index()] as u16);
             }
 
             Instruction::SETIFONT(x) => {
-                let c = self.v_reg[x as usize] as u16;
+                let c = self.v_reg[x.index()] as u16;
                 self.i_reg = c * 5; // 5c is the beginning of the character
                                     // TODO: Look at this when refactoring the Fontset =
             }
 
+            Instruction::SETIBIGFONT(x) => {
+                let c = (self.v_reg[x.index()] & 0xF) as u16;
+                self.i_reg = BIGFONT_ADDR as u16 + c * 10;
+            }
+
+            Instruction::SETPITCH(x) => {
+                self.pitch = self.v_reg[x.index()];
+                self.pattern_set = true;
+                self.send_pattern();
+            }
+
             Instruction::BCDTORAM(x) => {
-                let bcd = Self::double_dabble(&self.v_reg[x as usize]);
+                let bcd = Self::double_dabble(&self.v_reg[x.index()]);
 
                 // since VX is a u8 it ranges from 0 to 255, it will always be three digits
                 for (i, bin) in bcd.iter().enumerate() {
-                    self.ram[self.i_reg as usize + i] = *bin;
+                    self.write_ram(self.i_reg as usize + i, *bin)?;
                 }
             }
 
             // FX55 - Store V0 - VX into I
             Instruction::VTORAM(x) => {
-                for idx in 0..=x {
-                    self.ram[self.i_reg as usize + idx as usize] = self.v_reg[idx as usize];
+                for idx in 0..=x.index() {
+                    self.write_ram(self.i_reg as usize + idx, self.v_reg[idx])?;
                 }
+                self.load_store_increment(x);
             }
 
             // FX65 - Load I into V0 - VX
             Instruction::RAMTOV(x) => {
-                for idx in 0..=x {
-                    self.v_reg[idx as usize] = self.ram[self.i_reg as usize + idx as usize];
+                for idx in 0..=x.index() {
+                    self.v_reg[idx] = self.read_ram(self.i_reg as usize + idx)?;
                 }
+                self.load_store_increment(x);
+            }
+
+            // FX75 - Store V0 - VX into the RPL flags
+            Instruction::STORERPL(x) => {
+                let len = x.index() + 1;
+                self.rpl[0..len].copy_from_slice(&self.v_reg[0..len]);
             }
+
+            // FX85 - Load the RPL flags into V0 - VX
+            Instruction::LOADRPL(x) => {
+                let len = x.index() + 1;
+                self.v_reg[0..len].copy_from_slice(&self.rpl[0..len]);
+            }
+        }
+
+        Ok(())
+    }
+
+    // (RAM offset, register index) pairs from X to Y inclusive, counting down when Y < X
+    fn register_range(x: Reg, y: Reg) -> impl Iterator<Item = (usize, usize)> {
+        let (x, y) = (x.index(), y.index());
+
+        (0..=x.abs_diff(y)).map(move |offset| {
+            let idx = if x <= y { x + offset } else { x - offset };
+            (offset, idx)
+        })
+    }
+
+    // 8XY6/8XYE operand, VY on the COSMAC VIP and VX everywhere after it
+    fn shift_source(&self, x: Reg, y: Reg) -> u8 {
+        if self.quirks.shift_uses_vy {
+            self.v_reg[y.index()]
+        } else {
+            self.v_reg[x.index()]
+        }
+    }
+
+    // 8XY1/8XY2/8XY3 clobber VF on the COSMAC VIP
+    fn logic_vf_reset(&mut self) {
+        if self.quirks.vf_reset {
+            self.v_reg[0xF] = 0;
+        }
+    }
+
+    // I after FX55/FX65
+    fn load_store_increment(&mut self, x: Reg) {
+        let x = x.index() as u16;
+
+        match self.quirks.increment_i {
+            IncrementI::Unchanged => (),
+            IncrementI::ByX => self.i_reg = self.i_reg.wrapping_add(x),
+            IncrementI::ByXPlusOne => self.i_reg = self.i_reg.wrapping_add(x + 1),
         }
     }
 
@@ -479,7 +1157,7 @@ impl Emu {
         // 1st iteration: 7ns per cycle compared to the book's 25ns
         // 2nd iteration: 6ns per cycle compared to the book's 25ns
 
-        let mut num: u32 = (0 | num) as u32; // convert to 32-bit num for padding putting the original number at the end, need 4bits for every digit (20 in this case)
+        let mut num: u32 = *num as u32; // convert to 32-bit num for padding putting the original number at the end, need 4bits for every digit (20 in this case)
         // a 16 bit number would need (16 + 5*4) = 36 total bits to be converted
 
 
@@ -503,11 +1181,11 @@ impl Emu {
 
         let mut res = [0; 3];
 
-        for i in 0..3 {
+        for (i, digit) in res.iter_mut().enumerate() {
 
             let offset = 4*(4-i); // distance to right shift to extract the correct 4 bits
 
-            res[i] = ((num & (mask << offset)) >> offset) as u8;
+            *digit = ((num & (mask << offset)) >> offset) as u8;
         }
 
         res
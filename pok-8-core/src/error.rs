@@ -0,0 +1,51 @@
+use std::fmt;
+
+// Everything that can go wrong while loading or running a ROM
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EmuError {
+    UnknownOpcode { opcode: u16, addr: u16 }, // byte code that doesn't decode, and where it was fetched from
+    StackOverflow { addr: u16 },              // CALL with a full stack
+    StackUnderflow { addr: u16 },             // RET with an empty stack
+    MemoryOutOfRange { addr: usize },         // read or write past the end of RAM
+    RomTooLarge { size: usize, max: usize },  // ROM doesn't fit between START_ADDR and the end of RAM
+    InvalidSaveState { reason: String },      // save state is corrupt, truncated or from another version
+    InvalidMovie { reason: String },          // movie is corrupt, or was recorded against another ROM
+    InvalidCoverage { reason: String },       // coverage file is corrupt, or was saved against another ROM
+}
+
+impl fmt::Display for EmuError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EmuError::UnknownOpcode { opcode, addr } => {
+                write!(f, "unknown opcode {:#06X} at {:#05X}", opcode, addr)
+            }
+            EmuError::StackOverflow { addr } => write!(f, "stack overflow at {:#05X}", addr),
+            EmuError::StackUnderflow { addr } => write!(f, "stack underflow at {:#05X}", addr),
+            EmuError::MemoryOutOfRange { addr } => {
+                write!(f, "memory access out of range at {:#06X}", addr)
+            }
+            EmuError::RomTooLarge { size, max } => {
+                write!(f, "ROM is {} bytes but at most {} fit in memory", size, max)
+            }
+            EmuError::InvalidSaveState { reason } => write!(f, "invalid save state: {}", reason),
+            EmuError::InvalidMovie { reason } => write!(f, "invalid movie: {}", reason),
+            EmuError::InvalidCoverage { reason } => write!(f, "invalid coverage: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for EmuError {}
+
+// A word that isn't any CHIP-8, SUPER-CHIP or XO-CHIP instruction
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DecodeError {
+    pub opcode: u16,
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:#06X} isn't an instruction", self.opcode)
+    }
+}
+
+impl std::error::Error for DecodeError {}
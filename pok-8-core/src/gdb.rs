@@ -0,0 +1,499 @@
+use crate::debugger::*;
+use crate::emu::*;
+use crate::instruction::Reg;
+
+use std::io::{self, ErrorKind, Read, Write};
+use std::net::TcpStream;
+
+// Registers in the order g/G packets carry them, name and size in bytes. Values are big endian like CHIP-8 itself
+const REGISTERS: [(&str, usize); 21] = [
+    ("v0", 1),
+    ("v1", 1),
+    ("v2", 1),
+    ("v3", 1),
+    ("v4", 1),
+    ("v5", 1),
+    ("v6", 1),
+    ("v7", 1),
+    ("v8", 1),
+    ("v9", 1),
+    ("va", 1),
+    ("vb", 1),
+    ("vc", 1),
+    ("vd", 1),
+    ("ve", 1),
+    ("vf", 1),
+    ("i", 2),
+    ("pc", 2),
+    ("sp", 1),
+    ("dt", 1),
+    ("st", 1),
+];
+
+const REG_I: usize = 16;
+const REG_PC: usize = 17;
+const REG_SP: usize = 18;
+const REG_DT: usize = 19;
+const REG_ST: usize = 20;
+
+const PACKET_SIZE: usize = 0x1000; // advertised in qSupported, m replies are cut to fit
+const RUN_CHUNK: u64 = 10_000; // instructions continue runs between looks for an interrupt from the client
+
+// How a session ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Session {
+    Detached, // D, or the client hung up
+    Killed,   // k
+}
+
+// What a packet asks the stub to do once it's been handled
+enum Action {
+    Reply(String),
+    Output(String), // qRcmd text, sent as O packets and then OK
+    Resume { step: bool },
+    Detach,
+    Kill,
+}
+
+// A GDB remote serial protocol server around a Debugger. The client sees V0-VF, I, PC, SP, DT and ST as registers
+// and the whole of RAM as memory, and can set breakpoints and watchpoints, step, continue and write memory.
+// `monitor screen` and `monitor key <0-F> [down|up]` reach the things gdb has no packets for
+pub struct GdbStub {
+    dbg: Debugger,
+}
+
+impl GdbStub {
+    pub fn new(emu: Emu) -> Self {
+        Self {
+            dbg: Debugger::new(emu),
+        }
+    }
+
+    pub fn debugger(&self) -> &Debugger {
+        &self.dbg
+    }
+
+    pub fn into_emu(self) -> Emu {
+        self.dbg.into_emu()
+    }
+
+    // Talks to one client until it detaches or kills the target, breakpoints and watchpoints carry over to the next
+    pub fn serve(&mut self, stream: TcpStream) -> io::Result<Session> {
+        let mut conn = Connection::new(stream);
+
+        loop {
+            let packet = match conn.receive()? {
+                Some(Incoming::Packet(packet)) => packet,
+                Some(Incoming::Interrupt) => {
+                    conn.send("S02")?;
+                    continue;
+                }
+                None => return Ok(Session::Detached),
+            };
+
+            match self.handle(&packet) {
+                Action::Reply(reply) => conn.send(&reply)?,
+
+                Action::Output(text) => {
+                    for line in text.lines() {
+                        conn.send(&format!("O{}", hex(format!("{}\n", line).as_bytes())))?;
+                    }
+                    conn.send("OK")?;
+                }
+
+                Action::Resume { step } => {
+                    let ran = if step { Ok(self.dbg.step()) } else { self.run(&mut conn) };
+                    let reason = match ran {
+                        Ok(reason) => reason,
+                        // hung up while the ROM was running
+                        Err(err) if err.kind() == ErrorKind::UnexpectedEof => return Ok(Session::Detached),
+                        Err(err) => return Err(err),
+                    };
+                    conn.send(&stop_reply(&reason))?;
+                }
+
+                Action::Detach => {
+                    conn.send("OK")?;
+                    return Ok(Session::Detached);
+                }
+
+                Action::Kill => return Ok(Session::Killed),
+            }
+        }
+    }
+
+    // Continues in chunks so a ^C from the client gets noticed, which comes back as Limit
+    fn run(&mut self, conn: &mut Connection) -> io::Result<StopReason> {
+        loop {
+            match self.dbg.run_until_break(RUN_CHUNK) {
+                StopReason::Limit if !conn.interrupted()? => (),
+                reason => return Ok(reason),
+            }
+        }
+    }
+
+    fn handle(&mut self, packet: &str) -> Action {
+        let (kind, args) = packet.split_at(packet.chars().next().map_or(0, char::len_utf8));
+
+        let reply = match kind {
+            "?" => "S05".to_string(),
+            "g" => hex(&self.registers()),
+            "G" => ok_or_error(unhex(args).and_then(|bytes| self.set_registers(&bytes))),
+            "p" => parse_hex(args).and_then(|reg| self.register(reg as usize)).map_or(error(), |bytes| hex(&bytes)),
+            "P" => ok_or_error(args.split_once('=').and_then(|(reg, value)| {
+                self.set_register(parse_hex(reg)? as usize, &unhex(value)?)
+            })),
+
+            "m" => parse_pair(args).and_then(|(addr, len)| self.read_memory(addr, len)).map_or(error(), |bytes| hex(&bytes)),
+            "M" => ok_or_error(args.split_once(':').and_then(|(range, data)| {
+                let (addr, len) = parse_pair(range)?;
+                let bytes = unhex(data).filter(|bytes| bytes.len() == len)?;
+                self.dbg.emu_mut().set_ram(addr, &bytes).ok()
+            })),
+
+            "c" | "s" => {
+                if let Some(addr) = parse_hex(args) {
+                    self.dbg.emu_mut().set_pc(addr as u16);
+                }
+                return Action::Resume { step: kind == "s" };
+            }
+
+            "Z" => ok_or_error(parse_point(args).and_then(|point| self.insert_point(point))),
+            "z" => ok_or_error(parse_point(args).and_then(|point| self.remove_point(point))),
+
+            "H" => "OK".to_string(),
+            "T" => "OK".to_string(), // the one thread is always alive
+            "D" => return Action::Detach,
+            "k" => return Action::Kill,
+
+            "q" => {
+                if let Some(command) = args.strip_prefix("Rcmd,") {
+                    return match unhex(command).and_then(|bytes| String::from_utf8(bytes).ok()) {
+                        Some(command) => self.monitor(&command),
+                        None => Action::Reply(error()),
+                    };
+                }
+                self.query(args)
+            }
+
+            // anything else is unsupported, which an empty reply says
+            _ => String::new(),
+        };
+
+        Action::Reply(reply)
+    }
+
+    fn query(&self, args: &str) -> String {
+        if args.starts_with("Supported") {
+            return format!("PacketSize={:x};qXfer:features:read+", PACKET_SIZE);
+        }
+        if let Some(range) = args.strip_prefix("Xfer:features:read:target.xml:") {
+            return parse_pair(range).map_or(error(), |(offset, len)| {
+                let xml = target_xml();
+                let start = offset.min(xml.len());
+                let end = start.saturating_add(len).min(xml.len());
+                let more = if end < xml.len() { 'm' } else { 'l' };
+                format!("{}{}", more, &xml[start..end])
+            });
+        }
+
+        match args {
+            "Attached" => "1".to_string(),
+            "C" => "QC1".to_string(),
+            "fThreadInfo" => "m1".to_string(),
+            "sThreadInfo" => "l".to_string(),
+            _ => String::new(),
+        }
+    }
+
+    fn monitor(&mut self, command: &str) -> Action {
+        let words: Vec<&str> = command.split_whitespace().collect();
+
+        match words.as_slice() {
+            ["screen"] => Action::Output(self.dbg.emu().get_display().to_text()),
+            ["key", key, rest @ ..] => {
+                let Some(key) = u8::from_str_radix(key, 16).ok().filter(|key| *key < 16) else {
+                    return Action::Output(format!("'{}' isn't a key, 0-F", key));
+                };
+
+                match rest {
+                    [] | ["down"] => self.dbg.emu_mut().key_down(key as usize),
+                    ["up"] => self.dbg.emu_mut().key_up(key as usize),
+                    _ => return Action::Output("expected key <0-F> [down|up]".to_string()),
+                }
+                Action::Reply("OK".to_string())
+            }
+            _ => Action::Output("monitor commands: screen, key <0-F> [down|up]".to_string()),
+        }
+    }
+
+    fn registers(&self) -> Vec<u8> {
+        (0..REGISTERS.len()).flat_map(|reg| self.register(reg).unwrap_or_default()).collect()
+    }
+
+    fn register(&self, reg: usize) -> Option<Vec<u8>> {
+        let emu = self.dbg.emu();
+
+        let bytes = match reg {
+            0..=15 => vec![emu.v_reg()[reg]],
+            REG_I => emu.i_reg().to_be_bytes().to_vec(),
+            REG_PC => emu.pc().to_be_bytes().to_vec(),
+            REG_SP => vec![emu.sp() as u8],
+            REG_DT => vec![emu.delay_timer()],
+            REG_ST => vec![emu.sound_timer()],
+            _ => return None,
+        };
+        Some(bytes)
+    }
+
+    // SP can't be moved from here, the stack it points into isn't in RAM, so writes must leave it as it is
+    fn set_register(&mut self, reg: usize, bytes: &[u8]) -> Option<()> {
+        let (_, size) = REGISTERS.get(reg)?;
+        if bytes.len() != *size {
+            return None;
+        }
+
+        let emu = self.dbg.emu_mut();
+        let word = if *size == 2 { u16::from_be_bytes([bytes[0], bytes[1]]) } else { bytes[0] as u16 };
+
+        match reg {
+            0..=15 => emu.set_v_reg(Reg::new(reg as u8)?, bytes[0]),
+            REG_I => emu.set_i_reg(word),
+            REG_PC => emu.set_pc(word),
+            REG_SP if word == emu.sp() => (),
+            REG_DT => emu.set_delay_timer(bytes[0]),
+            REG_ST => emu.set_sound_timer(bytes[0]),
+            _ => return None,
+        }
+        Some(())
+    }
+
+    fn set_registers(&mut self, bytes: &[u8]) -> Option<()> {
+        let total: usize = REGISTERS.iter().map(|(_, size)| size).sum();
+        if bytes.len() != total {
+            return None;
+        }
+
+        let mut pos = 0;
+        for (reg, (_, size)) in REGISTERS.iter().enumerate() {
+            self.set_register(reg, &bytes[pos..(pos + size)])?;
+            pos += size;
+        }
+        Some(())
+    }
+
+    // Whatever of the range is in RAM, nothing at all is an error
+    fn read_memory(&self, addr: usize, len: usize) -> Option<Vec<u8>> {
+        let ram = self.dbg.emu().ram();
+        let end = addr.saturating_add(len.min(PACKET_SIZE / 2 - 8)).min(ram.len());
+
+        (addr < end || len == 0).then(|| ram[addr.min(end)..end].to_vec())
+    }
+
+    fn insert_point(&mut self, point: Point) -> Option<()> {
+        match point {
+            Point::Break(addr) => self.dbg.add_breakpoint(Breakpoint { addr, condition: None }),
+            Point::Watch(watch) => self.dbg.add_watchpoint(watch),
+        }
+        Some(())
+    }
+
+    fn remove_point(&mut self, point: Point) -> Option<()> {
+        match point {
+            Point::Break(addr) => {
+                let idx = self.dbg.breakpoints().iter().position(|bp| bp.addr == addr && bp.condition.is_none())?;
+                self.dbg.remove_breakpoint(idx).map(|_| ())
+            }
+            Point::Watch(watch) => {
+                let idx = self.dbg.watchpoints().iter().position(|w| *w == watch)?;
+                self.dbg.remove_watchpoint(idx).map(|_| ())
+            }
+        }
+    }
+}
+
+// Z and z packets, software and hardware breakpoints are the same thing here
+enum Point {
+    Break(u16),
+    Watch(Watchpoint),
+}
+
+// type,addr,kind where kind is the length for watchpoints
+fn parse_point(args: &str) -> Option<Point> {
+    let mut fields = args.split(',');
+    let kind = fields.next()?;
+    let addr = u16::try_from(parse_hex(fields.next()?)?).ok()?;
+    let len = parse_hex(fields.next()?.split(';').next()?)?;
+
+    let access = match kind {
+        "0" | "1" => return Some(Point::Break(addr)),
+        "2" => Access::Write,
+        "3" => Access::Read,
+        "4" => Access::ReadWrite,
+        _ => return None,
+    };
+
+    Some(Point::Watch(Watchpoint::Ram {
+        start: addr,
+        end: addr.saturating_add((len.max(1) - 1).min(u16::MAX as u32) as u16),
+        access,
+    }))
+}
+
+fn stop_reply(reason: &StopReason) -> String {
+    match reason {
+        StopReason::Step | StopReason::Breakpoint { .. } | StopReason::Halted => "S05".to_string(),
+        StopReason::RamWatch { addr, write: true } => format!("T05watch:{:x};", addr),
+        StopReason::RamWatch { addr, write: false } => format!("T05rwatch:{:x};", addr),
+        StopReason::IWatch { .. } => "S05".to_string(),
+        StopReason::Error(_) => "S04".to_string(), // SIGILL
+        StopReason::Limit => "S02".to_string(),    // SIGINT, the client interrupted
+    }
+}
+
+fn target_xml() -> String {
+    let mut xml = String::from("<?xml version=\"1.0\"?><!DOCTYPE target SYSTEM \"gdb-target.dtd\"><target version=\"1.0\">");
+    xml.push_str("<feature name=\"org.pok8.chip8\">");
+
+    for (regnum, (name, size)) in REGISTERS.iter().enumerate() {
+        let kind = if regnum == REG_PC { " type=\"code_ptr\"" } else { "" };
+        xml.push_str(&format!("<reg name=\"{}\" bitsize=\"{}\" regnum=\"{}\"{}/>", name, size * 8, regnum, kind));
+    }
+
+    xml.push_str("</feature></target>");
+    xml
+}
+
+fn ok_or_error(result: Option<()>) -> String {
+    result.map_or(error(), |_| "OK".to_string())
+}
+
+fn error() -> String {
+    "E01".to_string()
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn unhex(text: &str) -> Option<Vec<u8>> {
+    if !text.len().is_multiple_of(2) {
+        return None;
+    }
+
+    (0..text.len())
+        .step_by(2)
+        .map(|pos| u8::from_str_radix(text.get(pos..(pos + 2))?, 16).ok())
+        .collect()
+}
+
+fn parse_hex(text: &str) -> Option<u32> {
+    u32::from_str_radix(text, 16).ok()
+}
+
+// addr,len
+fn parse_pair(text: &str) -> Option<(usize, usize)> {
+    let (addr, len) = text.split_once(',')?;
+    Some((parse_hex(addr)? as usize, parse_hex(len)? as usize))
+}
+
+enum Incoming {
+    Packet(String),
+    Interrupt, // a bare 0x03, ^C on the client's end
+}
+
+// $packet#checksum framing and acks over TCP
+struct Connection {
+    stream: TcpStream,
+    buf: Vec<u8>,
+}
+
+impl Connection {
+    fn new(stream: TcpStream) -> Self {
+        Self { stream, buf: Vec::new() }
+    }
+
+    // Blocks for the next packet or interrupt, None once the client hangs up
+    fn receive(&mut self) -> io::Result<Option<Incoming>> {
+        loop {
+            if let Some(incoming) = self.parse()? {
+                return Ok(Some(incoming));
+            }
+
+            let mut chunk = [0; 1024];
+            let len = self.stream.read(&mut chunk)?;
+            if len == 0 {
+                return Ok(None);
+            }
+            self.buf.extend_from_slice(&chunk[0..len]);
+        }
+    }
+
+    fn parse(&mut self) -> io::Result<Option<Incoming>> {
+        // acks and noise before the next packet are dropped
+        while let Some(&byte) = self.buf.first() {
+            match byte {
+                b'$' => break,
+                0x03 => {
+                    self.buf.remove(0);
+                    return Ok(Some(Incoming::Interrupt));
+                }
+                _ => {
+                    self.buf.remove(0);
+                }
+            }
+        }
+
+        let Some(hash) = self.buf.iter().position(|b| *b == b'#') else {
+            return Ok(None);
+        };
+        if self.buf.len() < hash + 3 {
+            return Ok(None);
+        }
+
+        let body = self.buf[1..hash].to_vec();
+        let sum = std::str::from_utf8(&self.buf[(hash + 1)..(hash + 3)]).ok().and_then(|sum| u8::from_str_radix(sum, 16).ok());
+        self.buf.drain(0..(hash + 3));
+
+        // a bad checksum asks for the packet again
+        if sum != Some(checksum(&body)) {
+            self.stream.write_all(b"-")?;
+            return Ok(None);
+        }
+
+        self.stream.write_all(b"+")?;
+        Ok(Some(Incoming::Packet(String::from_utf8_lossy(&body).into_owned())))
+    }
+
+    // Checks for a ^C without waiting, an interrupt already read in by receive() counts too
+    fn interrupted(&mut self) -> io::Result<bool> {
+        self.stream.set_nonblocking(true)?;
+        let mut chunk = [0; 1024];
+        let read = self.stream.read(&mut chunk);
+        self.stream.set_nonblocking(false)?;
+
+        match read {
+            Ok(0) => return Err(ErrorKind::UnexpectedEof.into()),
+            Ok(len) => self.buf.extend_from_slice(&chunk[0..len]),
+            Err(err) if err.kind() == ErrorKind::WouldBlock => (),
+            Err(err) => return Err(err),
+        }
+
+        match self.buf.iter().position(|b| *b == 0x03) {
+            Some(pos) => {
+                self.buf.remove(pos);
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    fn send(&mut self, packet: &str) -> io::Result<()> {
+        write!(self.stream, "${}#{:02x}", packet, checksum(packet.as_bytes()))?;
+        self.stream.flush()
+    }
+}
+
+fn checksum(bytes: &[u8]) -> u8 {
+    bytes.iter().fold(0, |sum, b| sum.wrapping_add(*b))
+}
@@ -0,0 +1,151 @@
+use crate::emu::*;
+
+use std::iter;
+
+// Colours for the four pixel values: background, plane 1, plane 2, both planes
+pub type Palette = [[u8; 3]; 4];
+
+// Same as the desktop frontend's
+pub const DEFAULT_PALETTE: Palette = [[0, 0, 0], [255, 255, 255], [170, 170, 170], [85, 85, 85]];
+
+// An indexed colour PNG of the framebuffer, every pixel blown up to scale x scale
+pub fn to_png(display: &Display, palette: &Palette, scale: usize) -> Vec<u8> {
+    let scale = scale.max(1);
+    let (width, height) = (display.width * scale, display.height * scale);
+    let mut out = Vec::new();
+
+    let mut encoder = png::Encoder::new(&mut out, width as u32, height as u32);
+    encoder.set_color(png::ColorType::Indexed);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.set_palette(palette.concat());
+
+    let mut data = Vec::with_capacity(width * height);
+    for row in display.pixels.chunks(display.width) {
+        let line: Vec<u8> = row.iter().flat_map(|pixel| iter::repeat_n(*pixel, scale)).collect();
+        for _ in 0..scale {
+            data.extend_from_slice(&line);
+        }
+    }
+
+    // into memory, so nothing here can fail short of a bug
+    let mut writer = encoder.write_header().unwrap();
+    writer.write_image_data(&data).unwrap();
+    writer.finish().unwrap();
+
+    out
+}
+
+// Plain (P1) PBM, 1 for any lit pixel whichever plane it's on
+pub fn to_pbm(display: &Display) -> String {
+    let mut out = format!("P1\n{} {}\n", display.width, display.height);
+
+    for row in display.pixels.chunks(display.width) {
+        let bits: Vec<&str> = row.iter().map(|pixel| if *pixel == 0 { "0" } else { "1" }).collect();
+        out.push_str(&bits.join(" "));
+        out.push('\n');
+    }
+
+    out
+}
+
+// A framebuffer that owns its pixels, what from_png and from_text read back in
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Image {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<u8>, // colour indices 0-3, row major like Display's
+}
+
+impl Image {
+    pub fn display(&self) -> Display<'_> {
+        Display {
+            width: self.width,
+            height: self.height,
+            pixels: &self.pixels,
+        }
+    }
+
+    // Every pixel colour goes to the nearest palette entry, so a PNG saved in other colours still reads back,
+    // just not one that's been smoothed while it was scaled
+    pub fn from_png(data: &[u8], palette: &Palette) -> Result<Self, String> {
+        let mut decoder = png::Decoder::new(data);
+        decoder.set_transformations(png::Transformations::normalize_to_color8());
+
+        let mut reader = decoder.read_info().map_err(|err| err.to_string())?;
+        let mut buf = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut buf).map_err(|err| err.to_string())?;
+
+        let channels = info.color_type.samples();
+        let mut pixels = Vec::with_capacity(info.width as usize * info.height as usize);
+        for row in buf.chunks(info.line_size).take(info.height as usize) {
+            for pixel in row.chunks(channels).take(info.width as usize) {
+                let rgb = match channels {
+                    1 | 2 => [pixel[0]; 3],
+                    _ => [pixel[0], pixel[1], pixel[2]],
+                };
+                pixels.push(nearest(palette, rgb));
+            }
+        }
+
+        Ok(Self {
+            width: info.width as usize,
+            height: info.height as usize,
+            pixels,
+        })
+    }
+
+    // Display::to_text's format, . for off, # plane 1, + plane 2 and @ for both. Every row has to be as wide
+    pub fn from_text(text: &str) -> Result<Self, String> {
+        let rows: Vec<&str> = text.lines().map(|row| row.trim()).filter(|row| !row.is_empty()).collect();
+        let width = rows.first().map_or(0, |row| row.chars().count());
+        let mut pixels = Vec::with_capacity(width * rows.len());
+
+        for (idx, row) in rows.iter().enumerate() {
+            if row.chars().count() != width {
+                return Err(format!("row {} is {} wide, the first is {}", idx + 1, row.chars().count(), width));
+            }
+            for c in row.chars() {
+                pixels.push(match c {
+                    '.' => 0,
+                    '#' => 1,
+                    '+' => 2,
+                    '@' => 3,
+                    other => return Err(format!("row {} has '{}', expected one of . # + @", idx + 1, other)),
+                });
+            }
+        }
+
+        Ok(Self {
+            width,
+            height: rows.len(),
+            pixels,
+        })
+    }
+
+    // Undoes to_png's scale, None unless the image is exactly that size blown up by a whole number
+    pub fn downscale(&self, width: usize, height: usize) -> Option<Self> {
+        if width == 0 || !self.width.is_multiple_of(width) {
+            return None;
+        }
+
+        let scale = self.width / width;
+        if self.height != height * scale {
+            return None;
+        }
+
+        let pixels = (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x * scale, y * scale)))
+            .map(|(x, y)| self.pixels[x + self.width * y])
+            .collect();
+
+        Some(Self { width, height, pixels })
+    }
+}
+
+fn nearest(palette: &Palette, rgb: [u8; 3]) -> u8 {
+    let distance = |colour: &[u8; 3]| -> i32 {
+        colour.iter().zip(rgb).map(|(a, b)| (*a as i32 - b as i32).pow(2)).sum()
+    };
+
+    (0..palette.len()).min_by_key(|idx| distance(&palette[*idx])).unwrap_or(0) as u8
+}
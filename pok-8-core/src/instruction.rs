@@ -1,40 +1,463 @@
 
+use crate::error::DecodeError;
+
+use std::fmt;
+
+// Operands only hold what fits in their part of the opcode, so every Instruction encodes
+
+// V0 - VF
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Reg(u8);
+
+impl Reg {
+    pub const VF: Reg = Reg(0xF);
+
+    pub fn new(idx: u8) -> Option<Self> {
+        (idx < 16).then_some(Reg(idx))
+    }
+
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+// NNN, 12 bits
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Addr(u16);
+
+impl Addr {
+    pub fn new(addr: u16) -> Option<Self> {
+        (addr <= 0xFFF).then_some(Addr(addr))
+    }
+
+    pub fn get(self) -> u16 {
+        self.0
+    }
+}
+
+// NN, any u8 fits
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Byte(u8);
+
+impl Byte {
+    pub fn new(byte: u8) -> Self {
+        Byte(byte)
+    }
+
+    pub fn get(self) -> u8 {
+        self.0
+    }
+}
+
+// N, 4 bits
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Nibble(u8);
+
+impl Nibble {
+    pub fn new(n: u8) -> Option<Self> {
+        (n <= 0xF).then_some(Nibble(n))
+    }
+
+    pub fn get(self) -> u8 {
+        self.0
+    }
+}
+
+// Operands print the way the assembler reads them
+impl fmt::Display for Reg {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "V{:X}", self.0)
+    }
+}
+
+impl fmt::Display for Addr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:#05X}", self.0)
+    }
+}
+
+impl fmt::Display for Byte {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:#04X}", self.0)
+    }
+}
+
+impl fmt::Display for Nibble {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Instruction {
 
     NOP, // No Op
     CLR, // Clear Screen
     RET, // Return
-    JMP(u16), // 1NNN - Jump to Address NNN
-    CALL(u16), // 2NNN - Call sub at NNN
-    SKIPIFVNN(u16, u16), //3XNN - Skip if VX == 0xNN
-    SKIPIFNOTVNN(u16, u16), //4XNN - Skip if VX == 0xNN
-    SKIPIFVV(u16, u16), // 5XY0 - Skip if VX == VY
-    SETVNN(u16, u16), // 6XNN - VX = 0xNN
-    INCSETVNN(u16, u16), // 7XNN - VX += 0xNN (Doesn't affect carry flag)
-    SETVV(u16, u16), // 8XY0 - VX = VY
-    ORSETVV(u16, u16), // 8XY1 - VX |= VY
-    ANDSETVV(u16, u16), // 8XY2 - VX &= VY
-    XORSETVV(u16, u16), // 8XY3 - VX ^= VY
-    INCSETVV(u16, u16), // 8XY4 - VX += VY, Sets VF if carry
-    DECSETVV(u16, u16), // 8XY5 - VX -= VY, Clear VF if carry
-    SHIFTRV(u16), // 8XY6 - VX >>= 1, Dropped bit in VF
-    DIFFSETVV(u16, u16), // 8XY7 VX = VY - VX, Clear VF if borrow
-    SHIFTLV(u16), // 8XYE VX <<= 1, Store dropped bit in VF
-    SKIPIFNOTVV(u16, u16), // 9XY0 - Skip if VX != VY
-    SETINNN(u16), // ANNN - I = NNN
-    JMPV(u16), // BNNN - Jump to V0 + 0xNNN
-    RAND(u16, u16), // CXNN - VX = rand() & 0xNN
-    DRAW(u16, u16, u16), // DXYN - Draw sprite at (VX, VY) N pixels tall, on/off based on I. VF set if any pixels flipped
-    SKIPIFKEY(u16), // EX9E - Skip if key index in VX is pressed =
-    SKIPIFNOTKEY(u16), // EXA1 - Skip if key at VX is not pressed
-    SETVDT(u16), // FX07 - VX = Delay Timer
-    WAITFORKEY(u16), // FX0A - Wait for key, index in VX, BLOCKING
-    SETDTV(u16), // FX15 - Delay Timer = VX
-    SETSTV(u16), // FX18 - Sound Timer = VX
-    INCSETIV(u16), // FX1E - I += VX
-    SETIFONT(u16), // FX29 - Set I to the font char in VX
-    BCDTORAM(u16), // FX33 - stores BCD of VX into RAM[I]
-    VTORAM(u16), // FX55 - V0 - VX into RAM starting at RAM[I], Inclusive Range
-    RAMTOV(u16), // FX65 - RaM into V registers starting with RAM[I], Inclusive
+    SCROLLDOWN(Nibble), // 00CN - Scroll the display down N pixels
+    SCROLLUP(Nibble), // 00DN - Scroll the display up N pixels
+    SCROLLRIGHT, // 00FB - Scroll the display right 4 pixels
+    SCROLLLEFT, // 00FC - Scroll the display left 4 pixels
+    EXIT, // 00FD - Stop the interpreter
+    LORES, // 00FE - 64x32 display
+    HIRES, // 00FF - 128x64 display
+    JMP(Addr), // 1NNN - Jump to Address NNN
+    CALL(Addr), // 2NNN - Call sub at NNN
+    SKIPIFVNN(Reg, Byte), //3XNN - Skip if VX == 0xNN
+    SKIPIFNOTVNN(Reg, Byte), //4XNN - Skip if VX == 0xNN
+    SKIPIFVV(Reg, Reg), // 5XY0 - Skip if VX == VY
+    SAVERANGE(Reg, Reg), // 5XY2 - VX - VY into RAM starting at RAM[I], Inclusive, I unchanged
+    LOADRANGE(Reg, Reg), // 5XY3 - RAM starting at RAM[I] into VX - VY, Inclusive, I unchanged
+    SETVNN(Reg, Byte), // 6XNN - VX = 0xNN
+    INCSETVNN(Reg, Byte), // 7XNN - VX += 0xNN (Doesn't affect carry flag)
+    SETVV(Reg, Reg), // 8XY0 - VX = VY
+    ORSETVV(Reg, Reg), // 8XY1 - VX |= VY
+    ANDSETVV(Reg, Reg), // 8XY2 - VX &= VY
+    XORSETVV(Reg, Reg), // 8XY3 - VX ^= VY
+    INCSETVV(Reg, Reg), // 8XY4 - VX += VY, Sets VF if carry
+    DECSETVV(Reg, Reg), // 8XY5 - VX -= VY, Clear VF if carry
+    SHIFTRV(Reg, Reg), // 8XY6 - VX >>= 1 (or VX = VY >> 1), Dropped bit in VF
+    DIFFSETVV(Reg, Reg), // 8XY7 VX = VY - VX, Clear VF if borrow
+    SHIFTLV(Reg, Reg), // 8XYE VX <<= 1 (or VX = VY << 1), Store dropped bit in VF
+    SKIPIFNOTVV(Reg, Reg), // 9XY0 - Skip if VX != VY
+    SETINNN(Addr), // ANNN - I = NNN
+    LONGI, // F000 NNNN - I = NNNN, the address is the following word
+    JMPV(Addr), // BNNN - Jump to V0 + 0xNNN (or BXNN - VX + 0xXNN)
+    RAND(Reg, Byte), // CXNN - VX = rand() & 0xNN
+    DRAW(Reg, Reg, Nibble), // DXYN - Draw sprite at (VX, VY) N pixels tall, on/off based on I. VF set if any pixels flipped. N = 0 draws 16x16
+    SKIPIFKEY(Reg), // EX9E - Skip if key index in VX is pressed =
+    SKIPIFNOTKEY(Reg), // EXA1 - Skip if key at VX is not pressed
+    SETPLANES(Nibble), // FN01 - Select the drawing planes with bitmask N
+    LOADAUDIO, // F002 - Load the 16 byte audio pattern from RAM[I]
+    SETVDT(Reg), // FX07 - VX = Delay Timer
+    WAITFORKEY(Reg), // FX0A - Wait for key, index in VX, BLOCKING
+    SETDTV(Reg), // FX15 - Delay Timer = VX
+    SETSTV(Reg), // FX18 - Sound Timer = VX
+    INCSETIV(Reg), // FX1E - I += VX
+    SETIFONT(Reg), // FX29 - Set I to the font char in VX
+    SETIBIGFONT(Reg), // FX30 - Set I to the 8x10 font char in VX
+    BCDTORAM(Reg), // FX33 - stores BCD of VX into RAM[I]
+    SETPITCH(Reg), // FX3A - Audio pattern pitch = VX
+    VTORAM(Reg), // FX55 - V0 - VX into RAM starting at RAM[I], Inclusive Range
+    RAMTOV(Reg), // FX65 - RaM into V registers starting with RAM[I], Inclusive
+    STORERPL(Reg), // FX75 - V0 - VX into the RPL user flags, Inclusive
+    LOADRPL(Reg), // FX85 - RPL user flags into V0 - VX, Inclusive
     
-}
\ No newline at end of file
+}
+
+impl Instruction {
+    // F000's address word is left to whoever reads the next word
+    pub fn decode(byte: u16) -> Result<Instruction, DecodeError> {
+        //unpack the digits so we can pattern match them
+        let dig1 = (byte & 0xF000) >> 12;
+        let dig2 = (byte & 0x0F00) >> 8;
+        let dig3 = (byte & 0x00F0) >> 4;
+        let dig4 = byte & 0x000F;
+
+        // and the operands they make up, masked so they're always in range
+        let x = Reg(dig2 as u8);
+        let y = Reg(dig3 as u8);
+        let n = Nibble(dig4 as u8);
+        let nn = Byte((byte & 0xFF) as u8);
+        let nnn = Addr(byte & 0xFFF);
+
+        let op = match (dig1, dig2, dig3, dig4) {
+            (0, 0, 0, 0) => Instruction::NOP,
+
+            (0, 0, 0xE, 0) => Instruction::CLR,
+
+            (0, 0, 0xE, 0xE) => Instruction::RET,
+
+            (0, 0, 0xC, _) => Instruction::SCROLLDOWN(n),
+
+            (0, 0, 0xD, _) => Instruction::SCROLLUP(n),
+
+            (0, 0, 0xF, 0xB) => Instruction::SCROLLRIGHT,
+
+            (0, 0, 0xF, 0xC) => Instruction::SCROLLLEFT,
+
+            (0, 0, 0xF, 0xD) => Instruction::EXIT,
+
+            (0, 0, 0xF, 0xE) => Instruction::LORES,
+
+            (0, 0, 0xF, 0xF) => Instruction::HIRES,
+
+            (1, _, _, _) => Instruction::JMP(nnn),
+
+            (2, _, _, _) => Instruction::CALL(nnn),
+
+            (3, _, _, _) => Instruction::SKIPIFVNN(x, nn),
+
+            (4, _, _, _) => Instruction::SKIPIFNOTVNN(x, nn),
+
+            (5, _, _, 0) => Instruction::SKIPIFVV(x, y),
+
+            (5, _, _, 2) => Instruction::SAVERANGE(x, y),
+
+            (5, _, _, 3) => Instruction::LOADRANGE(x, y),
+
+            (6, _, _, _) => Instruction::SETVNN(x, nn),
+
+            (7, _, _, _) => Instruction::INCSETVNN(x, nn),
+
+            (8, _, _, 0) => Instruction::SETVV(x, y),
+
+            (8, _, _, 1) => Instruction::ORSETVV(x, y),
+
+            (8, _, _, 2) => Instruction::ANDSETVV(x, y),
+
+            (8, _, _, 3) => Instruction::XORSETVV(x, y),
+
+            (8, _, _, 4) => Instruction::INCSETVV(x, y),
+
+            (8, _, _, 5) => Instruction::DECSETVV(x, y),
+
+            (8, _, _, 6) => Instruction::SHIFTRV(x, y),
+
+            (8, _, _, 7) => Instruction::DIFFSETVV(x, y),
+
+            (8, _, _, 0xE) => Instruction::SHIFTLV(x, y),
+
+            (9, _, _, 0) => Instruction::SKIPIFNOTVV(x, y),
+
+            (0xA, _, _, _) => Instruction::SETINNN(nnn),
+
+            (0xB, _, _, _) => Instruction::JMPV(nnn),
+
+            (0xC, _, _, _) => Instruction::RAND(x, nn),
+
+            (0xD, _, _, _) => Instruction::DRAW(x, y, n),
+
+            (0xE, _, 9, 0xE) => Instruction::SKIPIFKEY(x),
+
+            (0xE, _, 0xA, 1) => Instruction::SKIPIFNOTKEY(x),
+
+            (0xF, 0, 0, 0) => Instruction::LONGI,
+
+            (0xF, _, 0, 1) => Instruction::SETPLANES(Nibble(dig2 as u8)),
+
+            (0xF, 0, 0, 2) => Instruction::LOADAUDIO,
+
+            (0xF, _, 0, 7) => Instruction::SETVDT(x),
+
+            (0xF, _, 0, 0xA) => Instruction::WAITFORKEY(x),
+
+            (0xF, _, 1, 5) => Instruction::SETDTV(x),
+
+            (0xF, _, 1, 8) => Instruction::SETSTV(x),
+
+            (0xF, _, 1, 0xE) => Instruction::INCSETIV(x),
+
+            (0xF, _, 2, 9) => Instruction::SETIFONT(x),
+
+            (0xF, _, 3, 0) => Instruction::SETIBIGFONT(x),
+
+            (0xF, _, 3, 3) => Instruction::BCDTORAM(x),
+
+            (0xF, _, 3, 0xA) => Instruction::SETPITCH(x),
+
+            (0xF, _, 5, 5) => Instruction::VTORAM(x),
+
+            (0xF, _, 6, 5) => Instruction::RAMTOV(x),
+
+            (0xF, _, 7, 5) => Instruction::STORERPL(x),
+
+            (0xF, _, 8, 5) => Instruction::LOADRPL(x),
+
+            (_, _, _, _) => return Err(DecodeError { opcode: byte }),
+        };
+
+        Ok(op)
+    }
+
+    // The opcode decode() turns back into self, LONGI's address word isn't part of it
+    pub fn encode(self) -> u16 {
+        let x = |x: Reg| (x.0 as u16) << 8;
+        let xy = |x: Reg, y: Reg| ((x.0 as u16) << 8) | ((y.0 as u16) << 4);
+        let xnn = |x: Reg, nn: Byte| ((x.0 as u16) << 8) | nn.0 as u16;
+
+        match self {
+            Instruction::NOP => 0x0000,
+            Instruction::CLR => 0x00E0,
+            Instruction::RET => 0x00EE,
+            Instruction::SCROLLDOWN(n) => 0x00C0 | n.0 as u16,
+            Instruction::SCROLLUP(n) => 0x00D0 | n.0 as u16,
+            Instruction::SCROLLRIGHT => 0x00FB,
+            Instruction::SCROLLLEFT => 0x00FC,
+            Instruction::EXIT => 0x00FD,
+            Instruction::LORES => 0x00FE,
+            Instruction::HIRES => 0x00FF,
+            Instruction::JMP(nnn) => 0x1000 | nnn.0,
+            Instruction::CALL(nnn) => 0x2000 | nnn.0,
+            Instruction::SKIPIFVNN(vx, nn) => 0x3000 | xnn(vx, nn),
+            Instruction::SKIPIFNOTVNN(vx, nn) => 0x4000 | xnn(vx, nn),
+            Instruction::SKIPIFVV(vx, vy) => 0x5000 | xy(vx, vy),
+            Instruction::SAVERANGE(vx, vy) => 0x5002 | xy(vx, vy),
+            Instruction::LOADRANGE(vx, vy) => 0x5003 | xy(vx, vy),
+            Instruction::SETVNN(vx, nn) => 0x6000 | xnn(vx, nn),
+            Instruction::INCSETVNN(vx, nn) => 0x7000 | xnn(vx, nn),
+            Instruction::SETVV(vx, vy) => 0x8000 | xy(vx, vy),
+            Instruction::ORSETVV(vx, vy) => 0x8001 | xy(vx, vy),
+            Instruction::ANDSETVV(vx, vy) => 0x8002 | xy(vx, vy),
+            Instruction::XORSETVV(vx, vy) => 0x8003 | xy(vx, vy),
+            Instruction::INCSETVV(vx, vy) => 0x8004 | xy(vx, vy),
+            Instruction::DECSETVV(vx, vy) => 0x8005 | xy(vx, vy),
+            Instruction::SHIFTRV(vx, vy) => 0x8006 | xy(vx, vy),
+            Instruction::DIFFSETVV(vx, vy) => 0x8007 | xy(vx, vy),
+            Instruction::SHIFTLV(vx, vy) => 0x800E | xy(vx, vy),
+            Instruction::SKIPIFNOTVV(vx, vy) => 0x9000 | xy(vx, vy),
+            Instruction::SETINNN(nnn) => 0xA000 | nnn.0,
+            Instruction::LONGI => 0xF000,
+            Instruction::JMPV(nnn) => 0xB000 | nnn.0,
+            Instruction::RAND(vx, nn) => 0xC000 | xnn(vx, nn),
+            Instruction::DRAW(vx, vy, n) => 0xD000 | xy(vx, vy) | n.0 as u16,
+            Instruction::SKIPIFKEY(vx) => 0xE09E | x(vx),
+            Instruction::SKIPIFNOTKEY(vx) => 0xE0A1 | x(vx),
+            Instruction::SETPLANES(n) => 0xF001 | (n.0 as u16) << 8,
+            Instruction::LOADAUDIO => 0xF002,
+            Instruction::SETVDT(vx) => 0xF007 | x(vx),
+            Instruction::WAITFORKEY(vx) => 0xF00A | x(vx),
+            Instruction::SETDTV(vx) => 0xF015 | x(vx),
+            Instruction::SETSTV(vx) => 0xF018 | x(vx),
+            Instruction::INCSETIV(vx) => 0xF01E | x(vx),
+            Instruction::SETIFONT(vx) => 0xF029 | x(vx),
+            Instruction::SETIBIGFONT(vx) => 0xF030 | x(vx),
+            Instruction::BCDTORAM(vx) => 0xF033 | x(vx),
+            Instruction::SETPITCH(vx) => 0xF03A | x(vx),
+            Instruction::VTORAM(vx) => 0xF055 | x(vx),
+            Instruction::RAMTOV(vx) => 0xF065 | x(vx),
+            Instruction::STORERPL(vx) => 0xF075 | x(vx),
+            Instruction::LOADRPL(vx) => 0xF085 | x(vx),
+        }
+    }
+
+    // The variant name on its own, the first word of the Display form
+    pub fn name(self) -> &'static str {
+        match self {
+            Instruction::NOP => "NOP",
+            Instruction::CLR => "CLR",
+            Instruction::RET => "RET",
+            Instruction::SCROLLDOWN(..) => "SCROLLDOWN",
+            Instruction::SCROLLUP(..) => "SCROLLUP",
+            Instruction::SCROLLRIGHT => "SCROLLRIGHT",
+            Instruction::SCROLLLEFT => "SCROLLLEFT",
+            Instruction::EXIT => "EXIT",
+            Instruction::LORES => "LORES",
+            Instruction::HIRES => "HIRES",
+            Instruction::JMP(..) => "JMP",
+            Instruction::CALL(..) => "CALL",
+            Instruction::SKIPIFVNN(..) => "SKIPIFVNN",
+            Instruction::SKIPIFNOTVNN(..) => "SKIPIFNOTVNN",
+            Instruction::SKIPIFVV(..) => "SKIPIFVV",
+            Instruction::SAVERANGE(..) => "SAVERANGE",
+            Instruction::LOADRANGE(..) => "LOADRANGE",
+            Instruction::SETVNN(..) => "SETVNN",
+            Instruction::INCSETVNN(..) => "INCSETVNN",
+            Instruction::SETVV(..) => "SETVV",
+            Instruction::ORSETVV(..) => "ORSETVV",
+            Instruction::ANDSETVV(..) => "ANDSETVV",
+            Instruction::XORSETVV(..) => "XORSETVV",
+            Instruction::INCSETVV(..) => "INCSETVV",
+            Instruction::DECSETVV(..) => "DECSETVV",
+            Instruction::SHIFTRV(..) => "SHIFTRV",
+            Instruction::DIFFSETVV(..) => "DIFFSETVV",
+            Instruction::SHIFTLV(..) => "SHIFTLV",
+            Instruction::SKIPIFNOTVV(..) => "SKIPIFNOTVV",
+            Instruction::SETINNN(..) => "SETINNN",
+            Instruction::LONGI => "LONGI",
+            Instruction::JMPV(..) => "JMPV",
+            Instruction::RAND(..) => "RAND",
+            Instruction::DRAW(..) => "DRAW",
+            Instruction::SKIPIFKEY(..) => "SKIPIFKEY",
+            Instruction::SKIPIFNOTKEY(..) => "SKIPIFNOTKEY",
+            Instruction::SETPLANES(..) => "SETPLANES",
+            Instruction::LOADAUDIO => "LOADAUDIO",
+            Instruction::SETVDT(..) => "SETVDT",
+            Instruction::WAITFORKEY(..) => "WAITFORKEY",
+            Instruction::SETDTV(..) => "SETDTV",
+            Instruction::SETSTV(..) => "SETSTV",
+            Instruction::INCSETIV(..) => "INCSETIV",
+            Instruction::SETIFONT(..) => "SETIFONT",
+            Instruction::SETIBIGFONT(..) => "SETIBIGFONT",
+            Instruction::BCDTORAM(..) => "BCDTORAM",
+            Instruction::SETPITCH(..) => "SETPITCH",
+            Instruction::VTORAM(..) => "VTORAM",
+            Instruction::RAMTOV(..) => "RAMTOV",
+            Instruction::STORERPL(..) => "STORERPL",
+            Instruction::LOADRPL(..) => "LOADRPL",
+        }
+    }
+}
+
+impl TryFrom<u16> for Instruction {
+    type Error = DecodeError;
+
+    fn try_from(opcode: u16) -> Result<Self, Self::Error> {
+        Instruction::decode(opcode)
+    }
+}
+
+impl From<Instruction> for u16 {
+    fn from(op: Instruction) -> u16 {
+        op.encode()
+    }
+}
+
+// Canonical assembly: the variant name, then registers as VX, bytes and addresses in hex and nibbles in decimal
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Instruction::NOP => write!(f, "NOP"),
+            Instruction::CLR => write!(f, "CLR"),
+            Instruction::RET => write!(f, "RET"),
+            Instruction::SCROLLDOWN(n) => write!(f, "SCROLLDOWN {}", n),
+            Instruction::SCROLLUP(n) => write!(f, "SCROLLUP {}", n),
+            Instruction::SCROLLRIGHT => write!(f, "SCROLLRIGHT"),
+            Instruction::SCROLLLEFT => write!(f, "SCROLLLEFT"),
+            Instruction::EXIT => write!(f, "EXIT"),
+            Instruction::LORES => write!(f, "LORES"),
+            Instruction::HIRES => write!(f, "HIRES"),
+            Instruction::JMP(nnn) => write!(f, "JMP {}", nnn),
+            Instruction::CALL(nnn) => write!(f, "CALL {}", nnn),
+            Instruction::SKIPIFVNN(x, nn) => write!(f, "SKIPIFVNN {}, {}", x, nn),
+            Instruction::SKIPIFNOTVNN(x, nn) => write!(f, "SKIPIFNOTVNN {}, {}", x, nn),
+            Instruction::SKIPIFVV(x, y) => write!(f, "SKIPIFVV {}, {}", x, y),
+            Instruction::SAVERANGE(x, y) => write!(f, "SAVERANGE {}, {}", x, y),
+            Instruction::LOADRANGE(x, y) => write!(f, "LOADRANGE {}, {}", x, y),
+            Instruction::SETVNN(x, nn) => write!(f, "SETVNN {}, {}", x, nn),
+            Instruction::INCSETVNN(x, nn) => write!(f, "INCSETVNN {}, {}", x, nn),
+            Instruction::SETVV(x, y) => write!(f, "SETVV {}, {}", x, y),
+            Instruction::ORSETVV(x, y) => write!(f, "ORSETVV {}, {}", x, y),
+            Instruction::ANDSETVV(x, y) => write!(f, "ANDSETVV {}, {}", x, y),
+            Instruction::XORSETVV(x, y) => write!(f, "XORSETVV {}, {}", x, y),
+            Instruction::INCSETVV(x, y) => write!(f, "INCSETVV {}, {}", x, y),
+            Instruction::DECSETVV(x, y) => write!(f, "DECSETVV {}, {}", x, y),
+            Instruction::SHIFTRV(x, y) => write!(f, "SHIFTRV {}, {}", x, y),
+            Instruction::DIFFSETVV(x, y) => write!(f, "DIFFSETVV {}, {}", x, y),
+            Instruction::SHIFTLV(x, y) => write!(f, "SHIFTLV {}, {}", x, y),
+            Instruction::SKIPIFNOTVV(x, y) => write!(f, "SKIPIFNOTVV {}, {}", x, y),
+            Instruction::SETINNN(nnn) => write!(f, "SETINNN {}", nnn),
+            Instruction::LONGI => write!(f, "LONGI"),
+            Instruction::JMPV(nnn) => write!(f, "JMPV {}", nnn),
+            Instruction::RAND(x, nn) => write!(f, "RAND {}, {}", x, nn),
+            Instruction::DRAW(x, y, n) => write!(f, "DRAW {}, {}, {}", x, y, n),
+            Instruction::SKIPIFKEY(x) => write!(f, "SKIPIFKEY {}", x),
+            Instruction::SKIPIFNOTKEY(x) => write!(f, "SKIPIFNOTKEY {}", x),
+            Instruction::SETPLANES(n) => write!(f, "SETPLANES {}", n),
+            Instruction::LOADAUDIO => write!(f, "LOADAUDIO"),
+            Instruction::SETVDT(x) => write!(f, "SETVDT {}", x),
+            Instruction::WAITFORKEY(x) => write!(f, "WAITFORKEY {}", x),
+            Instruction::SETDTV(x) => write!(f, "SETDTV {}", x),
+            Instruction::SETSTV(x) => write!(f, "SETSTV {}", x),
+            Instruction::INCSETIV(x) => write!(f, "INCSETIV {}", x),
+            Instruction::SETIFONT(x) => write!(f, "SETIFONT {}", x),
+            Instruction::SETIBIGFONT(x) => write!(f, "SETIBIGFONT {}", x),
+            Instruction::BCDTORAM(x) => write!(f, "BCDTORAM {}", x),
+            Instruction::SETPITCH(x) => write!(f, "SETPITCH {}", x),
+            Instruction::VTORAM(x) => write!(f, "VTORAM {}", x),
+            Instruction::RAMTOV(x) => write!(f, "RAMTOV {}", x),
+            Instruction::STORERPL(x) => write!(f, "STORERPL {}", x),
+            Instruction::LOADRPL(x) => write!(f, "LOADRPL {}", x),
+        }
+    }
+}
@@ -1,3 +1,18 @@
+pub mod asm;
+pub mod conformance;
+pub mod coverage;
+pub mod debugger;
+pub mod disasm;
 pub mod emu;
+pub mod error;
+pub mod gdb;
+pub mod image;
 pub mod instruction;
-pub mod speaker;
\ No newline at end of file
+pub mod movie;
+pub mod profile;
+pub mod quirks;
+pub mod rng;
+pub mod scenario;
+pub mod speaker;
+pub mod state;
+pub mod trace;
\ No newline at end of file
@@ -1,6 +1,21 @@
+pub mod asm;
+pub mod conformance;
+pub mod coverage;
+pub mod debugger;
+pub mod disasm;
 pub mod emu;
+pub mod error;
+pub mod gdb;
+pub mod image;
 pub mod instruction;
+pub mod movie;
+pub mod profile;
+pub mod quirks;
+pub mod rng;
+pub mod scenario;
 pub mod speaker;
+pub mod state;
+pub mod trace;
 
 // Re-Export
 pub use emu::*;
\ No newline at end of file
@@ -0,0 +1,137 @@
+use crate::emu::*;
+use crate::error::*;
+use crate::quirks::*;
+use crate::rng::*;
+use crate::state::*;
+
+// Binary layout: MAGIC, FORMAT_VERSION (u16), quirks and RNG encoded as in save states, the ROM hash (u64),
+// a flag byte plus u64 for the end hash, then a u32 frame count and one u16 key mask per frame
+const MAGIC: &[u8; 4] = b"POKM";
+pub const MOVIE_VERSION: u16 = 2; // 2 stores the stack_depth quirk as a u64, 1 had a u16
+
+// A recorded play session. Starting from power on with the same quirks, generator and ROM and replaying the
+// keypad frame by frame reproduces the run exactly. The generator as it was at power on stands in for the seed.
+// Movies always start with the RPL flags cleared.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Movie {
+    pub quirks: Quirks,
+    pub rng: Rng,
+    pub rom_hash: u64,         // fnv1a of the ROM it was recorded against
+    pub frames: Vec<u16>,      // keypad mask held during each frame, bit 0 is key 0
+    pub end_hash: Option<u64>, // Emu::state_hash after the last frame, playback checks against it
+}
+
+impl Movie {
+    // Take the settings from an Emu that has just been created, before it runs anything
+    pub fn new(emu: &Emu, rom: &[u8]) -> Self {
+        Self {
+            quirks: emu.quirks(),
+            rng: emu.rng(),
+            rom_hash: fnv1a(rom),
+            frames: Vec::new(),
+            end_hash: None,
+        }
+    }
+
+    // Power on an Emu the way the recording started, refusing a ROM it wasn't recorded against
+    pub fn start(&self, rom: &[u8]) -> Result<Emu, EmuError> {
+        if fnv1a(rom) != self.rom_hash {
+            return Err(invalid("recorded against a different ROM"));
+        }
+
+        let mut emu = Emu::with_quirks(self.quirks);
+        emu.set_rng(self.rng);
+        emu.load(rom)?;
+
+        Ok(emu)
+    }
+
+    // Call after the frame has run, with the keys it ran with
+    pub fn record(&mut self, emu: &Emu) {
+        self.frames.push(emu.keys_mask());
+    }
+
+    // Set up the keypad for the given frame, false once the movie has run out
+    pub fn apply(&self, emu: &mut Emu, frame: usize) -> bool {
+        match self.frames.get(frame) {
+            Some(mask) => {
+                emu.set_keys_mask(*mask);
+                true
+            }
+            None => false,
+        }
+    }
+
+    // Play the whole movie, returns the Emu as it was after the last frame
+    pub fn replay(&self, rom: &[u8]) -> Result<Emu, EmuError> {
+        let mut emu = self.start(rom)?;
+
+        for frame in 0..self.frames.len() {
+            self.apply(&mut emu, frame);
+            emu.run_frame()?;
+        }
+
+        Ok(emu)
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(self.frames.len() * 2 + 64);
+
+        out.extend_from_slice(MAGIC);
+        out.extend_from_slice(&MOVIE_VERSION.to_le_bytes());
+
+        write_quirks(&mut out, &self.quirks);
+        write_rng(&mut out, &self.rng);
+        out.extend_from_slice(&self.rom_hash.to_le_bytes());
+        out.push(self.end_hash.is_some() as u8);
+        out.extend_from_slice(&self.end_hash.unwrap_or(0).to_le_bytes());
+
+        out.extend_from_slice(&(self.frames.len() as u32).to_le_bytes());
+        for mask in &self.frames {
+            out.extend_from_slice(&mask.to_le_bytes());
+        }
+
+        out
+    }
+
+    pub fn from_bytes(data: &[u8]) -> Result<Self, EmuError> {
+        let mut reader = Reader::new(data, invalid);
+
+        if reader.take(MAGIC.len())? != MAGIC {
+            return Err(invalid("not a POK8 movie"));
+        }
+
+        let version = reader.u16()?;
+        if version == 0 || version > MOVIE_VERSION {
+            return Err(invalid(&format!("unsupported movie version {}", version)));
+        }
+
+        let quirks = read_quirks(&mut reader, version >= 2)?;
+        let rng = read_rng(&mut reader)?;
+        let rom_hash = reader.u64()?;
+        let has_end_hash = reader.bool()?;
+        let end_hash = reader.u64()?;
+
+        let count = reader.u32()? as usize;
+        let mut frames = Vec::with_capacity(count.min(data.len() / 2));
+        for _ in 0..count {
+            frames.push(reader.u16()?);
+        }
+
+        reader.finish()?;
+
+        Ok(Self {
+            quirks,
+            rng,
+            rom_hash,
+            frames,
+            end_hash: if has_end_hash { Some(end_hash) } else { None },
+        })
+    }
+}
+
+fn invalid(reason: &str) -> EmuError {
+    EmuError::InvalidMovie {
+        reason: reason.to_string(),
+    }
+}
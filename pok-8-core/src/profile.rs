@@ -0,0 +1,183 @@
+use crate::emu::*;
+use crate::instruction::*;
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+
+// Where the cycles go: per address, per kind of instruction and per subroutine, set up with Emu::set_profiler.
+// Subroutines are named by their entry address the way disasm::labels does, sub_XXX, under a root called main
+#[derive(Debug, Clone, Default)]
+pub struct Profiler {
+    cycles: u64,
+    addrs: HashMap<u16, (u64, Instruction)>, // executions of each address, and what was last there
+    kinds: HashMap<&'static str, u64>,
+    calls: HashMap<u16, u64>,     // CALLs into each subroutine
+    stack: Vec<u16>,              // entry addresses of the subroutines CALL went into, outermost first
+    stacks: HashMap<Vec<u16>, u64>, // cycles spent with exactly this call stack
+}
+
+// A row of the subroutine table, inclusive counts the subroutines it called as well
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Subroutine {
+    pub addr: u16,
+    pub calls: u64,
+    pub self_cycles: u64,
+    pub total_cycles: u64,
+}
+
+impl Profiler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cycles(&self) -> u64 {
+        self.cycles
+    }
+
+    // Busiest first, along with the instruction found there
+    pub fn hotspots(&self) -> Vec<(u16, u64, Instruction)> {
+        let mut hotspots: Vec<_> = self.addrs.iter().map(|(addr, (count, op))| (*addr, *count, *op)).collect();
+        hotspots.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        hotspots
+    }
+
+    // Instruction::name() and how many times one of those ran, busiest first
+    pub fn kinds(&self) -> Vec<(&'static str, u64)> {
+        let mut kinds: Vec<_> = self.kinds.iter().map(|(name, count)| (*name, *count)).collect();
+        kinds.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+        kinds
+    }
+
+    // Every subroutine that ran or was called, most inclusive cycles first
+    pub fn subroutines(&self) -> Vec<Subroutine> {
+        let mut subs: BTreeMap<u16, Subroutine> = BTreeMap::new();
+
+        for (addr, calls) in &self.calls {
+            subroutine(&mut subs, *addr).calls = *calls;
+        }
+
+        for (stack, cycles) in &self.stacks {
+            if let Some(last) = stack.last() {
+                subroutine(&mut subs, *last).self_cycles += cycles;
+            }
+
+            // recursion puts a subroutine on the stack more than once, it still only spent these cycles once
+            let mut seen: Vec<u16> = stack.clone();
+            seen.sort_unstable();
+            seen.dedup();
+            for addr in seen {
+                subroutine(&mut subs, addr).total_cycles += cycles;
+            }
+        }
+
+        let mut subs: Vec<Subroutine> = subs.into_values().collect();
+        subs.sort_by(|a, b| b.total_cycles.cmp(&a.total_cycles).then(a.addr.cmp(&b.addr)));
+        subs
+    }
+
+    // Hotspots, instruction kinds and subroutines, top rows of each
+    pub fn summary(&self, top: usize) -> String {
+        let percent = |count: u64| count as f64 * 100.0 / self.cycles.max(1) as f64;
+        let mut out = String::new();
+
+        writeln!(out, "{} instructions", self.cycles).unwrap();
+
+        writeln!(out, "\nHOTSPOTS\n  addr        count       %  instruction").unwrap();
+        for (addr, count, op) in self.hotspots().into_iter().take(top) {
+            writeln!(out, "  {:04X}  {:>11}  {:>5.1}%  {}", addr, count, percent(count), op).unwrap();
+        }
+
+        writeln!(out, "\nINSTRUCTIONS\n  name                count       %").unwrap();
+        for (name, count) in self.kinds().into_iter().take(top) {
+            writeln!(out, "  {:<12}  {:>11}  {:>5.1}%", name, count, percent(count)).unwrap();
+        }
+
+        writeln!(out, "\nSUBROUTINES\n  name        calls         self       %        total       %").unwrap();
+        for sub in self.subroutines().into_iter().take(top) {
+            writeln!(
+                out,
+                "  {}  {:>9}  {:>11}  {:>5.1}%  {:>11}  {:>5.1}%",
+                frame_name(sub.addr),
+                sub.calls,
+                sub.self_cycles,
+                percent(sub.self_cycles),
+                sub.total_cycles,
+                percent(sub.total_cycles)
+            )
+            .unwrap();
+        }
+
+        out
+    }
+
+    // One line per call stack, main;sub_2A4;sub_31E 1234, the input flamegraph.pl and inferno-flamegraph take
+    pub fn folded(&self) -> String {
+        let mut lines: Vec<String> = self
+            .stacks
+            .iter()
+            .map(|(stack, cycles)| {
+                let frames: Vec<String> = stack.iter().map(|addr| format!(";{}", frame_name(*addr))).collect();
+                format!("main{} {}", frames.concat(), cycles)
+            })
+            .collect();
+
+        lines.sort();
+        lines.iter().map(|line| format!("{}\n", line)).collect()
+    }
+
+    // Called before op runs, so a CALL counts towards its caller and a RET towards the subroutine it leaves
+    pub(crate) fn record(&mut self, emu: &Emu, addr: u16, op: Instruction) {
+        // a state load or a failed CALL leaves the tracked stack behind Emu's, so rebuild it from the return addresses
+        if self.stack.len() != emu.stack().len() {
+            self.stack = emu.stack().iter().map(|ret| caller_target(emu, *ret)).collect();
+        }
+
+        self.cycles += 1;
+        let seen = self.addrs.entry(addr).or_insert((0, op));
+        seen.0 += 1;
+        seen.1 = op;
+        *self.kinds.entry(op.name()).or_insert(0) += 1;
+
+        match self.stacks.get_mut(self.stack.as_slice()) {
+            Some(cycles) => *cycles += 1,
+            None => {
+                self.stacks.insert(self.stack.clone(), 1);
+            }
+        }
+
+        match op {
+            Instruction::CALL(nnn) => {
+                self.stack.push(nnn.get());
+                *self.calls.entry(nnn.get()).or_insert(0) += 1;
+            }
+            Instruction::RET => {
+                self.stack.pop();
+            }
+            _ => (),
+        }
+    }
+}
+
+fn subroutine(subs: &mut BTreeMap<u16, Subroutine>, addr: u16) -> &mut Subroutine {
+    subs.entry(addr).or_insert(Subroutine {
+        addr,
+        calls: 0,
+        self_cycles: 0,
+        total_cycles: 0,
+    })
+}
+
+fn frame_name(addr: u16) -> String {
+    format!("sub_{:03X}", addr)
+}
+
+// The subroutine a return address went into, read off the CALL just before it
+fn caller_target(emu: &Emu, ret: u16) -> u16 {
+    let at = ret.wrapping_sub(2) as usize;
+    let word = emu.ram().get(at..(at + 2)).map_or(0, |bytes| u16::from_be_bytes([bytes[0], bytes[1]]));
+
+    match Instruction::decode(word) {
+        Ok(Instruction::CALL(nnn)) => nnn.get(),
+        _ => ret.wrapping_sub(2), // self-modified since, the call site is the best name left
+    }
+}
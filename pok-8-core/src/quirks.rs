@@ -0,0 +1,101 @@
+// How FX55/FX65 leave the I register once they are done
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IncrementI {
+    Unchanged,  // SUPER-CHIP and most modern interpreters
+    ByX,        // CHIP-48 (off by one from the original)
+    ByXPlusOne, // COSMAC VIP, I ends up past the last register touched
+}
+
+// The original opcodes are ambiguous across platforms, each flag picks one interpretation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Quirks {
+    pub shift_uses_vy: bool,     // 8XY6/8XYE shift VY into VX instead of shifting VX in place
+    pub increment_i: IncrementI, // I after FX55/FX65
+    pub jump_uses_vx: bool,      // BXNN jumps to XNN + VX instead of NNN + V0
+    pub vf_reset: bool,          // 8XY1/8XY2/8XY3 clear VF
+    pub clip_sprites: bool,      // sprites are cut off at the screen edge instead of wrapping around
+    pub display_wait: bool,      // DXYN waits for the next frame before drawing
+    pub stack_depth: usize,      // nested CALLs allowed before the stack overflows
+}
+
+impl Quirks {
+    // Names accepted by from_name, in the order they are listed to users
+    pub const PRESETS: [&'static str; 5] = ["vip", "chip48", "schip", "xochip", "modern"];
+
+    pub fn cosmac_vip() -> Self {
+        Self {
+            shift_uses_vy: true,
+            increment_i: IncrementI::ByXPlusOne,
+            jump_uses_vx: false,
+            vf_reset: true,
+            clip_sprites: true,
+            display_wait: true,
+            stack_depth: 12,
+        }
+    }
+
+    pub fn chip48() -> Self {
+        Self {
+            shift_uses_vy: false,
+            increment_i: IncrementI::ByX,
+            jump_uses_vx: true,
+            vf_reset: false,
+            clip_sprites: true,
+            display_wait: false,
+            stack_depth: 16,
+        }
+    }
+
+    pub fn superchip() -> Self {
+        Self {
+            shift_uses_vy: false,
+            increment_i: IncrementI::Unchanged,
+            jump_uses_vx: true,
+            vf_reset: false,
+            clip_sprites: true,
+            display_wait: false,
+            stack_depth: 16,
+        }
+    }
+
+    pub fn xochip() -> Self {
+        Self {
+            shift_uses_vy: true,
+            increment_i: IncrementI::ByXPlusOne,
+            jump_uses_vx: false,
+            vf_reset: false,
+            clip_sprites: false,
+            display_wait: false,
+            stack_depth: 16,
+        }
+    }
+
+    pub fn modern() -> Self {
+        Self {
+            shift_uses_vy: false,
+            increment_i: IncrementI::Unchanged,
+            jump_uses_vx: false,
+            vf_reset: false,
+            clip_sprites: false,
+            display_wait: false,
+            stack_depth: 16,
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "vip" | "cosmac" | "cosmac-vip" => Some(Self::cosmac_vip()),
+            "chip48" | "chip-48" => Some(Self::chip48()),
+            "schip" | "superchip" | "super-chip" => Some(Self::superchip()),
+            "xochip" | "xo-chip" => Some(Self::xochip()),
+            "modern" => Some(Self::modern()),
+            _ => None,
+        }
+    }
+}
+
+impl Default for Quirks {
+    fn default() -> Self {
+        Self::modern()
+    }
+}
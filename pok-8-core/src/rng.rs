@@ -0,0 +1,99 @@
+// Which generator CXNN draws from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RngKind {
+    Xorshift, // xorshift64*, good spread and the default
+    Counter,  // a running sum fed by a frame counter, see Rng::next_counter
+}
+
+impl RngKind {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "xorshift" => Some(RngKind::Xorshift),
+            "counter" => Some(RngKind::Counter),
+            _ => None,
+        }
+    }
+}
+
+// CXNN's source of random bytes. All of its state is the one u64 so snapshots can carry it,
+// the same seed and inputs always give the same run
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rng {
+    kind: RngKind,
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(kind: RngKind, seed: u64) -> Self {
+        let state = match kind {
+            // xorshift sticks at 0 forever, and nearby seeds should still start far apart
+            RngKind::Xorshift => splitmix64(seed).max(1),
+            RngKind::Counter => seed & 0xFFFF,
+        };
+
+        Self { kind, state }
+    }
+
+    // Rebuild a generator exactly as it was, e.g. out of a save state. None for a state new() and the generator itself
+    // never produce: a xorshift state of 0, which would only ever give 0, or a counter state past 16 bits
+    pub fn from_parts(kind: RngKind, state: u64) -> Option<Self> {
+        let valid = match kind {
+            RngKind::Xorshift => state != 0,
+            RngKind::Counter => state <= 0xFFFF,
+        };
+
+        valid.then_some(Self { kind, state })
+    }
+
+    pub fn kind(&self) -> RngKind {
+        self.kind
+    }
+
+    pub fn state(&self) -> u64 {
+        self.state
+    }
+
+    pub fn next_byte(&mut self) -> u8 {
+        match self.kind {
+            RngKind::Xorshift => self.next_xorshift(),
+            RngKind::Counter => self.next_counter(),
+        }
+    }
+
+    // Called once per frame, only the counter generator cares
+    pub fn tick(&mut self) {
+        if self.kind == RngKind::Counter {
+            let counter = (self.state as u8).wrapping_add(1);
+            self.state = (self.state & !0xFF) | counter as u64;
+        }
+    }
+
+    fn next_xorshift(&mut self) -> u8 {
+        let mut x = self.state;
+        x ^= x >> 12;
+        x ^= x << 25;
+        x ^= x >> 27;
+        self.state = x;
+
+        (x.wrapping_mul(0x2545_F491_4F6C_DD1D) >> 56) as u8
+    }
+
+    // A running 8-bit sum fed by a counter bumped every frame (tick) as well as every call, so results follow the
+    // program's timing and repeat quickly. Loosely after the COSMAC VIP's, but not its routine or its sequence: that
+    // depends on the VIP interpreter's own instruction timing, which this core doesn't model
+    fn next_counter(&mut self) -> u8 {
+        let counter = self.state as u8;
+        let sum = ((self.state >> 8) as u8).wrapping_add(counter).rotate_right(1);
+        let counter = counter.wrapping_add(1);
+
+        self.state = ((sum as u64) << 8) | counter as u64;
+        sum
+    }
+}
+
+fn splitmix64(seed: u64) -> u64 {
+    let mut z = seed.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
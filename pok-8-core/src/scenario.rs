@@ -0,0 +1,654 @@
+use crate::asm::*;
+use crate::debugger::Compare;
+use crate::emu::*;
+use crate::image::*;
+use crate::instruction::*;
+use crate::quirks::*;
+use crate::rng::*;
+
+use std::fmt::{self, Write};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// Scenario files, one statement per line, ; starts a comment:
+//   rom <file>                  the program under test, relative to the scenario. .asm source is assembled first
+//   quirks <profile>            settings, before the first step. The seed is 0 unless given, so runs repeat
+//   seed <number>
+//   rng <kind>
+//   press <key>                 key in hex, held until it's released
+//   release <key>
+//   wait <frames>               run that many frames
+//   at <frame>                  run until that many frames have gone by since power on
+//   expect vX|i|pc|sp|dt|st <op> <value>     op is one of == != < <= > >=
+//   expect mem <addr> <byte> ...             RAM from addr holds exactly these
+//   expect halted
+//   expect state <hash>         Emu::state_hash, as pok8-headless prints it
+//   expect screen <file>        a .png, e.g. from pok8-headless, or text in Display::to_text's format
+//   expect screen [at <x> <y>]  followed by rows of . # + @ and a line with just `end`, which can be smaller
+//                               than the screen, it's checked against the part at x,y (0,0 unless given)
+// Numbers are 0x1F, $1F or 31. Expectations don't stop the run, every one that fails is reported.
+// Screen files and state hashes are goldens: bless() writes them from what the run produced instead of checking
+const DEFAULT_SEED: u64 = 0;
+
+// A test for a CHIP-8 program, see above for the file format
+#[derive(Debug, Clone)]
+pub struct Scenario {
+    pub file: PathBuf,
+    rom: PathBuf,
+    config: EmuConfig,
+    steps: Vec<(usize, Step)>, // with the line each came from
+}
+
+#[derive(Debug, Clone)]
+enum Step {
+    Press(usize),
+    Release(usize),
+    Wait(usize),
+    Expect(Expect),
+}
+
+#[derive(Debug, Clone)]
+enum Expect {
+    Reg(Target, Compare, u16),
+    Mem(u16, Vec<u8>),
+    Halted,
+    State(u64),
+    Screen(Image, (usize, usize)), // and the top left corner it goes at
+    ScreenFile(PathBuf),
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Target {
+    V(Reg),
+    I,
+    Pc,
+    Sp,
+    Dt,
+    St,
+}
+
+// A scenario that couldn't be read, line 0 is the file as a whole
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScenarioError {
+    pub file: String,
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ScenarioError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.line == 0 {
+            write!(f, "{}: {}", self.file, self.message)
+        } else {
+            write!(f, "{}:{}: {}", self.file, self.line, self.message)
+        }
+    }
+}
+
+impl std::error::Error for ScenarioError {}
+
+// An expectation that didn't hold, or whatever stopped the run. The message can run over several lines
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Failure {
+    pub line: usize,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Report {
+    pub frames: usize, // frames actually run, short of the scenario's if something stopped it
+    pub failures: Vec<Failure>,
+    pub blessed: usize, // goldens bless() wrote
+}
+
+impl Report {
+    pub fn passed(&self) -> bool {
+        self.failures.is_empty()
+    }
+}
+
+impl Scenario {
+    pub fn from_file(path: &Path) -> Result<Self, ScenarioError> {
+        let text = fs::read_to_string(path).map_err(|err| ScenarioError {
+            file: path.display().to_string(),
+            line: 0,
+            message: err.to_string(),
+        })?;
+
+        Self::parse(&text, path)
+    }
+
+    // file names the scenario in errors, and the paths inside it are relative to its directory
+    pub fn parse(text: &str, file: &Path) -> Result<Self, ScenarioError> {
+        let dir = file.parent().unwrap_or(Path::new("."));
+        let mut rom = None;
+        let mut config = EmuConfig {
+            seed: Some(DEFAULT_SEED),
+            ..EmuConfig::default()
+        };
+        let mut steps = Vec::new();
+        let mut frame = 0;
+
+        let mut lines = text.lines().enumerate().map(|(idx, line)| (idx + 1, line));
+        while let Some((number, line)) = lines.next() {
+            let err = |message: String| ScenarioError {
+                file: file.display().to_string(),
+                line: number,
+                message,
+            };
+
+            let line = line.split(';').next().unwrap_or_default().trim();
+            let words: Vec<&str> = line.split_whitespace().collect();
+            let Some((command, args)) = words.split_first() else {
+                continue;
+            };
+
+            let setting = matches!(*command, "quirks" | "seed" | "rng");
+            if setting && !steps.is_empty() {
+                return Err(err(format!("'{}' has to come before the first step", command)));
+            }
+
+            let step = match (*command, args) {
+                ("rom", [path]) => {
+                    if rom.is_some() {
+                        return Err(err("there's already a rom".to_string()));
+                    }
+                    rom = Some(dir.join(path));
+                    continue;
+                }
+                ("quirks", [name]) => {
+                    config.quirks = Quirks::from_name(name)
+                        .ok_or_else(|| err(format!("unknown quirk profile '{}', one of {}", name, Quirks::PRESETS.join(", "))))?;
+                    continue;
+                }
+                ("seed", [seed]) => {
+                    config.seed = Some(seed.parse::<u64>().map_err(|_| err(format!("Could not parse seed '{}'", seed)))?);
+                    continue;
+                }
+                ("rng", [name]) => {
+                    config.rng = RngKind::from_name(name).ok_or_else(|| err(format!("unknown random number generator '{}'", name)))?;
+                    continue;
+                }
+
+                ("press", [key]) => Step::Press(parse_key(key).map_err(err)?),
+                ("release", [key]) => Step::Release(parse_key(key).map_err(err)?),
+                ("wait", [frames]) => {
+                    let frames = parse_number(frames).map_err(err)? as usize;
+                    frame += frames;
+                    Step::Wait(frames)
+                }
+                ("at", [at]) => {
+                    let at = parse_number(at).map_err(err)? as usize;
+                    if at < frame {
+                        return Err(err(format!("frame {} has already gone by, this is frame {}", at, frame)));
+                    }
+                    let frames = at - frame;
+                    frame = at;
+                    Step::Wait(frames)
+                }
+
+                ("expect", ["halted"]) => Step::Expect(Expect::Halted),
+                ("expect", ["state", hash]) => {
+                    let hash = u64::from_str_radix(hash, 16).map_err(|_| err(format!("'{}' isn't a state hash", hash)))?;
+                    Step::Expect(Expect::State(hash))
+                }
+                ("expect", ["mem", addr, bytes @ ..]) if !bytes.is_empty() => {
+                    let addr = u16::try_from(parse_number(addr).map_err(err)?).map_err(|_| err(format!("'{}' is past the end of RAM", addr)))?;
+                    let bytes = bytes
+                        .iter()
+                        .map(|byte| u8::try_from(parse_number(byte)?).map_err(|_| format!("'{}' doesn't fit in a byte", byte)))
+                        .collect::<Result<Vec<u8>, String>>()
+                        .map_err(err)?;
+                    Step::Expect(Expect::Mem(addr, bytes))
+                }
+                ("expect", ["screen", path]) => Step::Expect(Expect::ScreenFile(dir.join(path))),
+                ("expect", ["screen", rest @ ..]) if matches!(rest, [] | ["at", _, _]) => {
+                    let at = match rest {
+                        ["at", x, y] => (parse_number(x).map_err(err)? as usize, parse_number(y).map_err(err)? as usize),
+                        _ => (0, 0),
+                    };
+
+                    let mut rows = String::new();
+                    loop {
+                        match lines.next() {
+                            Some((_, row)) if row.trim() == "end" => break,
+                            Some((_, row)) => {
+                                rows.push_str(row.split(';').next().unwrap_or_default());
+                                rows.push('\n');
+                            }
+                            None => return Err(err("the screen has no 'end'".to_string())),
+                        }
+                    }
+                    Step::Expect(Expect::Screen(Image::from_text(&rows).map_err(err)?, at))
+                }
+                ("expect", [target, op, value]) => {
+                    let target = parse_target(target).map_err(err)?;
+                    let compare = Compare::from_symbol(op).ok_or_else(|| err(format!("'{}' isn't one of == != < <= > >=", op)))?;
+                    let value = u16::try_from(parse_number(value).map_err(err)?).map_err(|_| err(format!("'{}' is too big", value)))?;
+                    Step::Expect(Expect::Reg(target, compare, value))
+                }
+
+                _ => return Err(err(format!("can't make sense of '{}'", line))),
+            };
+
+            steps.push((number, step));
+        }
+
+        let rom = rom.ok_or_else(|| ScenarioError {
+            file: file.display().to_string(),
+            line: 0,
+            message: "no rom to test".to_string(),
+        })?;
+
+        Ok(Self {
+            file: file.to_path_buf(),
+            rom,
+            config,
+            steps,
+        })
+    }
+
+    // Runs the whole thing from power on, stopping early only if the ROM can't load or the Emu hits an error
+    pub fn run(&self) -> Report {
+        self.execute(false)
+    }
+
+    // Runs it the same way but writes every golden instead of checking it: screen files get the screen as it is
+    // and state lines in the scenario get the hash. The other expectations are still checked
+    pub fn bless(&self) -> Report {
+        self.execute(true)
+    }
+
+    fn execute(&self, bless: bool) -> Report {
+        let mut report = Report {
+            frames: 0,
+            failures: Vec::new(),
+            blessed: 0,
+        };
+        let mut hashes = Vec::new(); // state lines for bless to rewrite, by line number
+
+        let mut emu = Emu::with_config(self.config);
+        if let Err(message) = self.load_rom().and_then(|rom| emu.load(&rom).map_err(|err| err.to_string())) {
+            report.failures.push(Failure { line: 0, message });
+            return report;
+        }
+
+        for (line, step) in &self.steps {
+            let checked = match step {
+                Step::Press(key) => {
+                    emu.key_down(*key);
+                    Ok(())
+                }
+                Step::Release(key) => {
+                    emu.key_up(*key);
+                    Ok(())
+                }
+                Step::Wait(frames) => {
+                    let mut ran = Ok(());
+                    for _ in 0..*frames {
+                        ran = emu.run_frame().map_err(|err| format!("stopped at frame {}: {}", report.frames, err));
+                        if ran.is_err() {
+                            break;
+                        }
+                        report.frames += 1;
+                    }
+                    ran
+                }
+                Step::Expect(Expect::State(_)) if bless => {
+                    hashes.push((*line, emu.state_hash()));
+                    Ok(())
+                }
+                Step::Expect(Expect::ScreenFile(path)) if bless => {
+                    report.blessed += 1;
+                    write_screen(path, &emu.get_display())
+                }
+                Step::Expect(expect) => check(&emu, expect),
+            };
+
+            if let Err(message) = checked {
+                let stopped = matches!(step, Step::Wait(_));
+                report.failures.push(Failure { line: *line, message });
+                if stopped {
+                    break;
+                }
+            }
+        }
+
+        if !hashes.is_empty() {
+            report.blessed += hashes.len();
+            if let Err(message) = self.rewrite_hashes(&hashes) {
+                report.failures.push(Failure { line: 0, message });
+            }
+        }
+
+        report
+    }
+
+    // Puts new hashes into the scenario's state lines, keeping their indentation and comments
+    fn rewrite_hashes(&self, hashes: &[(usize, u64)]) -> Result<(), String> {
+        let failed = |err: std::io::Error| format!("could not update '{}': {}", self.file.display(), err);
+        let text = fs::read_to_string(&self.file).map_err(failed)?;
+
+        let mut lines: Vec<String> = text.lines().map(|line| line.to_string()).collect();
+        for (number, hash) in hashes {
+            let Some(line) = lines.get_mut(number - 1) else {
+                continue;
+            };
+            let indent = &line[..(line.len() - line.trim_start().len())];
+            let comment = line.find(';').map_or("", |at| &line[at..]);
+            let gap = if comment.is_empty() { "" } else { " " };
+            *line = format!("{}expect state {:016x}{}{}", indent, hash, gap, comment);
+        }
+
+        let mut updated = lines.join("\n");
+        if text.ends_with('\n') {
+            updated.push('\n');
+        }
+        if updated != text {
+            fs::write(&self.file, updated).map_err(failed)?;
+        }
+        Ok(())
+    }
+
+    fn load_rom(&self) -> Result<Vec<u8>, String> {
+        if self.rom.extension().is_some_and(|ext| ext == "asm") {
+            assemble_file(&self.rom).map_err(|err| err.to_string())
+        } else {
+            fs::read(&self.rom).map_err(|err| format!("could not read ROM '{}': {}", self.rom.display(), err))
+        }
+    }
+}
+
+fn check(emu: &Emu, expect: &Expect) -> Result<(), String> {
+    match expect {
+        Expect::Reg(target, compare, value) => {
+            let (name, actual) = match target {
+                Target::V(reg) => (format!("V{:X}", reg.index()), emu.v_reg()[reg.index()] as u16),
+                Target::I => ("I".to_string(), emu.i_reg()),
+                Target::Pc => ("PC".to_string(), emu.pc()),
+                Target::Sp => ("SP".to_string(), emu.sp()),
+                Target::Dt => ("DT".to_string(), emu.delay_timer() as u16),
+                Target::St => ("ST".to_string(), emu.sound_timer() as u16),
+            };
+
+            match compare.holds(actual, *value) {
+                true => Ok(()),
+                false => Err(format!("{} is {:#04X}, expected {} {:#04X}", name, actual, compare.symbol(), value)),
+            }
+        }
+
+        Expect::Mem(addr, bytes) => {
+            let start = *addr as usize;
+            let actual = emu.ram().get(start..(start + bytes.len())).ok_or(format!("{:#05X} runs past the end of RAM", addr))?;
+
+            match actual == bytes.as_slice() {
+                true => Ok(()),
+                false => Err(format!("RAM at {:#05X} is {}, expected {}", addr, hex(actual), hex(bytes))),
+            }
+        }
+
+        Expect::Halted => match emu.is_halted() {
+            true => Ok(()),
+            false => Err(format!("still running, PC is {:#05X}", emu.pc())),
+        },
+
+        Expect::State(hash) => match emu.state_hash() {
+            actual if actual == *hash => Ok(()),
+            actual => Err(format!("state hash is {:016x}, expected {:016x}", actual, hash)),
+        },
+
+        Expect::Screen(expected, at) => screen_diff(&expected.display(), &emu.get_display(), *at).map_or(Ok(()), Err),
+
+        Expect::ScreenFile(path) => {
+            let actual = emu.get_display();
+            let expected = read_screen(path)?;
+            let expected = expected.downscale(actual.width, actual.height).unwrap_or(expected);
+            if (expected.width, expected.height) != (actual.width, actual.height) {
+                return Err(format!(
+                    "expected a {}x{} screen, it's {}x{}",
+                    expected.width, expected.height, actual.width, actual.height
+                ));
+            }
+            screen_diff(&expected.display(), &actual, (0, 0)).map_or(Ok(()), Err)
+        }
+    }
+}
+
+// A .png at one pixel per pixel, anything else as text
+fn write_screen(path: &Path, display: &Display) -> Result<(), String> {
+    let data = if path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("png")) {
+        to_png(display, &DEFAULT_PALETTE, 1)
+    } else {
+        display.to_text().into_bytes()
+    };
+
+    fs::write(path, data).map_err(|err| format!("could not write screen '{}': {}", path.display(), err))
+}
+
+// .png or text, whichever the file is
+fn read_screen(path: &Path) -> Result<Image, String> {
+    let read = || -> Result<Image, String> {
+        let data = fs::read(path).map_err(|err| err.to_string())?;
+        if path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("png")) {
+            Image::from_png(&data, &DEFAULT_PALETTE)
+        } else {
+            Image::from_text(&String::from_utf8_lossy(&data))
+        }
+    };
+
+    read().map_err(|err| format!("could not read screen '{}': {}", path.display(), err))
+}
+
+// None when expected matches the part of actual with its top left corner at `at`. Otherwise expected, actual and
+// an X under every pixel that differs, cut down to where the differences are and a little around them
+pub fn screen_diff(expected: &Display, actual: &Display, at: (usize, usize)) -> Option<String> {
+    let (left, top) = at;
+    if left + expected.width > actual.width || top + expected.height > actual.height {
+        return Some(format!(
+            "a {}x{} screen at {},{} doesn't fit on the {}x{} one",
+            expected.width, expected.height, left, top, actual.width, actual.height
+        ));
+    }
+
+    // in expected's coordinates from here on, reported in the screen's
+    let actual_pixel = |x: usize, y: usize| actual.pixel(left + x, top + y);
+    let differs = |x: usize, y: usize| expected.pixel(x, y) != actual_pixel(x, y);
+    let points: Vec<(usize, usize)> = (0..expected.height)
+        .flat_map(|y| (0..expected.width).map(move |x| (x, y)))
+        .filter(|(x, y)| differs(*x, *y))
+        .collect();
+    if points.is_empty() {
+        return None;
+    }
+
+    let (min_x, max_x) = (points.iter().map(|p| p.0).min()?, points.iter().map(|p| p.0).max()?);
+    let (min_y, max_y) = (points.iter().map(|p| p.1).min()?, points.iter().map(|p| p.1).max()?);
+    let columns = min_x.saturating_sub(4)..(max_x + 5).min(expected.width);
+    let rows = min_y.saturating_sub(2)..(max_y + 3).min(expected.height);
+
+    let glyph = |pixel: u8| ['.', '#', '+', '@'][pixel as usize & 3];
+    let width = columns.len().max("expected".len());
+    let mut out = String::new();
+
+    writeln!(
+        out,
+        "screen differs in {} pixels, x {}-{} y {}-{}",
+        points.len(),
+        left + min_x,
+        left + max_x,
+        top + min_y,
+        top + max_y
+    )
+    .unwrap();
+    writeln!(out, "  {:>3}  {:<width$}  {:<width$}  diff", "", "expected", "actual", width = width).unwrap();
+    for y in rows {
+        let line = |f: &dyn Fn(usize) -> char| columns.clone().map(f).collect::<String>();
+        write!(out, "  {:>3}  ", top + y).unwrap();
+        write!(out, "{:<width$}  ", line(&|x| glyph(expected.pixel(x, y))), width = width).unwrap();
+        write!(out, "{:<width$}  ", line(&|x| glyph(actual_pixel(x, y))), width = width).unwrap();
+        writeln!(out, "{}", line(&|x| if differs(x, y) { 'X' } else { '.' })).unwrap();
+    }
+
+    out.pop(); // the last newline, the caller decides how it ends
+    Some(out)
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02X}", byte)).collect::<Vec<_>>().join(" ")
+}
+
+fn parse_key(text: &str) -> Result<usize, String> {
+    usize::from_str_radix(text, 16)
+        .ok()
+        .filter(|key| *key < 16)
+        .ok_or(format!("'{}' isn't a key, 0-F", text))
+}
+
+fn parse_target(text: &str) -> Result<Target, String> {
+    match text.to_ascii_lowercase().as_str() {
+        "i" => Ok(Target::I),
+        "pc" => Ok(Target::Pc),
+        "sp" => Ok(Target::Sp),
+        "dt" => Ok(Target::Dt),
+        "st" => Ok(Target::St),
+        name => name
+            .strip_prefix('v')
+            .and_then(|idx| u8::from_str_radix(idx, 16).ok())
+            .and_then(Reg::new)
+            .map(Target::V)
+            .ok_or(format!("'{}' isn't a register, V0-VF, I, PC, SP, DT or ST", text)),
+    }
+}
+
+// 0x10, $10 or plain decimal
+fn parse_number(text: &str) -> Result<u32, String> {
+    let parsed = if let Some(hex) = text.strip_prefix("0x").or_else(|| text.strip_prefix('$')) {
+        u32::from_str_radix(hex, 16)
+    } else {
+        text.parse()
+    };
+
+    parsed.map_err(|_| format!("Could not parse number '{}'", text))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_error(text: &str) -> (usize, String) {
+        let err = Scenario::parse(text, Path::new("test.scenario")).unwrap_err();
+        assert_eq!(err.file, "test.scenario");
+        (err.line, err.message)
+    }
+
+    // A scenario and the ROM it runs, in a directory of their own
+    fn write_scenario(name: &str, text: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("pok8-scenario-{}-{}", std::process::id(), name));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join("dots.asm"),
+            "    SETINNN dots\n    SETVNN V0, 2\n    SETVNN V1, 1\n    DRAW V0, V1, 2\nloop:\n    JMP loop\ndots:\n    sprite \"#.#\", \".#.\"\n",
+        )
+        .unwrap();
+
+        let path = dir.join("test.scenario");
+        fs::write(&path, text).unwrap();
+        path
+    }
+
+    #[test]
+    fn parse_errors_name_their_line() {
+        let cases = [
+            ("rom a\nwait 10\nat 5", 3, "frame 5 has already gone by, this is frame 10"),
+            ("rom a\npress 1\nseed 3", 3, "'seed' has to come before the first step"),
+            ("rom a\nrom b", 2, "there's already a rom"),
+            ("rom a\nquirks nope", 2, "unknown quirk profile 'nope', one of vip, chip48, schip, xochip, modern"),
+            ("rom a\nseed -1", 2, "Could not parse seed '-1'"),
+            ("rom a\npress G", 2, "'G' isn't a key, 0-F"),
+            ("rom a\nwait lots", 2, "Could not parse number 'lots'"),
+            ("rom a\nexpect v0 =< 1", 2, "'=<' isn't one of == != < <= > >="),
+            ("rom a\nexpect vg == 1", 2, "'vg' isn't a register, V0-VF, I, PC, SP, DT or ST"),
+            ("rom a\nexpect pc == 0x10000", 2, "'0x10000' is too big"),
+            ("rom a\nexpect mem 0x200 1 0x100", 2, "'0x100' doesn't fit in a byte"),
+            ("rom a\nexpect state xyz", 2, "'xyz' isn't a state hash"),
+            ("rom a\nexpect screen\n..#\n", 2, "the screen has no 'end'"),
+            ("rom a\nfly away ; somewhere", 2, "can't make sense of 'fly away'"),
+            ("; nothing but\nwait 1", 0, "no rom to test"),
+        ];
+
+        for (text, line, message) in cases {
+            assert_eq!(parse_error(text), (line, message.to_string()), "{:?}", text);
+        }
+    }
+
+    #[test]
+    fn screen_rows_run_to_end_and_later_lines_keep_their_numbers() {
+        let text = "rom a\nexpect screen at 1 2 ; a comment\n.#. ; rows can have them too\n#.#\n  end\nat 0\nat 3\npress Z";
+        assert_eq!(parse_error(text), (8, "'Z' isn't a key, 0-F".to_string()));
+
+        let scenario = Scenario::parse("rom a\nwait 2\nat 2\nat 3\nexpect screen\n#\nend", Path::new("dir/test.scenario")).unwrap();
+        assert_eq!(scenario.rom, Path::new("dir/a"));
+        assert_eq!(scenario.steps.len(), 4);
+    }
+
+    #[test]
+    fn inline_screens_check_the_part_they_cover() {
+        let path = write_scenario(
+            "inline",
+            "rom dots.asm\nwait 1\nexpect screen at 1 0\n.....\n.#.#.\n..#..\nend\nexpect screen at 1 0\n.....\n.#.#.\n.....\nend\n",
+        );
+        let report = Scenario::from_file(&path).unwrap().run();
+
+        assert_eq!(report.frames, 1);
+        assert_eq!(report.failures.len(), 1);
+        assert_eq!(report.failures[0].line, 8);
+        assert!(report.failures[0].message.starts_with("screen differs in 1 pixels, x 3-3 y 2-2\n"), "{}", report.failures[0].message);
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn screen_diff_shows_both_screens_and_marks_the_difference() {
+        let expected = Image::from_text("......\n..#...\n......\n").unwrap();
+        let actual = Image::from_text("......\n..##..\n......\n").unwrap();
+
+        assert_eq!(screen_diff(&expected.display(), &expected.display(), (0, 0)), None);
+        assert_eq!(
+            screen_diff(&expected.display(), &actual.display(), (0, 0)).unwrap(),
+            [
+                "screen differs in 1 pixels, x 3-3 y 1-1",
+                "       expected  actual    diff",
+                "    0  ......    ......    ......",
+                "    1  ..#...    ..##..    ...X..",
+                "    2  ......    ......    ......",
+            ]
+            .join("\n")
+        );
+
+        let small = Image::from_text("##\n##\n").unwrap();
+        assert_eq!(
+            screen_diff(&small.display(), &actual.display(), (5, 0)).unwrap(),
+            "a 2x2 screen at 5,0 doesn't fit on the 6x3 one"
+        );
+    }
+
+    #[test]
+    fn bless_rewrites_state_lines_and_keeps_their_comments() {
+        let path = write_scenario("bless", "; the dots\nrom dots.asm\nwait 1\n  expect state 0 ; golden\nexpect state 1\n");
+        let scenario = Scenario::from_file(&path).unwrap();
+
+        assert_eq!(scenario.run().failures.len(), 2);
+        let report = scenario.bless();
+        assert!(report.passed(), "{:?}", report.failures);
+        assert_eq!(report.blessed, 2);
+
+        let text = fs::read_to_string(&path).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines[..3], ["; the dots", "rom dots.asm", "wait 1"]);
+        assert!(lines[3].starts_with("  expect state ") && lines[3].ends_with(" ; golden"), "{}", lines[3]);
+        assert_eq!(lines[3].trim().trim_end_matches(" ; golden"), lines[4]);
+        assert!(text.ends_with('\n'));
+
+        assert!(Scenario::from_file(&path).unwrap().run().passed());
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+}
@@ -1,37 +1,207 @@
-use playback_rs::{Player, Song};
+#[cfg(feature = "playback")]
+use playback_rs::{Hint, Player, Song};
 
+#[cfg(feature = "playback")]
+use std::error::Error;
+#[cfg(feature = "playback")]
+use std::io::Cursor;
 
+const PATTERN_BITS: usize = 128;
+
+// Emu reports sound through this, so it can run with whatever output (or none) the frontend has
+pub trait AudioSink {
+    // sound timer went from zero to non-zero
+    fn sound_on(&mut self);
+
+    // sound timer ran out or was set to zero
+    fn sound_off(&mut self);
+
+    // XO-CHIP program loaded a new audio pattern or pitch, see pattern_rate for the sample rate.
+    // None is back to the sink's own tone, after a reset or loading a state from before any pattern
+    fn pattern(&mut self, _pattern: Option<&[u8; 16]>, _pitch: u8) {}
+}
+
+// XO-CHIP pattern samples per second for a pitch register value, 4000Hz at the default of 64
+pub fn pattern_rate(pitch: u8) -> f32 {
+    4000.0 * 2f32.powf((pitch as f32 - 64.0) / 48.0)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Waveform {
+    Square,
+    Sine,
+}
+
+impl Waveform {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "square" => Some(Waveform::Square),
+            "sine" => Some(Waveform::Sine),
+            _ => None,
+        }
+    }
+}
+
+// The buzz played while the sound timer is running
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Tone {
+    pub frequency: f32, // Hz
+    pub waveform: Waveform,
+    pub volume: f32, // 0.0 - 1.0
+}
+
+impl Default for Tone {
+    fn default() -> Self {
+        Self {
+            frequency: 440.0,
+            waveform: Waveform::Square,
+            volume: 0.25,
+        }
+    }
+}
+
+// Generates the Tone, or the XO-CHIP pattern once a ROM has loaded one, one sample at a time
+pub struct Synth {
+    tone: Tone,
+    pattern: Option<[u8; 16]>, // 128 1-bit samples
+    pattern_rate: f32,         // pattern samples per second
+    phase: f32,                // 0..1 through the current wave or pattern
+}
+
+impl Synth {
+    pub fn new(tone: Tone) -> Self {
+        Self {
+            tone,
+            pattern: None,
+            pattern_rate: pattern_rate(64),
+            phase: 0.0,
+        }
+    }
+
+    pub fn set_pattern(&mut self, pattern: Option<&[u8; 16]>, pitch: u8) {
+        self.pattern = pattern.copied();
+        self.pattern_rate = pattern_rate(pitch);
+    }
+
+    pub fn next_sample(&mut self, sample_rate: f32) -> f32 {
+        let volume = self.tone.volume.clamp(0.0, 1.0);
+
+        let (sample, step) = match &self.pattern {
+            Some(pattern) => {
+                let bit = (self.phase * PATTERN_BITS as f32) as usize % PATTERN_BITS;
+                let high = pattern[bit / 8] & (0x80 >> (bit % 8)) != 0;
+
+                (if high { 1.0 } else { -1.0 }, self.pattern_rate / PATTERN_BITS as f32)
+            }
+            None => {
+                let sample = match self.tone.waveform {
+                    Waveform::Square => if self.phase < 0.5 { 1.0 } else { -1.0 },
+                    Waveform::Sine => (self.phase * std::f32::consts::TAU).sin(),
+                };
+
+                (sample, self.tone.frequency)
+            }
+        };
+
+        self.phase = (self.phase + step / sample_rate) % 1.0;
+        sample * volume
+    }
+}
+
+// Headless machines, tests and benches
+pub struct NullSink;
+
+impl AudioSink for NullSink {
+    fn sound_on(&mut self) {}
+
+    fn sound_off(&mut self) {}
+}
+
+
+// Longest sound a ROM can ask for in one go, ST = 255 at 60Hz
+#[cfg(feature = "playback")]
+const MAX_SOUND_SECS: f32 = 255.0 / 60.0;
+#[cfg(feature = "playback")]
+const SAMPLE_RATE: u32 = 44100;
+
+#[cfg(feature = "playback")]
 pub struct Buzzer {
 
     player: Player,
-    buzz: Song, 
+    synth: Synth,
 
 }
 
+#[cfg(feature = "playback")]
 impl Buzzer {
 
-    pub fn init() -> Self {
+    pub fn init(tone: Tone) -> Result<Self, Box<dyn Error + Send + Sync>> {
 
-        let ply = Player::new().expect("Failed to open an audio output.");
-        let sng = Song::from_file("./pok-8-core/buzz.wav").expect("Failed to load buzz from file");
+        let ply = Player::new()?;
 
-        Self {
+        Ok(Self {
 
             player: ply,
-            buzz: sng,
+            synth: Synth::new(tone),
 
-         }
+         })
     }
 
-    pub fn play(&self) -> () {
+    // playback-rs only plays decoded files, so the tone goes out as a mono 16-bit WAV
+    fn render(&mut self) -> Result<Song, Box<dyn Error + Send + Sync>> {
+
+        let frames = (MAX_SOUND_SECS * SAMPLE_RATE as f32) as u32;
+        let data_len = frames * 2;
+
+        let mut wav = Vec::with_capacity(44 + data_len as usize);
+        wav.extend_from_slice(b"RIFF");
+        wav.extend_from_slice(&(36 + data_len).to_le_bytes());
+        wav.extend_from_slice(b"WAVEfmt ");
+        wav.extend_from_slice(&16u32.to_le_bytes()); // fmt chunk size
+        wav.extend_from_slice(&1u16.to_le_bytes()); // PCM
+        wav.extend_from_slice(&1u16.to_le_bytes()); // mono
+        wav.extend_from_slice(&SAMPLE_RATE.to_le_bytes());
+        wav.extend_from_slice(&(SAMPLE_RATE * 2).to_le_bytes()); // byte rate
+        wav.extend_from_slice(&2u16.to_le_bytes()); // block align
+        wav.extend_from_slice(&16u16.to_le_bytes()); // bits per sample
+        wav.extend_from_slice(b"data");
+        wav.extend_from_slice(&data_len.to_le_bytes());
+
+        for _ in 0..frames {
+            let sample = self.synth.next_sample(SAMPLE_RATE as f32);
+            wav.extend_from_slice(&((sample * i16::MAX as f32) as i16).to_le_bytes());
+        }
+
+        let mut hint = Hint::new();
+        hint.with_extension("wav");
+
+        Ok(Song::new(Box::new(Cursor::new(wav)), &hint)?)
+    }
+
+}
+
+#[cfg(feature = "playback")]
+impl AudioSink for Buzzer {
+
+    fn sound_on(&mut self) {
 
-        self.player.play_song_next(&self.buzz).expect("Failed to play the buzz.");
+        // a missing buzz isn't worth stopping the game for
+        if let Ok(song) = self.render() {
+            let _ = self.player.play_song_now(&song);
+        }
 
     }
 
+    fn sound_off(&mut self) {
+
+        self.player.stop();
 
+    }
 
+    fn pattern(&mut self, pattern: Option<&[u8; 16]>, pitch: u8) {
 
+        self.synth.set_pattern(pattern, pitch);
 
+    }
 
-}
\ No newline at end of file
+}
@@ -0,0 +1,357 @@
+use crate::emu::{HIRES_HEIGHT, HIRES_WIDTH, SCREEN_HEIGHT, SCREEN_WIDTH};
+use crate::error::*;
+use crate::quirks::*;
+use crate::rng::*;
+
+use std::fmt::Write;
+
+// Binary layout: MAGIC, FORMAT_VERSION (u16), then the fields of SaveState in declaration order,
+// multi-byte values little endian, arrays length prefixed with a u32.
+// Version 1 had no RNG at the end, those states still load with the generator seeded from 0.
+// Versions 1 and 2 stored SP and a fixed 16 entry stack where 3 has just the return addresses in use.
+// Version 4 added pattern_set after the pitch, older states count any pattern or pitch change as set.
+// Version 5 widened the stack_depth quirk from a u16 to a u64, so no depth gets cut short on the way out
+const MAGIC: &[u8; 4] = b"POK8";
+pub const FORMAT_VERSION: u16 = 5;
+
+// Everything needed to put an Emu back exactly where it was
+#[derive(Debug, Clone, PartialEq)]
+pub struct SaveState {
+    pub pc: u16,
+    pub ram: Vec<u8>,
+    pub screen: Vec<u8>, // colour index per pixel, HIRES_WIDTH * HIRES_HEIGHT of them
+    pub hires: bool,
+    pub planes: u8,
+    pub v_reg: [u8; 16],
+    pub i_reg: u16,
+    pub stack: Vec<u16>, // return addresses in use, oldest first
+    pub keys: [bool; 16],
+    pub dt: u8,
+    pub st: u8,
+    pub vblank: bool,
+    pub halted: bool,
+    pub rpl: [u8; 16],
+    pub pattern: [u8; 16],
+    pub pitch: u8,
+    pub pattern_set: bool, // F002 or FX3A has run, the pattern plays instead of the buzzer tone
+    pub quirks: Quirks,
+    pub rng: Rng,
+}
+
+impl SaveState {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(self.ram.len() + self.screen.len() + 128);
+
+        out.extend_from_slice(MAGIC);
+        out.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+
+        out.extend_from_slice(&self.pc.to_le_bytes());
+        write_bytes(&mut out, &self.ram);
+        write_bytes(&mut out, &self.screen);
+        out.push(self.hires as u8);
+        out.push(self.planes);
+        out.extend_from_slice(&self.v_reg);
+        out.extend_from_slice(&self.i_reg.to_le_bytes());
+        out.extend_from_slice(&(self.stack.len() as u32).to_le_bytes());
+        for addr in &self.stack {
+            out.extend_from_slice(&addr.to_le_bytes());
+        }
+        out.extend(self.keys.iter().map(|key| *key as u8));
+        out.push(self.dt);
+        out.push(self.st);
+        out.push(self.vblank as u8);
+        out.push(self.halted as u8);
+        out.extend_from_slice(&self.rpl);
+        out.extend_from_slice(&self.pattern);
+        out.push(self.pitch);
+        out.push(self.pattern_set as u8);
+
+        write_quirks(&mut out, &self.quirks);
+        write_rng(&mut out, &self.rng);
+
+        out
+    }
+
+    pub fn from_bytes(data: &[u8]) -> Result<Self, EmuError> {
+        let mut reader = Reader::new(data, invalid);
+
+        if reader.take(MAGIC.len())? != MAGIC {
+            return Err(invalid("not a POK8 save state"));
+        }
+
+        let version = reader.u16()?;
+        if version == 0 || version > FORMAT_VERSION {
+            return Err(invalid(&format!("unsupported save state version {}", version)));
+        }
+
+        let pc = reader.u16()?;
+        let ram = reader.bytes()?;
+        let screen = reader.bytes()?;
+        let hires = reader.bool()?;
+        let planes = reader.u8()?;
+        let v_reg = reader.array()?;
+        let i_reg = reader.u16()?;
+        let stack = if version >= 3 {
+            let len = reader.u32()?;
+            (0..len).map(|_| reader.u16()).collect::<Result<Vec<_>, _>>()?
+        } else {
+            let sp = reader.u16()? as usize;
+            let mut stack = (0..16).map(|_| reader.u16()).collect::<Result<Vec<_>, _>>()?;
+            if sp > stack.len() {
+                return Err(invalid("stack pointer past the end of the stack"));
+            }
+            stack.truncate(sp);
+            stack
+        };
+        let mut keys = [false; 16];
+        for key in keys.iter_mut() {
+            *key = reader.bool()?;
+        }
+        let dt = reader.u8()?;
+        let st = reader.u8()?;
+        let vblank = reader.bool()?;
+        let halted = reader.bool()?;
+        let rpl = reader.array()?;
+        let pattern = reader.array()?;
+        let pitch = reader.u8()?;
+        let pattern_set = if version >= 4 {
+            reader.bool()?
+        } else {
+            pattern != [0; 16] || pitch != 64
+        };
+
+        let quirks = read_quirks(&mut reader, version >= 5)?;
+        let rng = if version >= 2 {
+            read_rng(&mut reader)?
+        } else {
+            Rng::new(RngKind::Xorshift, 0)
+        };
+
+        reader.finish()?;
+
+        Ok(Self {
+            pc,
+            ram,
+            screen,
+            hires,
+            planes,
+            v_reg,
+            i_reg,
+            stack,
+            keys,
+            dt,
+            st,
+            vblank,
+            halted,
+            rpl,
+            pattern,
+            pitch,
+            pattern_set,
+            quirks,
+            rng,
+        })
+    }
+
+    // Human readable dump, RAM as one hex string and the screen as one string of colour indexes per row
+    pub fn to_json(&self) -> String {
+        let mut json = String::new();
+
+        let (width, height) = if self.hires {
+            (HIRES_WIDTH, HIRES_HEIGHT)
+        } else {
+            (SCREEN_WIDTH, SCREEN_HEIGHT)
+        };
+
+        let hex: String = self.ram.iter().map(|b| format!("{:02x}", b)).collect();
+        let rows: Vec<String> = self
+            .screen
+            .chunks(width)
+            .take(height)
+            .map(|row| {
+                let row: String = row.iter().map(|p| char::from(b'0' + (p & 0b11))).collect();
+                format!("\"{}\"", row)
+            })
+            .collect();
+
+        // write! into a String can't fail
+        let _ = writeln!(json, "{{");
+        let _ = writeln!(json, "  \"version\": {},", FORMAT_VERSION);
+        let _ = writeln!(json, "  \"pc\": {},", self.pc);
+        let _ = writeln!(json, "  \"i_reg\": {},", self.i_reg);
+        let _ = writeln!(json, "  \"v_reg\": {:?},", self.v_reg);
+        let _ = writeln!(json, "  \"sp\": {},", self.stack.len());
+        let _ = writeln!(json, "  \"stack\": {:?},", self.stack);
+        let _ = writeln!(json, "  \"dt\": {},", self.dt);
+        let _ = writeln!(json, "  \"st\": {},", self.st);
+        let _ = writeln!(json, "  \"keys\": {:?},", self.keys);
+        let _ = writeln!(json, "  \"vblank\": {},", self.vblank);
+        let _ = writeln!(json, "  \"halted\": {},", self.halted);
+        let _ = writeln!(json, "  \"hires\": {},", self.hires);
+        let _ = writeln!(json, "  \"planes\": {},", self.planes);
+        let _ = writeln!(json, "  \"rpl\": {:?},", self.rpl);
+        let _ = writeln!(json, "  \"pattern\": {:?},", self.pattern);
+        let _ = writeln!(json, "  \"pitch\": {},", self.pitch);
+        let _ = writeln!(json, "  \"pattern_set\": {},", self.pattern_set);
+        let _ = writeln!(json, "  \"quirks\": {{");
+        let _ = writeln!(json, "    \"shift_uses_vy\": {},", self.quirks.shift_uses_vy);
+        let _ = writeln!(json, "    \"increment_i\": \"{:?}\",", self.quirks.increment_i);
+        let _ = writeln!(json, "    \"jump_uses_vx\": {},", self.quirks.jump_uses_vx);
+        let _ = writeln!(json, "    \"vf_reset\": {},", self.quirks.vf_reset);
+        let _ = writeln!(json, "    \"clip_sprites\": {},", self.quirks.clip_sprites);
+        let _ = writeln!(json, "    \"display_wait\": {},", self.quirks.display_wait);
+        let _ = writeln!(json, "    \"stack_depth\": {}", self.quirks.stack_depth);
+        let _ = writeln!(json, "  }},");
+        let _ = writeln!(json, "  \"rng\": {{");
+        let _ = writeln!(json, "    \"kind\": \"{:?}\",", self.rng.kind());
+        // a string since not every JSON reader keeps all 64 bits of a number
+        let _ = writeln!(json, "    \"state\": \"{:#018x}\"", self.rng.state());
+        let _ = writeln!(json, "  }},");
+        let _ = writeln!(json, "  \"screen\": [\n    {}\n  ],", rows.join(",\n    "));
+        let _ = writeln!(json, "  \"ram\": \"{}\"", hex);
+        let _ = writeln!(json, "}}");
+
+        json
+    }
+}
+
+fn invalid(reason: &str) -> EmuError {
+    EmuError::InvalidSaveState {
+        reason: reason.to_string(),
+    }
+}
+
+// 64-bit FNV-1a, enough to tell ROMs and states apart, not meant to resist anyone
+pub fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
+
+pub(crate) fn write_bytes(out: &mut Vec<u8>, bytes: &[u8]) {
+    out.extend_from_slice(&(bytes.len() as u32).to_le_bytes());
+    out.extend_from_slice(bytes);
+}
+
+pub(crate) fn write_quirks(out: &mut Vec<u8>, quirks: &Quirks) {
+    out.push(quirks.shift_uses_vy as u8);
+    out.push(match quirks.increment_i {
+        IncrementI::Unchanged => 0,
+        IncrementI::ByX => 1,
+        IncrementI::ByXPlusOne => 2,
+    });
+    out.push(quirks.jump_uses_vx as u8);
+    out.push(quirks.vf_reset as u8);
+    out.push(quirks.clip_sprites as u8);
+    out.push(quirks.display_wait as u8);
+    out.extend_from_slice(&(quirks.stack_depth as u64).to_le_bytes());
+}
+
+// wide_depth for formats that store stack_depth as a u64, older ones have a u16
+pub(crate) fn read_quirks(reader: &mut Reader, wide_depth: bool) -> Result<Quirks, EmuError> {
+    Ok(Quirks {
+        shift_uses_vy: reader.bool()?,
+        increment_i: match reader.u8()? {
+            0 => IncrementI::Unchanged,
+            1 => IncrementI::ByX,
+            2 => IncrementI::ByXPlusOne,
+            _ => return Err(reader.invalid("unknown I increment quirk")),
+        },
+        jump_uses_vx: reader.bool()?,
+        vf_reset: reader.bool()?,
+        clip_sprites: reader.bool()?,
+        display_wait: reader.bool()?,
+        stack_depth: if wide_depth {
+            usize::try_from(reader.u64()?).map_err(|_| reader.invalid("stack depth too big for this machine"))?
+        } else {
+            reader.u16()? as usize
+        },
+    })
+}
+
+pub(crate) fn write_rng(out: &mut Vec<u8>, rng: &Rng) {
+    out.push(match rng.kind() {
+        RngKind::Xorshift => 0,
+        RngKind::Counter => 1,
+    });
+    out.extend_from_slice(&rng.state().to_le_bytes());
+}
+
+pub(crate) fn read_rng(reader: &mut Reader) -> Result<Rng, EmuError> {
+    let kind = match reader.u8()? {
+        0 => RngKind::Xorshift,
+        1 => RngKind::Counter,
+        _ => return Err(reader.invalid("unknown random number generator")),
+    };
+
+    let state = reader.u64()?;
+    Rng::from_parts(kind, state).ok_or_else(|| reader.invalid("random number generator state it could never be in"))
+}
+
+// Bounds checked cursor over a save state or movie, invalid builds the error for whichever it is
+pub(crate) struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+    invalid: fn(&str) -> EmuError,
+}
+
+impl<'a> Reader<'a> {
+    pub(crate) fn new(data: &'a [u8], invalid: fn(&str) -> EmuError) -> Self {
+        Self { data, pos: 0, invalid }
+    }
+
+    pub(crate) fn invalid(&self, reason: &str) -> EmuError {
+        (self.invalid)(reason)
+    }
+
+    // everything should have been read by the end
+    pub(crate) fn finish(&self) -> Result<(), EmuError> {
+        if self.pos != self.data.len() {
+            return Err(self.invalid("trailing bytes at the end"));
+        }
+        Ok(())
+    }
+
+    pub(crate) fn take(&mut self, len: usize) -> Result<&'a [u8], EmuError> {
+        let end = self.pos.checked_add(len).filter(|end| *end <= self.data.len());
+        let end = end.ok_or_else(|| self.invalid("data is truncated"))?;
+
+        let bytes = &self.data[self.pos..end];
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    pub(crate) fn u8(&mut self) -> Result<u8, EmuError> {
+        Ok(self.take(1)?[0])
+    }
+
+    pub(crate) fn bool(&mut self) -> Result<bool, EmuError> {
+        Ok(self.u8()? != 0)
+    }
+
+    pub(crate) fn u16(&mut self) -> Result<u16, EmuError> {
+        let bytes = self.take(2)?;
+        Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+    }
+
+    pub(crate) fn u32(&mut self) -> Result<u32, EmuError> {
+        let bytes = self.take(4)?;
+        Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    pub(crate) fn u64(&mut self) -> Result<u64, EmuError> {
+        let mut bytes = [0; 8];
+        bytes.copy_from_slice(self.take(8)?);
+        Ok(u64::from_le_bytes(bytes))
+    }
+
+    pub(crate) fn bytes(&mut self) -> Result<Vec<u8>, EmuError> {
+        let len = self.u32()? as usize;
+        Ok(self.take(len)?.to_vec())
+    }
+
+    pub(crate) fn array<const N: usize>(&mut self) -> Result<[u8; N], EmuError> {
+        let mut array = [0; N];
+        array.copy_from_slice(self.take(N)?);
+        Ok(array)
+    }
+}
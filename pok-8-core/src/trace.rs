@@ -0,0 +1,150 @@
+use crate::emu::*;
+use crate::error::*;
+use crate::instruction::*;
+
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::ops::RangeInclusive;
+use std::path::Path;
+
+// Columns are the state before the instruction runs, with the mnemonic last behind a ; so
+// `cut -d';' -f1` leaves something to diff against another emulator's log
+const HEADER: &str = "# cycle pc opcode v0 v1 v2 v3 v4 v5 v6 v7 v8 v9 va vb vc vd ve vf i sp dt st ; mnemonic";
+
+// What a Tracer writes, Default is every instruction straight to the output
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TraceOptions {
+    pub ranges: Vec<RangeInclusive<u16>>, // only instructions fetched from inside one of these, empty is everywhere
+    pub ring: Option<usize>,              // keep just the last N lines and write them out when the Emu hits an error
+}
+
+impl TraceOptions {
+    // 0x200-0x2FF, $200-$2FF, or a single address
+    pub fn parse_range(text: &str) -> Option<RangeInclusive<u16>> {
+        let parse = |text: &str| match text.strip_prefix("0x").or_else(|| text.strip_prefix('$')) {
+            Some(hex) => u16::from_str_radix(hex, 16).ok(),
+            None => text.parse().ok(),
+        };
+
+        match text.split_once('-') {
+            Some((start, end)) => Some(parse(start)?..=parse(end)?).filter(|range| !range.is_empty()),
+            None => parse(text).map(|addr| addr..=addr),
+        }
+    }
+}
+
+// One line per instruction Emu::cycle runs, set up with Emu::set_tracer
+pub struct Tracer {
+    out: BufWriter<Box<dyn Write>>,
+    options: TraceOptions,
+    ring: VecDeque<String>,
+    cycles: u64,             // instructions fetched since tracing started, filtered out or not
+    error: Option<io::Error>, // the first write that failed, tracing stops there and finish() reports it
+}
+
+impl Tracer {
+    pub fn new(out: impl Write + 'static, options: TraceOptions) -> Self {
+        let mut tracer = Self {
+            out: BufWriter::new(Box::new(out)),
+            options,
+            ring: VecDeque::new(),
+            cycles: 0,
+            error: None,
+        };
+
+        tracer.write(HEADER);
+        tracer
+    }
+
+    pub fn to_file(path: impl AsRef<Path>, options: TraceOptions) -> io::Result<Self> {
+        Ok(Self::new(File::create(path)?, options))
+    }
+
+    pub fn cycles(&self) -> u64 {
+        self.cycles
+    }
+
+    // Writes out whatever the ring is holding, a no-op when every line goes straight out anyway
+    pub fn dump(&mut self) {
+        while let Some(line) = self.ring.pop_front() {
+            self.write(&line);
+        }
+    }
+
+    // Flushes the output, nothing in the ring is written unless dump() was called
+    pub fn finish(mut self) -> io::Result<()> {
+        if let Some(err) = self.error.take() {
+            return Err(err);
+        }
+        self.out.flush()
+    }
+
+    pub(crate) fn record(&mut self, emu: &Emu, addr: u16, opcode: u16, op: Option<Instruction>) {
+        self.cycles += 1;
+
+        let wanted = self.options.ranges.is_empty() || self.options.ranges.iter().any(|range| range.contains(&addr));
+        if !wanted {
+            return;
+        }
+
+        let line = self.line(emu, addr, opcode, op);
+        match self.options.ring {
+            Some(capacity) => {
+                if self.ring.len() == capacity {
+                    self.ring.pop_front();
+                }
+                if capacity > 0 {
+                    self.ring.push_back(line);
+                }
+            }
+            None => self.write(&line),
+        }
+    }
+
+    // The ring goes out first so the error lands right after the instruction that caused it
+    pub(crate) fn fail(&mut self, err: &EmuError) {
+        self.dump();
+        self.write(&format!("# error after cycle {}: {}", self.cycles, err));
+        if let Err(err) = self.out.flush() {
+            self.error.get_or_insert(err);
+        }
+    }
+
+    fn line(&self, emu: &Emu, addr: u16, opcode: u16, op: Option<Instruction>) -> String {
+        let regs: Vec<String> = emu.v_reg().iter().map(|v| format!("{:02X}", v)).collect();
+
+        // LONGI's address is the word after it, which isn't part of the opcode
+        let mnemonic = match op {
+            Some(Instruction::LONGI) => {
+                let ram = emu.ram();
+                let at = |offset: usize| ram.get(addr as usize + offset).copied().unwrap_or(0);
+                format!("LONGI {:#06X}", u16::from_be_bytes([at(2), at(3)]))
+            }
+            Some(op) => op.to_string(),
+            None => "???".to_string(),
+        };
+
+        format!(
+            "{:010} {:04X} {:04X} {} {:04X} {:X} {:02X} {:02X} ; {}",
+            self.cycles,
+            addr,
+            opcode,
+            regs.join(" "),
+            emu.i_reg(),
+            emu.sp(),
+            emu.delay_timer(),
+            emu.sound_timer(),
+            mnemonic
+        )
+    }
+
+    fn write(&mut self, line: &str) {
+        if self.error.is_some() {
+            return;
+        }
+        if let Err(err) = writeln!(self.out, "{}", line) {
+            self.error = Some(err);
+        }
+    }
+}